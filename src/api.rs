@@ -0,0 +1,201 @@
+//! The operations the CLI is built on, for programs that would rather link bob than scrape its
+//! output. Nothing here prints, progress is reported through a callback instead.
+//!
+//! Errors are [`anyhow::Error`]s, the ones bob knows how to classify carry a [`BobError`] that
+//! can be downcast to.
+//!
+//! [`BobError`]: crate::enums::BobError
+
+use crate::enums::InstallResult;
+use crate::models::{Config, InputVersion, InstalledVersion};
+use crate::modules::progress::{self, Event};
+use crate::modules::{
+    install_handler, net, output, reinstall_handler, state, uninstall_handler, use_handler, utils,
+};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Builds the HTTP client every other call expects, honoring the proxy, certificate and
+/// header settings of `config`.
+pub async fn create_client(config: &Config) -> Result<Client> {
+    quietly(net::create_client(config)).await
+}
+
+/// Resolves `nightly`, `stable`, a version like `v0.9.5` or a commit hash. Only `stable` needs
+/// the network, it's looked up on GitHub.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// let client = bob_core::create_client(&config).await?;
+/// let version = bob_core::resolve(&client, "stable").await?;
+/// println!("stable is {}", version.tag_name);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn resolve(client: &Client, version: &str) -> Result<InputVersion> {
+    quietly(utils::parse_version_type(client, version)).await
+}
+
+/// Downloads and extracts `version`, or builds it when it's a commit hash. An installed nightly
+/// is updated when upstream published a newer one, other installed versions are left alone.
+///
+/// `on_progress` gets the same phase/done/total events `--progress-file` writes, only for this
+/// install. Installs running at the same time each report to their own callback.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// let client = bob_core::create_client(&config).await?;
+/// let version = bob_core::resolve(&client, "nightly").await?;
+/// let result = bob_core::install(&client, &config, &version, |event| {
+///     println!("{}: {}/{}", event.phase, event.done, event.total);
+/// })
+/// .await?;
+/// if let bob_core::InstallResult::InstallationSuccess(location) = result {
+///     println!("installed into {location}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn install<F>(
+    client: &Client,
+    config: &Config,
+    version: &InputVersion,
+    on_progress: F,
+) -> Result<InstallResult>
+where
+    F: Fn(&Event) + Send + Sync + 'static,
+{
+    let install = install_handler::start(version, client, config);
+    quietly(progress::with_listener(Arc::new(on_progress), install)).await
+}
+
+/// Makes `version` the one `nvim` runs, installing it first when it's missing.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// let client = bob_core::create_client(&config).await?;
+/// let version = bob_core::resolve(&client, "v0.9.5").await?;
+/// bob_core::use_version(&client, &config, version).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn use_version(client: &Client, config: &Config, version: InputVersion) -> Result<()> {
+    quietly(use_handler::start(version, client, config.clone())).await
+}
+
+/// Installs the used version again and points `nvim` back at it, for when its files got
+/// corrupted. The used version doesn't change, though a used nightly comes back as the latest
+/// nightly. Returns the version that was reinstalled.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// let client = bob_core::create_client(&config).await?;
+/// let version = bob_core::reinstall_current(&client, &config).await?;
+/// println!("reinstalled {}", version.tag_name);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn reinstall_current(client: &Client, config: &Config) -> Result<InputVersion> {
+    quietly(reinstall_handler::reinstall_current(client, config)).await
+}
+
+/// Whether updating would change the build `nvim` runs right now: the used version is nightly
+/// and upstream published a different one, or it's a release, `default_version` is `stable`
+/// and a newer release is out. Commit builds and other releases never change.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// let client = bob_core::create_client(&config).await?;
+/// if bob_core::active_update_pending(&client, &config).await? {
+///     println!("Updating will change your active nvim");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn active_update_pending(client: &Client, config: &Config) -> Result<bool> {
+    quietly(install_handler::is_active_update_pending(client, config)).await
+}
+
+/// Whether any version is installed, cheaper than [`list`] as it stops at the first one found.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// if !bob_core::has_any_installed(&config).await? {
+///     println!("Nothing is installed yet");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn has_any_installed(config: &Config) -> Result<bool> {
+    quietly(utils::has_any_installed(config)).await
+}
+
+/// Every installed version, releases in semver order followed by commit builds and nightly.
+/// Directories left behind by failed installs are skipped.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// for version in bob_core::list(&config).await? {
+///     println!("{}{}", version.name, if version.used { " (used)" } else { "" });
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn list(config: &Config) -> Result<Vec<InstalledVersion>> {
+    quietly(utils::get_installed_versions(config)).await
+}
+
+/// Installed versions that were never the used one and aren't pinned, the ones `bob list
+/// --unused` suggests uninstalling.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let config = bob_core::Config::default();
+/// for name in bob_core::unused_versions(&config).await? {
+///     println!("{name} was never used");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn unused_versions(config: &Config) -> Result<Vec<String>> {
+    quietly(state::unused_versions(config)).await
+}
+
+/// Removes an installed version and returns the directory it was in. The version the installation
+/// folder links to can't be uninstalled, switch to another one first, and neither can a version
+/// pinned with `bob pin`. A `.bob-version` file or `BOB_NVIM_VERSION` doesn't protect a version.
+pub async fn uninstall(config: &Config, version: &InputVersion) -> Result<PathBuf> {
+    quietly(remove(config, version)).await
+}
+
+async fn remove(config: &Config, version: &InputVersion) -> Result<PathBuf> {
+    if utils::is_version_linked(&version.tag_name, config).await {
+        return Err(anyhow!(
+            "{} is the used version, switch to a different one before uninstalling it",
+            version.tag_name
+        ));
+    }
+    if state::is_pinned(config, &version.tag_name).await? {
+        return Err(anyhow!(
+            "{} is pinned, unpin it before uninstalling it",
+            version.tag_name
+        ));
+    }
+    uninstall_handler::remove_version(version, config).await
+}
+
+// Progress bars, printed text and the output of build tools all belong to the program linking
+// bob, they're held back for the call only so the CLI in the same process still prints
+async fn quietly<F: Future>(future: F) -> F::Output {
+    output::silenced(future).await
+}
@@ -1,4 +1,10 @@
 use crate::models::LocalVersion;
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
+use thiserror::Error;
 
 pub enum InstallResult {
     InstallationSuccess(String),
@@ -6,12 +12,277 @@ pub enum InstallResult {
     NightlyIsUpdated,
 }
 
-pub enum VersionType {
-    Standard,
-    Hash,
+// Only what a version's name tells is kept here. Which nightly build is installed comes from its
+// bob.json and is compared with utils::compare_nightlies, and names that aren't versions
+// (neovim-git, directories linked in by hand) fall back to plain name matching in
+// utils::is_same_version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Version {
+    Release(semver::Version),
+    Nightly,
+    Hash(String),
+}
+
+impl Version {
+    // Accepts tags with or without the v prefix, stable isn't a version by itself and has to be
+    // resolved against upstream first
+    pub fn parse(name: &str) -> Option<Version> {
+        let name = name.trim();
+        if name == "nightly" {
+            return Some(Version::Nightly);
+        }
+
+        let release_regex = Regex::new(r"^v?[0-9]+\.[0-9]+\.[0-9]+$").unwrap();
+        if release_regex.is_match(name) {
+            return semver::Version::parse(name.trim_start_matches('v'))
+                .ok()
+                .map(Version::Release);
+        }
+
+        let hash_regex = Regex::new(r"^[0-9a-f]{5,40}$").unwrap();
+        if hash_regex.is_match(name) {
+            return Some(Version::Hash(name.to_string()));
+        }
+        None
+    }
+
+    // Builds from source are stored under an abbreviated hash, so hashes match by prefix
+    pub fn matches(&self, other: &Version) -> bool {
+        match (self, other) {
+            (Version::Hash(first), Version::Hash(second)) => {
+                first.starts_with(second.as_str()) || second.starts_with(first.as_str())
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Version::Release(version) => write!(f, "v{version}"),
+            Version::Nightly => write!(f, "nightly"),
+            Version::Hash(hash) => write!(f, "{hash}"),
+        }
+    }
+}
+
+// Releases sort by semver, builds from source come after them and nightly is always last
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Version::Release(first), Version::Release(second)) => first.cmp(second),
+            (Version::Hash(first), Version::Hash(second)) => first.cmp(second),
+            (Version::Nightly, Version::Nightly) => Ordering::Equal,
+            (Version::Release(_), _) | (Version::Hash(_), Version::Nightly) => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommitStatus {
+    Passing,
+    Pending,
+    Failing,
+    Unknown,
+}
+
+// Ordered by severity so the worst result of a doctor run is just the max
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
 }
 
 pub enum PostDownloadVersionType {
     Standard(LocalVersion),
     Hash,
 }
+
+#[derive(Debug, Error)]
+pub enum BobError {
+    #[error("Please provide a proper version string, got {0}")]
+    InvalidVersion(String),
+    #[error("{}", format_version_not_found(.version, .suggestions))]
+    VersionNotFound {
+        version: String,
+        suggestions: Vec<String>,
+    },
+    #[error("{0} is not installed")]
+    NotInstalled(String),
+    #[error("Cannot reach GitHub, reason: {0}")]
+    Network(String),
+    #[error("Offline mode: cannot reach upstream to {0}")]
+    Offline(String),
+    #[error("{}", format_rate_limit(.reset))]
+    RateLimited { reset: Option<DateTime<Utc>> },
+    #[error("GitHub's secondary rate limit is still in effect, please try again in a few minutes")]
+    SecondaryRateLimited,
+    #[error("GitHub API request to {url} failed with status {status}: {message}")]
+    Api {
+        url: String,
+        status: u16,
+        message: String,
+    },
+    #[error("Failed to download {version}, reason: {reason}")]
+    DownloadFailed { version: String, reason: String },
+    #[error("Failed to expand {archive}, reason: {reason}")]
+    ExtractionFailed { archive: String, reason: String },
+    #[error("{0}")]
+    Usage(String),
+    #[error("Updates are available for {}", .0.join(", "))]
+    UpdatesAvailable(Vec<String>),
+}
+
+impl BobError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BobError::InvalidVersion(_) => "invalid_version",
+            BobError::VersionNotFound { .. } => "version_not_found",
+            BobError::NotInstalled(_) => "not_installed",
+            BobError::Network(_) => "network",
+            BobError::Offline(_) => "offline",
+            BobError::RateLimited { .. } | BobError::SecondaryRateLimited => "rate_limited",
+            BobError::Api { .. } => "api",
+            BobError::DownloadFailed { .. } => "download_failed",
+            BobError::ExtractionFailed { .. } => "extraction_failed",
+            BobError::Usage(_) => "usage",
+            BobError::UpdatesAvailable(_) => "updates_available",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BobError::InvalidVersion(_) | BobError::Usage(_) => 2,
+            BobError::VersionNotFound { .. } => 4,
+            BobError::NotInstalled(_) => 5,
+            BobError::Network(_)
+            | BobError::Offline(_)
+            | BobError::Api { .. }
+            | BobError::DownloadFailed { .. } => 6,
+            BobError::RateLimited { .. } | BobError::SecondaryRateLimited => 7,
+            BobError::UpdatesAvailable(_) => 8,
+            BobError::ExtractionFailed { .. } => 9,
+        }
+    }
+}
+
+// Only the outermost error is looked at, an io error buried in a network failure's sources
+// shouldn't turn it into a filesystem one
+pub fn get_error_kind(error: &anyhow::Error) -> &'static str {
+    if let Some(error) = error.downcast_ref::<BobError>() {
+        return error.kind();
+    }
+    if error.is::<std::io::Error>() {
+        return "filesystem";
+    }
+    if error.is::<reqwest::Error>() {
+        return "network";
+    }
+    "other"
+}
+
+pub fn get_exit_code(error: &anyhow::Error) -> i32 {
+    if let Some(error) = error.downcast_ref::<BobError>() {
+        return error.exit_code();
+    }
+    if error.is::<std::io::Error>() {
+        return 10;
+    }
+    // Requests that fail outright are passed up as is, they never reach GitHub at all
+    if error.is::<reqwest::Error>() {
+        return 6;
+    }
+    1
+}
+
+fn format_version_not_found(version: &str, suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return format!(
+            "Version {version} not found upstream, please provide an existing neovim version"
+        );
+    }
+    format!(
+        "Version {version} not found upstream, did you mean {}?",
+        suggestions.join(", ")
+    )
+}
+
+fn format_rate_limit(reset: &Option<DateTime<Utc>>) -> String {
+    match reset {
+        Some(reset) => format!(
+            "GitHub API rate limit exceeded, it resets at {}",
+            reset.with_timezone(&Local).format("%H:%M:%S")
+        ),
+        None => String::from("GitHub API rate limit exceeded"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_by_kind() {
+        let release = Some(Version::Release(semver::Version::new(0, 9, 5)));
+        assert_eq!(Version::parse("v0.9.5"), release);
+        assert_eq!(Version::parse("0.9.5"), release);
+        assert_eq!(Version::parse(" v0.9.5\n"), release);
+        assert_eq!(Version::parse("nightly"), Some(Version::Nightly));
+        assert_eq!(
+            Version::parse("1a2b3c4"),
+            Some(Version::Hash(String::from("1a2b3c4")))
+        );
+    }
+
+    #[test]
+    fn other_names_arent_versions() {
+        for name in [
+            "stable",
+            "v0.9",
+            "v0.9.5-dev",
+            "1a2b",
+            "neovim-git",
+            "state.json",
+            "",
+        ] {
+            assert_eq!(Version::parse(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn hashes_match_by_prefix_and_other_kinds_exactly() {
+        let version = |name: &str| Version::parse(name).unwrap();
+        for (first, second, expected) in [
+            ("v0.9.5", "0.9.5", true),
+            ("v0.9.5", "v0.9.4", false),
+            ("nightly", "nightly", true),
+            ("nightly", "v0.9.5", false),
+            ("1a2b3c4", "1a2b3c4d5e6f", true),
+            ("1a2b3c4d5e6f", "1a2b3c4", true),
+            ("1a2b3c4", "1a2b3c5", false),
+            ("1a2b3c4", "nightly", false),
+            ("123456", "v0.9.5", false),
+        ] {
+            assert_eq!(
+                version(first).matches(&version(second)),
+                expected,
+                "{first} {second}"
+            );
+            assert_eq!(
+                version(second).matches(&version(first)),
+                expected,
+                "{second} {first}"
+            );
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! The library behind the `bob` neovim version manager, the `bob` binary is a thin CLI on top
+//! of it. Start with [`create_client`], then [`resolve`] a version and [`install`] or
+//! [`use_version`] it.
+
+pub mod api;
+pub mod enums;
+pub mod models;
+pub(crate) mod modules;
+
+pub use api::{
+    active_update_pending, create_client, has_any_installed, install, list, reinstall_current,
+    resolve, uninstall, unused_versions, use_version,
+};
+pub use enums::{BobError, InstallResult, Version};
+pub use models::{Config, InputVersion, InstalledVersion};
+pub use modules::progress::Event;
+
+// The bob binary is nothing but this, it isn't part of the library's API
+#[doc(hidden)]
+pub use modules::cli::run as run_cli;
@@ -1,43 +1,523 @@
-use super::enums::VersionType;
+use super::enums::{CheckStatus, Version};
+use crate::modules::{downloader, net, use_handler, utils};
+use anyhow::{anyhow, Result};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+// The result printed on stdout when running with --json, details holds whatever is specific to
+// the command
+#[derive(Serialize, Debug, Default)]
+pub struct CommandOutput {
+    pub action: String,
+    pub versions: Vec<String>,
+    pub paths: Vec<PathBuf>,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub details: serde_json::Value,
+    // Lets a command report its result and still fail, like doctor finding problems
+    #[serde(skip)]
+    pub exit_code: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remedy: Option<String>,
+}
+
+// Written next to a version whose binary was swapped for a self-built one by install --binary
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub binary: PathBuf,
+    pub binary_version: String,
+    pub runtime: String,
+    pub assembled_at: DateTime<Utc>,
+}
+
+// Recorded once a version is in place so bob verify can tell what changed since, files map their
+// path relative to the version directory to a SHA-256 or, for symlinks, to where they point
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub recorded_at: DateTime<Utc>,
+    pub files: BTreeMap<String, String>,
+}
+
+// Hashes bob already computed for a version's files, reused by bob verify for as long as a file
+// keeps the size and modification time it had when it was hashed
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct HashCache {
+    pub files: BTreeMap<String, CachedHash>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedHash {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstalledVersion {
+    pub name: String,
+    pub version: Option<Version>,
+    pub path: PathBuf,
+    pub used: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResolvedSetting {
+    pub name: String,
+    pub value: serde_json::Value,
+    pub source: String,
+}
+
+// Everything but the tag has a default so bob.json files written by older versions still parse
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct UpstreamVersion {
     pub tag_name: String,
+    #[serde(default)]
     pub published_at: String,
+    #[serde(default)]
+    pub target_commitish: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReleaseAsset {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone)]
 pub struct LocalVersion {
     pub file_name: String,
     pub file_format: String,
-    pub path: String,
+    pub path: PathBuf,
 }
 
+// Everything past the message is optional so a trimmed down response (mirrors, older API
+// versions) still deserializes
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RepoCommit {
+    #[serde(default)]
+    pub sha: Option<String>,
+    #[serde(default)]
+    pub html_url: Option<String>,
     pub commit: Commit,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Commit {
+    #[serde(default)]
     pub author: CommitAuthor,
     pub message: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CommitAuthor {
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
+    pub date: Option<DateTime<Utc>>,
 }
 
+// commits runs from the oldest commit after base up to head, total_commits counts all of
+// them even when the page holds fewer
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Comparison {
+    #[serde(default)]
+    pub total_commits: usize,
+    #[serde(default)]
+    pub commits: Vec<RepoCommit>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CombinedStatus {
+    pub state: String,
+    #[serde(default)]
+    pub total_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckRuns {
+    #[serde(default)]
+    pub check_runs: Vec<CheckRun>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckRun {
+    pub status: String,
+    #[serde(default)]
+    pub conclusion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Config {
     pub enable_nightly_info: Option<bool>,
     pub downloads_dir: Option<String>,
     pub installation_location: Option<String>,
+    pub default_version: Option<String>,
+    pub ca_cert_path: Option<String>,
+    pub danger_accept_invalid_certs: Option<bool>,
+    pub proxy: Option<String>,
+    pub preflight_check: Option<bool>,
+    pub low_priority: Option<bool>,
+    pub progress_file: Option<String>,
+    pub log_file: Option<String>,
+    pub prefer_appimage: Option<bool>,
+    pub extra_headers: Option<HashMap<String, String>>,
+    pub state_dir: Option<String>,
+    pub require_passing_nightly: Option<bool>,
+    pub use_gh_auth: Option<bool>,
+    pub archive_strip_prefix: Option<String>,
+    pub local_mirror_dir: Option<String>,
+    pub github_api_url: Option<String>,
+    pub github_download_url: Option<String>,
+    pub github_token: Option<String>,
+    pub install_desktop_entry: Option<bool>,
+    pub binary_mode: Option<String>,
+    pub gui: Option<bool>,
+    pub preserve_nightly_metadata: Option<bool>,
+    pub notify: Option<bool>,
+    pub staging_dir: Option<String>,
+    pub downloader: Option<String>,
+    pub offline: Option<bool>,
+    pub per_arch_dirs: Option<bool>,
+    pub use_strategy: Option<String>,
+    pub profiles: Option<BTreeMap<String, Config>>,
+    // Not part of the file, set once a profile was merged in so its settings can be told apart
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+}
+
+impl Config {
+    // Every setting the profile sets replaces the base one, whatever it leaves unset keeps the
+    // value from the base config and falls back to the default from there
+    pub fn with_profile(self, name: &str) -> Result<Config> {
+        let profiles = self.profiles.clone().unwrap_or_default();
+        let profile = match profiles.get(name) {
+            Some(value) => value,
+            None if profiles.is_empty() => {
+                return Err(anyhow!(
+                    "Unknown profile {name}, the config doesn't define any"
+                ))
+            }
+            None => {
+                let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                return Err(anyhow!(
+                    "Unknown profile {name}, the config defines {}",
+                    names.join(", ")
+                ));
+            }
+        };
+        if profile.profiles.is_some() {
+            return Err(anyhow!("Profile {name} can't define profiles of its own"));
+        }
+
+        let mut merged = serde_json::to_value(&self)?;
+        if let (Some(base), serde_json::Value::Object(overrides)) =
+            (merged.as_object_mut(), serde_json::to_value(profile)?)
+        {
+            for (key, value) in overrides {
+                if !value.is_null() {
+                    base.insert(key, value);
+                }
+            }
+        }
+        let mut config: Config = serde_json::from_value(merged)?;
+        config.active_profile = Some(name.to_owned());
+        Ok(config)
+    }
+
+    // Octal like chmod takes it, with or without a leading 0 or 0o
+    pub fn get_binary_mode(&self) -> Result<u32> {
+        const DEFAULT_BINARY_MODE: u32 = 0o755;
+
+        let mode = match &self.binary_mode {
+            Some(value) => value,
+            None => return Ok(DEFAULT_BINARY_MODE),
+        };
+        let digits = mode.trim().trim_start_matches("0o");
+        match u32::from_str_radix(digits, 8) {
+            Ok(value) if !digits.is_empty() && value <= 0o7777 => Ok(value),
+            _ => Err(anyhow!(
+                "binary_mode ({mode}) has to be an octal mode like 755 or 0750"
+            )),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let downloads_dir = normalize_path(&utils::get_downloads_location(self)?);
+        // With the symlink strategy the installation folder links into downloads_dir once a
+        // version is used, so only its parent is resolved
+        let installation_dir = utils::get_installation_folder(self)?;
+        let installation_dir = match (installation_dir.parent(), installation_dir.file_name()) {
+            (Some(parent), Some(name)) => normalize_path(parent).join(name),
+            _ => normalize_path(&installation_dir),
+        };
+
+        // Removing one of them would otherwise wipe the other along with it
+        if downloads_dir.starts_with(&installation_dir)
+            || installation_dir.starts_with(&downloads_dir)
+        {
+            return Err(anyhow!(
+                "downloads_dir ({}) and installation_location ({}) can't be nested in one another",
+                downloads_dir.display(),
+                installation_dir.display()
+            ));
+        }
+
+        if let Some(prefix) = &self.archive_strip_prefix {
+            let is_relative = Path::new(prefix)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if prefix.is_empty() || !is_relative {
+                return Err(anyhow!(
+                    "archive_strip_prefix ({prefix}) has to be a relative path inside the archive"
+                ));
+            }
+        }
+
+        self.get_binary_mode()?;
+        if let Some(downloader) = &self.downloader {
+            if !downloader::DOWNLOADERS.contains(&downloader.as_str()) {
+                return Err(anyhow!(
+                    "downloader ({downloader}) has to be one of {}",
+                    downloader::DOWNLOADERS.join(", ")
+                ));
+            }
+        }
+        if let Some(strategy) = &self.use_strategy {
+            if !use_handler::USE_STRATEGIES.contains(&strategy.as_str()) {
+                return Err(anyhow!(
+                    "use_strategy ({strategy}) has to be one of {}",
+                    use_handler::USE_STRATEGIES.join(", ")
+                ));
+            }
+        }
+        if let Some(url) = &self.github_api_url {
+            net::validate_endpoint("github_api_url", url)?;
+        }
+        if let Some(url) = &self.github_download_url {
+            net::validate_endpoint("github_download_url", url)?;
+        }
+        Ok(())
+    }
+}
+
+// Resolves symlinks for the part of the path that exists, the rest is cleaned up lexically
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+
+    let mut normalized = std::fs::canonicalize(existing).unwrap_or_else(|_| existing.to_path_buf());
+    for component in rest.iter().rev() {
+        normalized.push(component);
+    }
+
+    let mut cleaned = PathBuf::new();
+    for component in normalized.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                cleaned.pop();
+            }
+            other => cleaned.push(other),
+        }
+    }
+    cleaned
 }
 
 pub struct InputVersion {
     pub tag_name: String,
-    pub version_type: VersionType,
+    pub version: Version,
+}
+
+// Tags are compared by the version they parse to, so 0.9.5 and v0.9.5 are the same and a
+// resolved stable equals its release. Builds from source match by prefix like Version::matches
+impl PartialEq for InputVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.version.matches(&other.version)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    pub schema_version: u32,
+    pub used: Option<String>,
+    pub previous: Option<String>,
+    pub install_mode: Option<String>,
+    #[serde(default)]
+    pub versions: BTreeMap<String, VersionState>,
+}
+
+// Every commit before lower is known good and the one at upper is known bad, the first bad
+// commit lies between them until the two meet
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BisectState {
+    pub good: String,
+    pub bad: String,
+    pub cmd: Option<String>,
+    pub commits: Vec<BisectCommit>,
+    pub lower: usize,
+    pub upper: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BisectCommit {
+    pub sha: String,
+    pub summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VersionState {
+    pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+
+    #[test]
+    fn github_releases_deserialize() {
+        let release: UpstreamVersion =
+            serde_json::from_str(include_str!("../../tests/fixtures/release.json")).unwrap();
+        assert_eq!(release.tag_name, "v0.9.5");
+        assert_eq!(release.published_at, "2023-12-30T13:33:39Z");
+        assert_eq!(release.target_commitish.as_deref(), Some("master"));
+        assert!(!release.prerelease);
+        assert!(release.body.unwrap().starts_with("## Nvim 0.9.5"));
+
+        let names = release
+            .assets
+            .iter()
+            .map(|asset| asset.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["nvim-linux64.tar.gz", "nvim-win64.zip"]);
+        let asset = &release.assets[0];
+        assert_eq!(asset.size, 10297545);
+        assert_eq!(
+            asset.browser_download_url,
+            "https://github.com/neovim/neovim/releases/download/v0.9.5/nvim-linux64.tar.gz"
+        );
+        assert_eq!(
+            asset.updated_at.unwrap().to_rfc3339(),
+            "2023-12-30T13:34:12+00:00"
+        );
+    }
+
+    #[test]
+    fn old_nightly_metadata_still_deserializes() {
+        let nightly: UpstreamVersion = serde_json::from_str(r#"{"tag_name": "nightly"}"#).unwrap();
+        assert_eq!(nightly.tag_name, "nightly");
+        assert!(nightly.published_at.is_empty());
+        assert!(nightly.target_commitish.is_none() && nightly.body.is_none());
+        assert!(nightly.assets.is_empty());
+    }
+
+    #[test]
+    fn input_versions_compare_by_parsed_version() {
+        let input = |tag: &str| InputVersion {
+            tag_name: tag.to_string(),
+            version: Version::parse(tag).unwrap(),
+        };
+        // Tags in the same group name the same version, a resolved stable keeps the tag of its
+        // release so it falls in the v0.9.5 group
+        let groups = [
+            vec!["v0.9.5", "0.9.5"],
+            vec!["v0.9.4"],
+            vec!["nightly"],
+            vec!["1a2b3c4", "1a2b3c4d5e"],
+        ];
+        for (first_group, first_tags) in groups.iter().enumerate() {
+            for (second_group, second_tags) in groups.iter().enumerate() {
+                for (first, second) in first_tags
+                    .iter()
+                    .flat_map(|first| second_tags.iter().map(move |second| (first, second)))
+                {
+                    assert_eq!(
+                        input(first) == input(second),
+                        first_group == second_group,
+                        "{first} {second}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn config_with(downloads_dir: &Path, installation_dir: &Path) -> Config {
+        Config {
+            downloads_dir: Some(downloads_dir.to_string_lossy().into_owned()),
+            installation_location: Some(installation_dir.to_string_lossy().into_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nested_folders_are_rejected() {
+        let dir = TestDir::new("models-nested");
+        let root = dir.path();
+        for (downloads_dir, installation_dir) in [
+            (root.join("bob"), root.join("bob").join("nvim-bin")),
+            (root.join("nvim-bin").join("bob"), root.join("nvim-bin")),
+            (root.join("bob"), root.join("other").join("..").join("bob")),
+            (root.join("bob"), root.join("bob")),
+        ] {
+            let error = config_with(&downloads_dir, &installation_dir)
+                .validate()
+                .unwrap_err();
+            assert!(
+                error.to_string().contains("can't be nested"),
+                "{} and {}",
+                downloads_dir.display(),
+                installation_dir.display()
+            );
+        }
+    }
+
+    #[test]
+    fn sibling_folders_are_accepted() {
+        let dir = TestDir::new("models-sibling");
+        let root = dir.path();
+        for (downloads_dir, installation_dir) in [
+            (root.join("bob"), root.join("nvim-bin")),
+            (root.join("bob"), root.join("bob-nvim")),
+            (
+                root.join("share").join("bob"),
+                root.join("bin").join("nvim"),
+            ),
+        ] {
+            assert!(config_with(&downloads_dir, &installation_dir)
+                .validate()
+                .is_ok());
+        }
+    }
 }
@@ -0,0 +1,227 @@
+use super::{install_handler, output, utils};
+use crate::enums::BobError;
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::{fs, process::Command, time};
+use tracing::{info, warn};
+
+// A run that takes longer than this is stuck on something, not slow to start
+const RUN_TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Timings {
+    version: String,
+    min: f64,
+    median: f64,
+    max: f64,
+}
+
+// Every version is started runs times with --startuptime, the total it reports is the time
+// until NVIM STARTED. The first version benchmarked is the baseline the others are compared to
+pub async fn start(
+    client: &Client,
+    config: Config,
+    versions: Vec<String>,
+    runs: u32,
+    args: Option<String>,
+    install: bool,
+) -> Result<CommandOutput> {
+    if runs == 0 {
+        return Err(BobError::Usage(String::from("--runs has to be at least 1")).into());
+    }
+    let args: Vec<String> = args
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect();
+
+    let mut binaries = Vec::new();
+    let mut skipped = Vec::new();
+    for version in utils::resolve_all(client, &versions).await {
+        let version = version?;
+        match get_binary(client, &config, &version, install).await? {
+            Some(binary) => binaries.push((version.tag_name, binary)),
+            None => {
+                warn!(
+                    "Skipping {}, it isn't installed. Pass --install to install it first",
+                    version.tag_name
+                );
+                skipped.push(version.tag_name);
+            }
+        }
+    }
+
+    let log_file = std::env::temp_dir().join(format!("bob-startuptime-{}.log", std::process::id()));
+    let mut results = Vec::new();
+    for (version, binary) in binaries {
+        info!("Starting {version} {runs} times");
+        let mut totals = Vec::new();
+        for _ in 0..runs {
+            totals.push(measure(&binary, &args, &log_file).await?);
+        }
+        totals.sort_by(f64::total_cmp);
+        results.push(Timings {
+            version,
+            min: totals[0],
+            median: get_median(&totals),
+            max: totals[totals.len() - 1],
+        });
+    }
+    if fs::metadata(&log_file).await.is_ok() {
+        fs::remove_file(&log_file).await?;
+    }
+
+    print_table(&results);
+
+    let baseline = results.first().map(|timings| timings.median);
+    Ok(CommandOutput {
+        action: String::from("benchmark"),
+        versions: results
+            .iter()
+            .map(|timings| timings.version.clone())
+            .collect(),
+        details: json!({
+            "runs": runs,
+            "args": args,
+            "results": results
+                .iter()
+                .map(|timings| json!({
+                    "version": timings.version,
+                    "min_ms": timings.min,
+                    "median_ms": timings.median,
+                    "max_ms": timings.max,
+                    "delta_ms": baseline.map(|baseline| timings.median - baseline),
+                }))
+                .collect::<Vec<_>>(),
+            "skipped": skipped,
+        }),
+        ..Default::default()
+    })
+}
+
+async fn get_binary(
+    client: &Client,
+    config: &Config,
+    version: &InputVersion,
+    install: bool,
+) -> Result<Option<PathBuf>> {
+    if !utils::is_version_installed(&version.tag_name, config).await? {
+        if !install {
+            return Ok(None);
+        }
+        install_handler::start(version, client, config).await?;
+    }
+
+    let version_dir = match utils::find_version_dir(&version.tag_name, config).await? {
+        Some(value) => value,
+        None => return Err(BobError::NotInstalled(version.tag_name.clone()).into()),
+    };
+    Ok(Some(
+        version_dir
+            .join(utils::get_platform_name())
+            .join("bin")
+            .join(utils::get_binary_name()),
+    ))
+}
+
+// --startuptime appends to the file, it's removed first so the last NVIM STARTED is this run's
+async fn measure(binary: &Path, args: &[String], log_file: &Path) -> Result<f64> {
+    if fs::metadata(log_file).await.is_ok() {
+        fs::remove_file(log_file).await?;
+    }
+
+    let child = Command::new(binary)
+        .arg("--headless")
+        .arg("--startuptime")
+        .arg(log_file)
+        .args(args)
+        .arg("+qa!")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    match time::timeout(RUN_TIMEOUT, child).await {
+        Ok(Ok(_)) => (),
+        Ok(Err(error)) => {
+            return Err(anyhow!(
+                "Failed to run {}, reason: {error}",
+                binary.display()
+            ))
+        }
+        Err(_) => {
+            return Err(anyhow!(
+                "{} didn't exit within {}s",
+                binary.display(),
+                RUN_TIMEOUT.as_secs()
+            ))
+        }
+    }
+
+    let content = match fs::read_to_string(log_file).await {
+        Ok(value) => value,
+        Err(error) => {
+            return Err(anyhow!(
+                "{} didn't write a startup time log, reason: {error}",
+                binary.display()
+            ))
+        }
+    };
+    content
+        .lines()
+        .rev()
+        .find(|line| line.contains("NVIM STARTED"))
+        .and_then(|line| line.split_whitespace().next())
+        .and_then(|total| total.parse().ok())
+        .ok_or_else(|| anyhow!("Couldn't find the total startup time in the log"))
+}
+
+fn get_median(sorted: &[f64]) -> f64 {
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+fn print_table(results: &[Timings]) {
+    let width = results
+        .iter()
+        .map(|timings| timings.version.len())
+        .chain(std::iter::once("Version".len()))
+        .max()
+        .unwrap_or_default();
+    output::print_human(format!(
+        "{:<width$} | {:>9} | {:>9} | {:>9} | Delta",
+        "Version", "Min", "Median", "Max"
+    ));
+    output::print_human(format!(
+        "{}+{}+{}+{}+{}",
+        "-".repeat(width + 1),
+        "-".repeat(11),
+        "-".repeat(11),
+        "-".repeat(11),
+        "-".repeat(16)
+    ));
+
+    let baseline = results.first().map(|timings| timings.median);
+    for timings in results {
+        let delta = match baseline {
+            Some(baseline) if baseline > 0.0 && timings.median != baseline => {
+                let delta = timings.median - baseline;
+                format!("{delta:+.1}ms ({:+.0}%)", delta / baseline * 100.0)
+            }
+            _ => String::from("baseline"),
+        };
+        output::print_human(format!(
+            "{:<width$} | {:>7.1}ms | {:>7.1}ms | {:>7.1}ms | {delta}",
+            timings.version, timings.min, timings.median, timings.max
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use super::{install_handler, net, output, progress, prompt, state, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{BisectCommit, BisectState, CommandOutput, Comparison, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tokio::{fs, process::Command};
+use tracing::{debug, info, warn};
+
+pub const BISECT_FILE: &str = "bisect.json";
+
+// Each step installs the commit halfway between the last good and first bad one, built from
+// source like any other hash so a commit tested before is reused. The state is saved after
+// every answer, running bob bisect without --good and --bad picks up where it stopped
+pub async fn start(
+    client: &Client,
+    config: Config,
+    good: Option<String>,
+    bad: Option<String>,
+    cmd: Option<String>,
+    reset: bool,
+) -> Result<CommandOutput> {
+    let bisect_file = state::get_state_dir(&config).await?.join(BISECT_FILE);
+    if reset {
+        if fs::metadata(&bisect_file).await.is_ok() {
+            fs::remove_file(&bisect_file).await?;
+            info!("Dropped the saved bisect");
+        } else {
+            info!("No bisect is in progress");
+        }
+        return Ok(CommandOutput {
+            action: String::from("bisect"),
+            paths: vec![bisect_file],
+            ..Default::default()
+        });
+    }
+
+    let mut bisect = match (good, bad) {
+        (Some(good), Some(bad)) => {
+            if fs::metadata(&bisect_file).await.is_ok() {
+                warn!("Replacing the bisect that was in progress");
+            }
+            create(client, &good, &bad).await?
+        }
+        (None, None) => match load(&bisect_file).await? {
+            Some(value) => value,
+            None => {
+                return Err(BobError::Usage(String::from(
+                    "No bisect is in progress, start one with --good and --bad",
+                ))
+                .into())
+            }
+        },
+        _ => {
+            return Err(
+                BobError::Usage(String::from("--good and --bad have to be given together")).into(),
+            )
+        }
+    };
+    if cmd.is_some() {
+        bisect.cmd = cmd;
+    }
+    save(&bisect_file, &bisect).await?;
+
+    // The command runs where bob was started
+    let working_dir = std::env::current_dir()?;
+    let mut tested = 0;
+    while bisect.lower < bisect.upper {
+        let middle = (bisect.lower + bisect.upper) / 2;
+        let commit = bisect.commits[middle].clone();
+        info!(
+            "Testing {} \"{}\", {} commits left to check, about {} more steps",
+            short_sha(&commit.sha),
+            commit.summary,
+            bisect.upper - bisect.lower,
+            get_remaining_steps(bisect.upper - bisect.lower)
+        );
+
+        let binary = install_commit(client, &config, &commit.sha).await?;
+        let is_good = match &bisect.cmd {
+            Some(cmd) => run_check(cmd, &binary, &working_dir).await?,
+            None => {
+                output::print_human(format!("Try it with {}", binary.display()));
+                prompt::ask_good_or_bad(&format!("Is {} good?", short_sha(&commit.sha)))?
+            }
+        };
+        info!(
+            "{} is {}",
+            short_sha(&commit.sha),
+            if is_good { "good" } else { "bad" }
+        );
+
+        if is_good {
+            bisect.lower = middle + 1;
+        } else {
+            bisect.upper = middle;
+        }
+        tested += 1;
+        save(&bisect_file, &bisect).await?;
+    }
+
+    let first_bad = &bisect.commits[bisect.upper];
+    let url = format!("{}/commit/{}", net::repository_url(), first_bad.sha);
+    output::print_human(format!(
+        "{} is the first bad commit: {}\n{url}",
+        first_bad.sha, first_bad.summary
+    ));
+    fs::remove_file(&bisect_file).await?;
+
+    Ok(CommandOutput {
+        action: String::from("bisect"),
+        versions: vec![first_bad.sha.clone()],
+        details: json!({
+            "good": bisect.good,
+            "bad": bisect.bad,
+            "first_bad": first_bad,
+            "url": url,
+            "tested": tested,
+        }),
+        ..Default::default()
+    })
+}
+
+async fn create(client: &Client, good: &str, bad: &str) -> Result<BisectState> {
+    let good = utils::parse_version_type(client, good).await?.tag_name;
+    let bad = utils::parse_version_type(client, bad).await?.tag_name;
+    let commits = get_commits_between(client, &good, &bad).await?;
+    if commits.is_empty() {
+        return Err(BobError::Usage(format!(
+            "{bad} doesn't come after {good}, there's nothing to bisect"
+        ))
+        .into());
+    }
+    info!(
+        "Bisecting {} commits between {good} and {bad}",
+        commits.len()
+    );
+
+    // The bad end is known bad already, only the commits before it need testing
+    let upper = commits.len() - 1;
+    Ok(BisectState {
+        good,
+        bad,
+        cmd: None,
+        commits,
+        lower: 0,
+        upper,
+    })
+}
+
+// The compare API pages through the same commits list it returns inline, oldest first
+async fn get_commits_between(client: &Client, good: &str, bad: &str) -> Result<Vec<BisectCommit>> {
+    const PER_PAGE: usize = 100;
+    let mut commits = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let comparison: Comparison = progress::with_spinner(
+            "Fetching commits to bisect…",
+            net::get_json(
+                client,
+                &format!("compare/{good}...{bad}?per_page={PER_PAGE}&page={page}"),
+            ),
+        )
+        .await?;
+        let is_last_page = comparison.commits.len() < PER_PAGE
+            || commits.len() + comparison.commits.len() >= comparison.total_commits;
+        commits.extend(comparison.commits.into_iter().filter_map(|commit| {
+            Some(BisectCommit {
+                sha: commit.sha?,
+                summary: commit
+                    .commit
+                    .message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+            })
+        }));
+        if is_last_page {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(commits)
+}
+
+async fn install_commit(client: &Client, config: &Config, sha: &str) -> Result<PathBuf> {
+    let version = InputVersion {
+        tag_name: sha.to_owned(),
+        version: Version::Hash(sha.to_owned()),
+    };
+    install_handler::start(&version, client, config).await?;
+
+    let version_dir = match utils::find_version_dir(sha, config).await? {
+        Some(value) => value,
+        None => return Err(BobError::NotInstalled(sha.to_owned()).into()),
+    };
+    Ok(version_dir
+        .join(utils::get_platform_name())
+        .join("bin")
+        .join(utils::get_binary_name()))
+}
+
+// Exits with 0 for good and anything else for bad, like git bisect run. The build is first in
+// PATH so the command can just call nvim, BOB_BISECT_NVIM points at it as well
+async fn run_check(cmd: &str, binary: &Path, working_dir: &Path) -> Result<bool> {
+    let mut paths = Vec::new();
+    if let Some(bin_dir) = binary.parent() {
+        paths.push(bin_dir.to_path_buf());
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&path));
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            let mut command = Command::new("cmd");
+            command.args(["/C", cmd]);
+        } else {
+            let mut command = Command::new("sh");
+            command.args(["-c", cmd]);
+        }
+    }
+    debug!("Running `{cmd}` against {}", binary.display());
+    let status = command
+        .current_dir(working_dir)
+        .env("PATH", std::env::join_paths(paths)?)
+        .env("BOB_BISECT_NVIM", binary)
+        .stdout(output::child_stdout())
+        .status()
+        .await;
+    match status {
+        Ok(value) => Ok(value.success()),
+        Err(error) => Err(anyhow!("Failed to run `{cmd}`, reason: {error}")),
+    }
+}
+
+async fn load(bisect_file: &Path) -> Result<Option<BisectState>> {
+    match fs::read_to_string(bisect_file).await {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => Err(anyhow!(
+                "{} is corrupted, start over with bob bisect --reset, reason: {error}",
+                bisect_file.display()
+            )),
+        },
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(anyhow!(
+            "Failed to read {}, reason: {error}",
+            bisect_file.display()
+        )),
+    }
+}
+
+async fn save(bisect_file: &Path, bisect: &BisectState) -> Result<()> {
+    let temp_file = bisect_file.with_extension("json.tmp");
+    fs::write(&temp_file, serde_json::to_string_pretty(bisect)?).await?;
+    if let Err(error) = fs::rename(&temp_file, bisect_file).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            bisect_file.display()
+        ));
+    }
+    Ok(())
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+fn get_remaining_steps(commits: usize) -> u32 {
+    (commits + 1).next_power_of_two().trailing_zeros()
+}
@@ -0,0 +1,76 @@
+use super::{install_handler, output, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{CommandOutput, Config};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
+use reqwest::Client;
+use serde_json::json;
+
+// By default the window is the same one install shows, from the installed nightly up to the
+// latest one. --since moves its start
+pub async fn start(
+    client: &Client,
+    config: Config,
+    since: Option<String>,
+) -> Result<CommandOutput> {
+    let since = match since {
+        Some(value) => resolve_since(client, &value).await?,
+        None => match utils::get_local_nightly(&config).await {
+            Ok(local) => local.published_at,
+            Err(_) => {
+                return Err(BobError::Usage(String::from(
+                    "nightly isn't installed, pass --since to pick where the changelog starts",
+                ))
+                .into())
+            }
+        },
+    };
+    let upstream = utils::get_upstream_nightly(client).await?;
+
+    let commits = if since < upstream.published_at {
+        utils::get_commits_for_nightly(client, &since, &upstream.published_at).await?
+    } else {
+        Vec::new()
+    };
+    if commits.is_empty() {
+        output::print_human("No new commits");
+    } else {
+        install_handler::print_commit_list(&commits);
+    }
+
+    Ok(CommandOutput {
+        action: String::from("changelog"),
+        versions: vec![upstream.tag_name],
+        details: json!({
+            "since": since,
+            "until": upstream.published_at,
+            "commits": commits,
+        }),
+        ..Default::default()
+    })
+}
+
+// Dates are taken as UTC midnight, a release tag stands for the moment it was published
+async fn resolve_since(client: &Client, since: &str) -> Result<String> {
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        if let Some(midnight) = date.and_hms_opt(0, 0, 0) {
+            return Ok(Utc
+                .from_utc_datetime(&midnight)
+                .to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(since) {
+        return Ok(date
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if let Some(Version::Release(_)) = Version::parse(since) {
+        let tag = utils::normalize_tag(since);
+        return Ok(utils::get_release(client, &tag).await?.published_at);
+    }
+
+    Err(BobError::Usage(format!(
+        "--since takes a date like 2024-01-31, an RFC 3339 time or a release tag like v0.9.5, got {since}"
+    ))
+    .into())
+}
@@ -1,83 +1,1219 @@
-use super::{erase_handler, install_handler, ls_handler, uninstall_handler, use_handler, utils};
-use crate::{enums::InstallResult, models::Config};
-use anyhow::Result;
-use clap::Parser;
+use super::{
+    benchmark_handler, bisect_handler, changelog_handler, complete_handler, config_handler,
+    contents_handler, doctor_handler, env_handler, erase_handler, info_handler, install_handler,
+    logging, ls_handler, man_handler, migrate_handler, net, notes_handler, notify, open_handler,
+    output, pin_handler, progress, prompt, reinstall_handler, report_handler, reset_handler,
+    self_update_handler, state, test_handler, uninstall_handler, use_handler, utils,
+    verify_handler,
+};
+use crate::{
+    enums::{self, BobError, InstallResult, Version},
+    models::{CommandOutput, Config, InputVersion},
+};
+use anyhow::{anyhow, Result};
+use clap::{error::ErrorKind, ArgAction, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use reqwest::Client;
-use tracing::info;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use yansi::Paint;
+
+const EXIT_CODES: &str = "Exit codes:
+  0   Success, including when there was nothing to do
+  1   Any other failure
+  2   Usage error or invalid version string
+  3   bob doctor found warnings but no failures
+  4   The version doesn't exist upstream
+  5   The version isn't installed
+  6   Network failure or GitHub API error
+  7   GitHub rate limit exceeded
+  8   Updates are available, only returned by install --check and self-update --check
+  9   Failed to expand a downloaded archive
+  10  Filesystem or permission error
+  11  bob doctor found failures
+  12  bob verify found missing, modified or extra files";
 
 #[derive(Debug, Parser)]
-#[command(version)]
-enum Cli {
+#[command(version, about, after_help = EXIT_CODES)]
+pub struct Cli {
+    /// Log more of what bob does, pass twice for even more detail
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Append a full debug log to this file, whatever the console shows
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<String>,
+
+    /// Don't warn about running bob as root/administrator
+    #[arg(long, global = true)]
+    allow_root: bool,
+
+    /// Write newline delimited JSON progress events to this file instead of drawing progress bars
+    #[arg(long, global = true, value_name = "PATH")]
+    progress_file: Option<String>,
+
+    /// Print the result as a JSON object on stdout and everything else on stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Answer yes to every question bob would ask, same as BOB_NONINTERACTIVE=1
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Merge this profile from the config over the base settings, defaults to $BOB_PROFILE
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
     /// Switch to the specified version, will auto-invoke install command
     /// if the version is not installed already
     Use {
-        /// Version to switch to |nightly|stable|<version-string>|<commit-hash>|
-        version: String,
+        /// Version to switch to |nightly|stable|<version-string>|<commit-hash>|,
+        /// defaults to the configured default_version
+        version: Option<String>,
+
+        /// Add Neovim to the application launcher, a desktop entry on Linux and a Start Menu shortcut on Windows
+        #[arg(long, conflicts_with = "ephemeral")]
+        desktop: bool,
+
+        /// Only run the command given after -- with the version, without switching to it
+        #[arg(long, requires = "command")]
+        ephemeral: bool,
+
+        /// Command to run with --ephemeral, e.g. -- nvim file.txt
+        #[arg(last = true, requires = "ephemeral", value_name = "COMMAND")]
+        command: Vec<String>,
     },
 
     /// Install the specified version, can also be used to update
     /// out-of-date nightly version
     Install {
-        /// Version to be installed |nightly|stable|<version-string>|<commit-hash>|
-        version: String,
+        /// Versions to be installed |nightly|stable|<version-string>|<commit-hash>|,
+        /// defaults to the configured default_version
+        versions: Vec<String>,
+
+        /// Wait for upstream to publish a nightly that differs from the installed one
+        /// before installing nightly
+        #[arg(long)]
+        wait_for_newer: bool,
+
+        /// Only check whether the versions are missing or out of date, exits with 8 if any are
+        #[arg(long, conflicts_with = "wait_for_newer")]
+        check: bool,
+
+        /// Use this self-built nvim binary with the runtime of the given version, the two have to
+        /// share their major and minor version
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["check", "wait_for_newer"])]
+        binary: Option<PathBuf>,
+
+        /// Give up waiting for a newer nightly after this many minutes
+        #[arg(
+            long,
+            value_name = "MINUTES",
+            default_value_t = 60,
+            requires = "wait_for_newer"
+        )]
+        wait_timeout: u64,
+    },
+
+    /// Update installed channels to their newest builds and report what changed for each one
+    Update {
+        /// Channels to be updated |nightly|stable|
+        #[arg(required_unless_present = "all")]
+        versions: Vec<String>,
+
+        /// Update nightly and stable, whichever of them are installed
+        #[arg(long, conflicts_with = "versions")]
+        all: bool,
     },
 
+    /// Install the used version again in place to repair broken files, without switching away
+    /// from it
+    Reinstall,
+
     /// Uninstall the specified version
     #[clap(visible_alias = "rm")]
     Uninstall {
-        /// Version to be uninstalled |nightly|stable|<version-string>|<commit-hash>|
+        /// Version to be uninstalled |nightly|stable|<version-string>|<commit-hash>|, leave it out to pick several from a list
+        version: Option<String>,
+
+        /// Uninstall the version even when it's pinned
+        #[arg(long, requires = "version")]
+        force: bool,
+
+        /// Let the active version be picked from the list
+        #[arg(long, conflicts_with = "version")]
+        allow_active: bool,
+
+        /// Let pinned versions be picked from the list
+        #[arg(long, conflicts_with = "version")]
+        allow_pinned: bool,
+    },
+
+    /// Check the files of installed versions against what was recorded when they were installed
+    Verify {
+        /// Version to be verified |nightly|stable|<version-string>|<commit-hash>|
+        #[arg(required_unless_present = "all")]
+        version: Option<String>,
+
+        /// Verify every installed version
+        #[arg(long, conflicts_with = "version")]
+        all: bool,
+
+        /// Offer to reinstall the versions that failed
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Protect an installed version from being uninstalled until it's unpinned
+    Pin {
+        /// Version to be pinned |nightly|stable|<version-string>|<commit-hash>|
+        version: String,
+    },
+
+    /// Allow a pinned version to be uninstalled again
+    Unpin {
+        /// Version to be unpinned |nightly|stable|<version-string>|<commit-hash>|
         version: String,
     },
 
+    /// Remove everything bob set up, erase plus its state, caches, logs, PATH lines and
+    /// optionally the config, to start over from scratch
+    Reset {
+        /// Keep the config file
+        #[arg(long)]
+        keep_config: bool,
+
+        /// Only print what would be removed and what would be left behind
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Erase any change bob ever made, including neovim installation,
     /// neovim version downloads and registry changes
     Erase,
 
     /// List all installed and used versions
     #[clap(visible_alias = "ls")]
-    List,
+    List {
+        /// Only list versions that were never used, candidates for uninstalling
+        #[arg(long)]
+        unused: bool,
+    },
+
+    /// Import Neovim versions installed by asdf, nvm style managers or by hand, the originals
+    /// are left in place
+    Migrate {
+        /// Also look in this directory, either a Neovim installation or a directory of them
+        #[arg(long, value_name = "DIR")]
+        from: Vec<PathBuf>,
+
+        /// Link the installations into the downloads folder instead of copying them
+        #[arg(long)]
+        link: bool,
+    },
+
+    /// Print a Markdown summary of bob's setup to paste into a bug report, nothing is sent
+    /// anywhere
+    Report {
+        /// Open a new GitHub issue in the browser with the report filled in
+        #[arg(long)]
+        open: bool,
+    },
+
+    /// Show a release's date, assets for this platform and release notes before installing it
+    Info {
+        /// Version to show |nightly|stable|<version-string>|<commit-hash>|
+        version: String,
+
+        /// Also show the install date, size and origin of the installed build
+        #[arg(long)]
+        local: bool,
+    },
+
+    /// Open the GitHub page of a release or commit in the browser
+    Open {
+        /// Version to open |nightly|stable|<version-string>|<commit-hash>|
+        #[arg(required_unless_present = "changelog")]
+        version: Option<String>,
+
+        /// Open the comparison between the installed nightly and the latest one instead
+        #[arg(long, conflicts_with = "version")]
+        changelog: bool,
+    },
+
+    /// Print the release notes of a version, cached so they can be read offline later
+    Notes {
+        /// Version to print the notes of |nightly|stable|<version-string>|
+        version: String,
+    },
+
+    /// Print the commits that went into nightly since the installed one
+    Changelog {
+        /// Start from this date (2024-01-31), RFC 3339 time or release tag (v0.9.5) instead
+        #[arg(long, value_name = "DATE|TAG")]
+        since: Option<String>,
+    },
+
+    /// Start the installed build of a version headlessly with your config to see whether it
+    /// loads, without switching to it. Exits with 1 when it doesn't
+    Test {
+        /// Version to test |nightly|stable|<version-string>|<commit-hash>|
+        version: String,
+
+        /// Ex command to run after the config loaded, e.g. checkhealth
+        #[arg(long, value_name = "COMMAND")]
+        cmd: Option<String>,
+
+        /// Stop nvim and count the test as failed after this many seconds
+        #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+        timeout: u64,
+
+        /// Switch to the version if the test passed
+        #[arg(long)]
+        then_use: bool,
+    },
+
+    /// Compare how long the installed versions take to start, each one is started several times
+    /// with --startuptime and the first version given is the baseline
+    Benchmark {
+        /// Versions to compare |nightly|stable|<version-string>|<commit-hash>|
+        #[arg(required = true)]
+        versions: Vec<String>,
+
+        /// Start each version this many times
+        #[arg(long, default_value_t = 10)]
+        runs: u32,
+
+        /// Extra arguments passed to nvim, e.g. '-u init.lua'
+        #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+        args: Option<String>,
+
+        /// Install versions that aren't installed instead of skipping them
+        #[arg(long)]
+        install: bool,
+    },
+
+    /// Find the commit that introduced a regression by building and testing the commits between
+    /// a good and a bad version, run it again without --good and --bad to resume
+    Bisect {
+        /// Last version known to work |stable|<version-string>|<commit-hash>|
+        #[arg(long, requires = "bad")]
+        good: Option<String>,
+
+        /// First version known to be broken |nightly|stable|<version-string>|<commit-hash>|
+        #[arg(long, requires = "good")]
+        bad: Option<String>,
+
+        /// Test each commit with this command instead of asking, exit code 0 means good
+        #[arg(long, value_name = "COMMAND")]
+        cmd: Option<String>,
+
+        /// Drop the bisect in progress
+        #[arg(long, conflicts_with_all = ["good", "bad", "cmd"])]
+        reset: bool,
+    },
+
+    /// Print the files of an installed version as a tree
+    Contents {
+        /// Version to show |nightly|stable|<version-string>|<commit-hash>|
+        version: String,
+    },
+
+    /// Print a completion script for the given shell to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print the man page of bob or one of its subcommands to stdout
+    Man {
+        /// Subcommand to print the page of, e.g. install for bob-install(1)
+        subcommand: Option<String>,
+
+        /// Write every page into this directory as <name>.1 instead of printing one
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+
+    /// Completes version arguments for the shell completion scripts
+    #[command(name = "_complete", hide = true)]
+    Complete {
+        /// Refresh the cached list of upstream versions instead of completing
+        #[arg(long)]
+        refresh_cache: bool,
+
+        /// The command line being completed, without the leading bob
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
+
+    /// Look for problems with bob's state
+    Doctor {
+        /// Remove stale entries instead of only reporting them
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Print the line that adds bob's neovim to PATH for your shell
+    Env {
+        /// Shell to print the line for, detected from $SHELL by default
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+
+        /// Append the line to the shell's startup file instead, or the user PATH on windows
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Update bob itself to its latest release
+    SelfUpdate {
+        /// Only check whether a newer bob is out, exits with 8 if it is
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Inspect bob's configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
 }
 
-pub async fn start(config: Config) -> Result<()> {
-    let cli = Cli::parse();
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print every setting with the value bob uses and where it came from, secrets are redacted
+    Show,
+}
 
-    match cli {
-        Cli::Use { version } => {
-            let client = Client::new();
-            let version = utils::parse_version_type(&client, &version).await?;
+// Everything the bob binary does, errors are reported here and turned into the exit code
+pub async fn run() -> i32 {
+    let error = match load_and_start().await {
+        Ok(code) => return code,
+        Err(error) => error,
+    };
+
+    logging::init_default();
+    if output::is_json() {
+        output::print_error(&error);
+    } else if let Some(BobError::UpdatesAvailable(_)) = error.downcast_ref() {
+        // Reported through the exit code for scripts, it isn't a failure to whoever reads it
+        info!("{error}");
+    } else {
+        error!("Error: {error}");
+    }
+    enums::get_exit_code(&error)
+}
+
+async fn load_and_start() -> Result<i32> {
+    let cli = parse()?;
+    let mut config: Config = match utils::get_config_file() {
+        Some(config_file) => match tokio::fs::read_to_string(config_file).await {
+            Ok(config_file) => serde_json::from_str(&config_file)?,
+            Err(_) => Config::default(),
+        },
+        None => Config::default(),
+    };
+    if let Some(profile) = utils::get_profile_name(cli.profile.as_deref()) {
+        config = config.with_profile(&profile)?;
+    }
+    let log_file = cli.log_file.as_deref().or(config.log_file.as_deref());
+    logging::init(cli.verbose, cli.quiet, log_file)?;
+
+    // The .bob fallback is resolved against the directory bob was started in
+    utils::init_data_dir()?;
+    config.validate()?;
+    start(cli, config).await
+}
+
+pub fn parse() -> Result<Cli> {
+    match Cli::try_parse() {
+        Ok(value) => Ok(value),
+        // Usage errors happen before --json is parsed, so the flag is looked for by hand
+        Err(error) if std::env::args().any(|arg| arg == "--json") && error.use_stderr() => {
+            output::enable_json();
+            // Only the error itself is kept, the usage and help hints that follow it are dropped
+            let message = error
+                .to_string()
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ");
+            let message = message.trim_start_matches("error: ").to_owned();
+            Err(BobError::Usage(message).into())
+        }
+        Err(error) => error.exit(),
+    }
+}
+
+// Returns the exit code, only commands that report problems in their result set it
+pub async fn start(cli: Cli, config: Config) -> Result<i32> {
+    if cli.json {
+        output::enable_json();
+        // These print scripts and pages that can't be wrapped in a result object
+        if let Command::Completions { .. } | Command::Man { .. } | Command::Complete { .. } =
+            cli.command
+        {
+            return Err(
+                BobError::Usage(String::from("This command doesn't support --json")).into(),
+            );
+        }
+    }
+
+    if cli.yes {
+        prompt::assume_yes();
+    }
 
+    // Read from the directory bob was started in, every later lookup reuses it
+    state::init_version_override()?;
+
+    if let Some(path) = cli.progress_file.as_ref().or(config.progress_file.as_ref()) {
+        progress::init_event_stream(path)?;
+    }
+
+    // Completion scripts are redirected straight into files, nothing else can be printed
+    let is_quiet = matches!(
+        cli.command,
+        Command::Completions { .. } | Command::Complete { .. } | Command::Man { .. }
+    );
+
+    if !cli.allow_root && !is_quiet && utils::is_running_elevated() {
+        warn!("Bob is running as root/administrator, any files it creates won't be accessible when running it as a regular user later on");
+        warn!("Run bob as a regular user or pass --allow-root if this is intentional");
+    }
+
+    if !is_quiet && !matches!(cli.command, Command::Erase | Command::Reset { .. }) {
+        migrate_handler::offer_arch_migration(&config).await?;
+    }
+
+    if !matches!(
+        cli.command,
+        Command::List { .. }
+            | Command::Erase
+            | Command::Reset { .. }
+            | Command::Doctor { .. }
+            | Command::Config { .. }
+            | Command::SelfUpdate { .. }
+            | Command::Env { .. }
+            | Command::Changelog { .. }
+            | Command::Report { .. }
+            | Command::Notes { .. }
+            | Command::Info { .. }
+            | Command::Open { .. }
+            | Command::Verify { repair: false, .. }
+            | Command::Completions { .. }
+            | Command::Complete { .. }
+            | Command::Man { .. }
+    ) {
+        let problems = state::tidy(&config, false).await?;
+        for problem in &problems {
+            warn!("{problem}");
+        }
+        if !problems.is_empty() {
+            warn!("Run `bob use <version>` to switch to an installed version or `bob doctor --fix` to clear stale entries");
+        }
+    }
+
+    if matches!(
+        cli.command,
+        Command::Use { .. }
+            | Command::Install { .. }
+            | Command::Update { .. }
+            | Command::Uninstall { .. }
+            | Command::Reinstall
+            | Command::Verify { repair: true, .. }
+            | Command::Bisect { .. }
+            | Command::Benchmark { install: true, .. }
+    ) {
+        utils::clean_leftovers(&config).await?;
+    }
+
+    let client = net::create_client(&config).await?;
+    let started = Instant::now();
+
+    let result = match cli.command {
+        Command::Use {
+            version,
+            ephemeral: true,
+            command,
+            ..
+        } => {
+            let version = version_or_default(version, &config, "use")?;
+            let version = resolve_version(&client, &version).await?;
+            let exit_code =
+                use_handler::run_ephemeral(&version, &client, &config, &command).await?;
+            CommandOutput {
+                action: String::from("use"),
+                versions: vec![version.tag_name],
+                details: json!({
+                    "ephemeral": true,
+                    "command": command,
+                }),
+                exit_code,
+                ..Default::default()
+            }
+        }
+        Command::Use {
+            version, desktop, ..
+        } => {
+            let version = version_or_default(version, &config, "use")?;
+            let version = resolve_version(&client, &version).await?;
+            let installation_dir = utils::get_installation_folder(&config)?;
+
+            let tag_name = version.tag_name.clone();
+            let mut config = config;
+            if desktop {
+                config.install_desktop_entry = Some(true);
+            }
             use_handler::start(version, &client, config).await?;
+            CommandOutput {
+                action: String::from("use"),
+                versions: vec![tag_name],
+                paths: vec![installation_dir],
+                ..Default::default()
+            }
         }
-        Cli::Install { version } => {
-            let client = Client::new();
-            let version = utils::parse_version_type(&client, &version).await?;
+        Command::Install {
+            versions,
+            check: true,
+            ..
+        } => {
+            let versions = if versions.is_empty() {
+                vec![version_or_default(None, &config, "install")?]
+            } else {
+                versions
+            };
 
-            match install_handler::start(&version, &client, &config).await? {
-                InstallResult::InstallationSuccess(location) => {
-                    info!(
-                        "{} has been successfully installed in {location}",
-                        version.tag_name
-                    );
+            let mut outdated = Vec::new();
+            for version in utils::resolve_all(&client, &versions).await {
+                let version = version?;
+                if install_handler::is_update_available(&version, &client, &config).await? {
+                    outdated.push(version.tag_name);
                 }
-                InstallResult::VersionAlreadyInstalled => {
-                    info!("{} is already installed", version.tag_name);
+            }
+            if !outdated.is_empty() {
+                if install_handler::is_active_update_pending(&client, &config).await? {
+                    info!("Updating will change the nvim you're using");
                 }
-                InstallResult::NightlyIsUpdated => {
-                    info!("Nightly up to date!");
+                notify::send(
+                    &config,
+                    "Neovim updates available",
+                    &format!("{} can be updated", outdated.join(", ")),
+                )
+                .await;
+                return Err(BobError::UpdatesAvailable(outdated).into());
+            }
+
+            info!("Everything is up to date");
+            CommandOutput {
+                action: String::from("check"),
+                versions,
+                ..Default::default()
+            }
+        }
+        Command::Install {
+            versions,
+            binary: Some(binary),
+            ..
+        } => {
+            let version = match versions.as_slice() {
+                [] => version_or_default(None, &config, "install")?,
+                [version] => version.clone(),
+                _ => {
+                    return Err(BobError::Usage(String::from(
+                        "--binary can only be paired with one runtime version",
+                    ))
+                    .into())
                 }
+            };
+            let version = resolve_version(&client, &version).await?;
+            let version_dir =
+                install_handler::assemble(&version, &binary, &client, &config).await?;
+            CommandOutput {
+                action: String::from("install"),
+                paths: vec![version_dir],
+                details: json!({
+                    "results": [{ "version": version.tag_name, "result": "assembled" }],
+                    "binary": binary,
+                }),
+                versions: vec![version.tag_name],
+                ..Default::default()
             }
         }
-        Cli::Uninstall { version } => {
+        Command::Install {
+            versions,
+            wait_for_newer,
+            wait_timeout,
+            ..
+        } => {
+            let wait = wait_for_newer.then(|| Duration::from_secs(wait_timeout * 60));
+            let versions = if versions.is_empty() {
+                vec![version_or_default(None, &config, "install")?]
+            } else {
+                versions
+            };
+
+            install_batch("install", &versions, &client, &config, wait, false).await?
+        }
+        Command::Update { versions, all } => {
+            let versions = if all {
+                get_installed_channels(&config).await?
+            } else {
+                versions
+            };
+            if versions.is_empty() {
+                info!("Neither nightly nor a release is installed, there's nothing to update");
+                CommandOutput {
+                    action: String::from("update"),
+                    ..Default::default()
+                }
+            } else {
+                install_batch("update", &versions, &client, &config, None, true).await?
+            }
+        }
+        Command::Reinstall => reinstall_handler::start(&client, config).await?,
+        Command::Uninstall {
+            version: Some(version),
+            force,
+            ..
+        } => {
             info!("Starting uninstallation process");
-            uninstall_handler::start(&version, config).await?;
+            uninstall_handler::start(&version, &client, config, force).await?
+        }
+        Command::Uninstall {
+            version: None,
+            allow_active,
+            allow_pinned,
+            ..
+        } => uninstall_handler::start_interactive(config, allow_active, allow_pinned).await?,
+        Command::Verify {
+            version,
+            all: _,
+            repair,
+        } => {
+            let version = match version {
+                Some(version) => Some(resolve_version(&client, &version).await?),
+                None => None,
+            };
+            verify_handler::start(&client, config, version, repair).await?
+        }
+        Command::Pin { version } => {
+            let version = resolve_version(&client, &version).await?;
+            pin_handler::start(config, version, true).await?
+        }
+        Command::Unpin { version } => {
+            let version = resolve_version(&client, &version).await?;
+            pin_handler::start(config, version, false).await?
+        }
+        Command::Erase => erase_handler::start(config).await?,
+        Command::Reset {
+            keep_config,
+            dry_run,
+        } => {
+            let log_file = cli.log_file.clone().or(config.log_file.clone());
+            reset_handler::start(config, log_file, keep_config, dry_run).await?
+        }
+        Command::List { unused } => ls_handler::start(config, cli.verbose > 0, unused).await?,
+        Command::Contents { version } => {
+            let version = utils::parse_version_type(&client, &version).await?;
+            contents_handler::start(version, config).await?
+        }
+        Command::Migrate { from, link } => migrate_handler::start(config, from, link).await?,
+        Command::Doctor { fix } => doctor_handler::start(&client, config, fix).await?,
+        Command::Report { open } => {
+            let log_file = cli.log_file.clone().or(config.log_file.clone());
+            report_handler::start(config, log_file, open).await?
+        }
+        Command::Info { version, local } => {
+            let version = resolve_version(&client, &version).await?;
+            info_handler::start(&client, config, version, local).await?
+        }
+        Command::Open { version, changelog } => {
+            let version = match version {
+                Some(version) => Some(resolve_version(&client, &version).await?),
+                None => None,
+            };
+            open_handler::start(&client, config, version, changelog).await?
+        }
+        Command::Notes { version } => {
+            let version = resolve_version(&client, &version).await?;
+            notes_handler::start(&client, config, version).await?
+        }
+        Command::Test {
+            version,
+            cmd,
+            timeout,
+            then_use,
+        } => {
+            let version = resolve_version(&client, &version).await?;
+            let timeout = Duration::from_secs(timeout);
+            test_handler::start(&client, config, version, cmd, timeout, then_use).await?
+        }
+        Command::Benchmark {
+            versions,
+            runs,
+            args,
+            install,
+        } => benchmark_handler::start(&client, config, versions, runs, args, install).await?,
+        Command::Bisect {
+            good,
+            bad,
+            cmd,
+            reset,
+        } => bisect_handler::start(&client, config, good, bad, cmd, reset).await?,
+        Command::Changelog { since } => changelog_handler::start(&client, config, since).await?,
+        Command::Env { shell, apply } => env_handler::start(config, shell, apply).await?,
+        Command::SelfUpdate { check } => self_update_handler::start(&client, check).await?,
+        Command::Config {
+            command: ConfigCommand::Show,
+        } => config_handler::start(config).await?,
+        Command::Completions { shell } => {
+            let mut command = Cli::command();
+            clap_complete::generate(shell, &mut command, "bob", &mut std::io::stdout());
+            print!("{}", complete_handler::get_dynamic_script(shell));
+            return Ok(0);
+        }
+        Command::Man {
+            subcommand,
+            out_dir,
+        } => {
+            man_handler::start(Cli::command(), subcommand, out_dir).await?;
+            return Ok(0);
+        }
+        Command::Complete {
+            refresh_cache,
+            words,
+        } => {
+            if refresh_cache {
+                complete_handler::refresh_remote_cache(&client, &config).await?;
+            } else {
+                complete_handler::start(words, config).await?;
+            }
+            return Ok(0);
+        }
+    };
+
+    let exit_code = result.exit_code;
+    if output::is_json() {
+        output::print_json(&CommandOutput {
+            duration_ms: started.elapsed().as_millis(),
+            ..result
+        })?;
+    }
+    Ok(exit_code)
+}
+
+async fn resolve_version(client: &Client, version: &str) -> Result<InputVersion> {
+    progress::emit("resolve", 0, 1);
+    let version = utils::parse_version_type(client, version).await?;
+    progress::emit("resolve", 1, 1);
+    Ok(version)
+}
+
+struct InstallRow {
+    // What was asked for, a channel like stable or nightly resolves to a tag
+    input: String,
+    tag_name: Option<String>,
+    old: Option<String>,
+    new: Option<String>,
+    download_bytes: u64,
+    elapsed: Duration,
+    result: std::result::Result<&'static str, String>,
+}
+
+impl InstallRow {
+    fn get_status(&self) -> &'static str {
+        match self.result {
+            Ok("installed") if self.old.is_some() => "updated",
+            Ok("installed") => "installed",
+            Ok(_) => "current",
+            Err(_) => "failed",
         }
-        Cli::Erase => {
-            erase_handler::start(config).await?;
+    }
+}
+
+// Installing several versions and updating channels both end in the same report, updates always
+// print the table and keep a failed channel in it even when it's the only one
+async fn install_batch(
+    action: &str,
+    versions: &[String],
+    client: &Client,
+    config: &Config,
+    wait: Option<Duration>,
+    summarize: bool,
+) -> Result<CommandOutput> {
+    let rows = match install_versions(versions, client, config, wait, summarize).await {
+        Ok(value) => value,
+        Err(error) => {
+            notify::send(
+                config,
+                &format!("Failed to install {}", versions.join(", ")),
+                &error.to_string(),
+            )
+            .await;
+            return Err(error);
+        }
+    };
+    if summarize || rows.len() > 1 {
+        print_install_summary(&rows);
+    }
+
+    let failed: Vec<&str> = rows
+        .iter()
+        .filter(|row| row.result.is_err())
+        .map(|row| row.input.as_str())
+        .collect();
+    if !failed.is_empty() {
+        notify::send(
+            config,
+            &format!("Failed to install {}", failed.join(", ")),
+            "See bob's output for the reasons",
+        )
+        .await;
+        error!("Failed to install {}", failed.join(", "));
+    }
+    let installed: Vec<&str> = rows
+        .iter()
+        .filter(|row| row.result == Ok("installed"))
+        .filter_map(|row| row.tag_name.as_deref())
+        .collect();
+    if !installed.is_empty() {
+        notify::send(
+            config,
+            "Neovim installed",
+            &format!("Installed {}", installed.join(", ")),
+        )
+        .await;
+    }
+
+    let downloads_dir = utils::get_downloads_folder(config).await?;
+    let succeeded = rows
+        .iter()
+        .filter(|row| row.result.is_ok())
+        .filter_map(|row| row.tag_name.clone())
+        .collect::<Vec<_>>();
+    Ok(CommandOutput {
+        action: action.to_owned(),
+        paths: succeeded
+            .iter()
+            .map(|tag_name| downloads_dir.join(tag_name))
+            .collect(),
+        details: json!({
+            "results": rows
+                .iter()
+                .map(|row| json!({
+                    "input": row.input,
+                    "version": row.tag_name,
+                    "result": row.result.as_ref().ok(),
+                    "status": row.get_status(),
+                    "reason": row.result.as_ref().err(),
+                    "old": row.old,
+                    "new": row.new,
+                    "download_bytes": row.download_bytes,
+                    "duration_ms": row.elapsed.as_millis() as u64,
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        versions: succeeded,
+        exit_code: if failed.is_empty() { 0 } else { 1 },
+        ..Default::default()
+    })
+}
+
+// Nightly when it's installed and stable when any release is, the channels update --all refreshes
+async fn get_installed_channels(config: &Config) -> Result<Vec<String>> {
+    let installed = utils::get_installed_versions(config).await?;
+    let mut channels = Vec::new();
+    if installed
+        .iter()
+        .any(|version| matches!(version.version, Some(Version::Nightly)))
+    {
+        channels.push(String::from("nightly"));
+    }
+    if installed
+        .iter()
+        .any(|version| matches!(version.version, Some(Version::Release(_))))
+    {
+        channels.push(String::from("stable"));
+    }
+    Ok(channels)
+}
+
+// A failed version of a batch is kept as a row rather than failing the rest, only a single
+// version fails the command right away unless it's kept as a row too
+async fn install_versions(
+    versions: &[String],
+    client: &Client,
+    config: &Config,
+    wait: Option<Duration>,
+    keep_failures: bool,
+) -> Result<Vec<InstallRow>> {
+    let mut rows = Vec::new();
+    if let ([input], false) = (versions, keep_failures) {
+        let version = resolve_version(client, input).await?;
+        let row = install_row(input, &version, client, config, wait).await;
+        if let Err(error) = row.result {
+            return Err(anyhow!(error));
         }
-        Cli::List => {
-            ls_handler::start(config).await?;
+        rows.push(row);
+    } else {
+        progress::emit("resolve", 0, versions.len() as u64);
+        let resolved = utils::resolve_all(client, versions).await;
+        progress::emit("resolve", versions.len() as u64, versions.len() as u64);
+
+        // Versions that are already installed are skipped by install_handler, so re-running
+        // a partially failed batch only retries what's still missing
+        let mut seen: Vec<InputVersion> = Vec::new();
+        for (input, version) in versions.iter().zip(resolved) {
+            let row = match version {
+                // Aliases like stable and 0.9.5 can resolve to a version given earlier
+                Ok(version) if seen.contains(&version) => {
+                    debug!("Skipping {input}, it's the same as {}", version.tag_name);
+                    continue;
+                }
+                Ok(version) => {
+                    let row = install_row(input, &version, client, config, wait).await;
+                    seen.push(version);
+                    row
+                }
+                Err(error) => InstallRow {
+                    input: input.clone(),
+                    tag_name: None,
+                    old: None,
+                    new: None,
+                    download_bytes: 0,
+                    elapsed: Duration::ZERO,
+                    result: Err(error.to_string()),
+                },
+            };
+            if let Err(error) = &row.result {
+                error!("Failed to install {input}: {error}");
+            }
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+// Downloads are measured through the progress events, the archive itself is gone once the
+// version is expanded
+async fn install_row(
+    input: &str,
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+    wait: Option<Duration>,
+) -> InstallRow {
+    let started = Instant::now();
+    let old = describe_installed(input, version, config).await;
+
+    let downloaded = Arc::new(AtomicU64::new(0));
+    let counter = downloaded.clone();
+    let listener: progress::Listener = Arc::new(move |event: &progress::Event| {
+        if event.phase == "download" {
+            counter.fetch_max(event.done, Ordering::Relaxed);
         }
+    });
+    let install = install_version(version, client, config, wait);
+    let result = progress::with_listener(listener, install).await;
+
+    let new = match result {
+        Ok(_) => describe_installed(&version.tag_name, version, config).await,
+        Err(_) => None,
+    };
+    InstallRow {
+        input: input.to_owned(),
+        tag_name: Some(version.tag_name.clone()),
+        old,
+        new,
+        download_bytes: downloaded.load(Ordering::Relaxed),
+        elapsed: started.elapsed(),
+        result: result.map_err(|error| error.to_string()),
     }
+}
 
-    Ok(())
+// What a channel stands for at the moment, nightly by the commit it was built from and stable by
+// the newest release installed
+async fn describe_installed(
+    input: &str,
+    version: &InputVersion,
+    config: &Config,
+) -> Option<String> {
+    match version.version {
+        Version::Release(_) if input == "stable" => utils::get_installed_versions(config)
+            .await
+            .ok()?
+            .into_iter()
+            .rfind(|installed| matches!(installed.version, Some(Version::Release(_))))
+            .map(|installed| installed.name),
+        _ if !utils::is_version_installed(&version.tag_name, config)
+            .await
+            .ok()? =>
+        {
+            None
+        }
+        Version::Nightly => {
+            let nightly = utils::get_local_nightly(config).await.ok()?;
+            Some(match utils::get_commit_sha(&nightly) {
+                Some(sha) => format!("nightly {}", &sha[..7]),
+                None => format!(
+                    "nightly {}",
+                    nightly.published_at.get(..10).unwrap_or_default()
+                ),
+            })
+        }
+        _ => Some(version.tag_name.clone()),
+    }
+}
+
+fn print_install_summary(rows: &[InstallRow]) {
+    let cells = rows
+        .iter()
+        .map(|row| {
+            [
+                row.input.clone(),
+                row.old.clone().unwrap_or_else(|| String::from("-")),
+                row.new.clone().unwrap_or_else(|| String::from("-")),
+                match row.download_bytes {
+                    0 => String::from("-"),
+                    bytes => utils::format_size(bytes),
+                },
+                format!("{:.1}s", row.elapsed.as_secs_f64()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let headers = ["Channel", "Old", "New", "Download", "Time"];
+    let widths = (0..headers.len())
+        .map(|column| {
+            cells
+                .iter()
+                .map(|row| row[column].chars().count())
+                .chain([headers[column].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let pad = |values: &[String]| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:<width$}"))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    };
+    output::print_human(format!("{} | Status", pad(&headers.map(String::from))));
+    output::print_human(format!(
+        "{}-+-{}",
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("-+-"),
+        "-".repeat(10)
+    ));
+    for (row, cells) in rows.iter().zip(&cells) {
+        let status = match (&row.result, row.get_status()) {
+            (Err(reason), status) => Paint::red(format!("{status}: {reason}")),
+            (_, status @ ("updated" | "installed")) => Paint::green(status.to_owned()),
+            (_, status) => Paint::new(status.to_owned()).dimmed(),
+        };
+        output::print_human(format!("{} | {status}", pad(cells)));
+    }
+}
+
+async fn install_version(
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+    wait: Option<Duration>,
+) -> Result<&'static str> {
+    if let Some(timeout) = wait {
+        if version.tag_name == "nightly" {
+            install_handler::wait_for_newer_nightly(client, config, timeout).await?;
+        }
+    }
+
+    match install_handler::start(version, client, config).await? {
+        InstallResult::InstallationSuccess(location) => {
+            info!(
+                "{} has been successfully installed in {location}",
+                version.tag_name
+            );
+            // A copy doesn't pick up the new files the way a link or shims do
+            if use_handler::get_use_strategy(config) == "copy"
+                && utils::is_version_linked(&version.tag_name, config).await
+            {
+                use_handler::relink(version, config).await?;
+            }
+            Ok("installed")
+        }
+        InstallResult::VersionAlreadyInstalled => {
+            info!("{} is already installed", version.tag_name);
+            Ok("already_installed")
+        }
+        InstallResult::NightlyIsUpdated => {
+            info!("Nightly up to date!");
+            Ok("up_to_date")
+        }
+    }
+}
+
+fn version_or_default(
+    version: Option<String>,
+    config: &Config,
+    subcommand: &str,
+) -> Result<String> {
+    if let Some(version) = version.or_else(|| config.default_version.clone()) {
+        return Ok(version);
+    }
+
+    let message = "No version was provided and default_version isn't set in the config";
+    if output::is_json() {
+        return Err(BobError::Usage(message.to_owned()).into());
+    }
+
+    let mut command = Cli::command().bin_name("bob");
+    command.build();
+    command
+        .find_subcommand_mut(subcommand)
+        .unwrap()
+        .error(ErrorKind::MissingRequiredArgument, message)
+        .exit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{plant_version, TestDir};
+
+    #[tokio::test]
+    async fn update_all_covers_the_installed_channels() {
+        let dir = TestDir::new("cli-installed-channels");
+        let config = dir.config();
+        assert!(get_installed_channels(&config).await.unwrap().is_empty());
+
+        plant_version(&config, "v0.9.5");
+        assert_eq!(get_installed_channels(&config).await.unwrap(), ["stable"]);
+
+        plant_version(&config, "nightly");
+        assert_eq!(
+            get_installed_channels(&config).await.unwrap(),
+            ["nightly", "stable"]
+        );
+    }
 }
@@ -0,0 +1,184 @@
+use crate::models::Config;
+
+use super::utils;
+use anyhow::Result;
+use clap_complete::Shell;
+use reqwest::Client;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::fs;
+
+pub const REMOTE_CACHE_FILE: &str = "remote_versions.json";
+const REMOTE_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+const INSTALLED_VERSION_COMMANDS: [&str; 7] = [
+    "use",
+    "uninstall",
+    "rm",
+    "contents",
+    "pin",
+    "unpin",
+    "verify",
+];
+
+// Shells call this on every tab press, so it only ever reads from disk, a stale remote list is
+// refreshed by a detached bob that the next completion picks up
+pub async fn start(words: Vec<String>, config: Config) -> Result<()> {
+    let mut words = words.iter().map(String::as_str);
+    let mut subcommand = None;
+    while let Some(word) = words.next() {
+        if word == "--progress-file" {
+            words.next();
+        } else if !word.starts_with('-') {
+            subcommand = Some(word);
+            break;
+        }
+    }
+    let arguments = words.collect::<Vec<_>>();
+    let current = arguments.last().copied().unwrap_or_default();
+    if current.starts_with('-') {
+        return Ok(());
+    }
+
+    let candidates = match subcommand {
+        Some(command) if INSTALLED_VERSION_COMMANDS.contains(&command) && arguments.len() <= 1 => {
+            get_installed_versions(&config).await
+        }
+        Some("install") => {
+            let mut candidates = vec![String::from("nightly"), String::from("stable")];
+            candidates.extend(get_remote_versions(&config).await);
+            candidates
+        }
+        Some("update") => vec![String::from("nightly"), String::from("stable")],
+        _ => Vec::new(),
+    };
+
+    for candidate in candidates {
+        if candidate.starts_with(current) {
+            println!("{candidate}");
+        }
+    }
+    Ok(())
+}
+
+pub async fn refresh_remote_cache(client: &Client, config: &Config) -> Result<()> {
+    let tags = utils::get_available_versions(client).await?;
+
+    let cache_file = get_cache_file(config)?;
+    let temp_file = cache_file.with_extension("json.tmp");
+    fs::write(&temp_file, serde_json::to_string(&tags)?).await?;
+    fs::rename(temp_file, cache_file).await?;
+    Ok(())
+}
+
+fn get_cache_file(config: &Config) -> Result<PathBuf> {
+    Ok(utils::get_downloads_location(config)?.join(REMOTE_CACHE_FILE))
+}
+
+async fn get_installed_versions(config: &Config) -> Vec<String> {
+    let downloads_dir = match utils::get_downloads_location(config) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+    let mut entries = match fs::read_dir(downloads_dir).await {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut versions = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_dir = entry
+            .file_type()
+            .await
+            .is_ok_and(|file_type| file_type.is_dir());
+        match entry.file_name().into_string() {
+            Ok(name) if is_dir && name != "neovim-git" && name != utils::CACHE_DIR => {
+                versions.push(name)
+            }
+            _ => (),
+        }
+    }
+    versions.sort();
+    versions
+}
+
+async fn get_remote_versions(config: &Config) -> Vec<String> {
+    let cache_file = match get_cache_file(config) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+
+    let is_fresh = fs::metadata(&cache_file)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < REMOTE_CACHE_TTL);
+    if !is_fresh {
+        spawn_cache_refresh();
+    }
+
+    // A completion can't wait on the network, without a cache there's just nothing to offer yet
+    match fs::read_to_string(&cache_file).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn spawn_cache_refresh() {
+    let executable = match std::env::current_exe() {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    let _ = std::process::Command::new(executable)
+        .args(["_complete", "--refresh-cache"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+// Appended to the generated scripts so version arguments are completed by `bob _complete`
+pub fn get_dynamic_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => {
+            r#"
+_bob_dynamic() {
+    case "${COMP_WORDS[1]}" in
+        use|install|uninstall|rm|contents)
+            if [[ "${COMP_WORDS[COMP_CWORD]}" != -* && ${COMP_CWORD} -ge 2 ]]; then
+                local IFS=$'\n'
+                COMPREPLY=($(bob _complete "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+                return 0
+            fi
+            ;;
+    esac
+    _bob "$@"
+}
+
+complete -F _bob_dynamic -o bashdefault -o default bob
+"#
+        }
+        Shell::Zsh => {
+            r#"
+_bob_dynamic() {
+    if (( CURRENT >= 3 )) && [[ ${words[2]} == (use|install|uninstall|rm|contents) && ${words[CURRENT]} != -* ]]; then
+        local -a versions
+        versions=(${(f)"$(bob _complete "${(@)words[2,CURRENT]}" 2>/dev/null)"})
+        compadd -a versions
+    else
+        _bob "$@"
+    fi
+}
+
+compdef _bob_dynamic bob
+"#
+        }
+        Shell::Fish => {
+            r#"
+complete -c bob -n "__fish_seen_subcommand_from use install uninstall rm contents" -f -a "(bob _complete (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null)"
+"#
+        }
+        _ => "",
+    }
+}
@@ -0,0 +1,219 @@
+use super::{net, output, use_handler, utils};
+use crate::models::{CommandOutput, Config, ResolvedSetting};
+use anyhow::{anyhow, Result};
+use reqwest::Url;
+use serde_json::{json, Map, Value};
+use tokio::fs;
+use yansi::Paint;
+
+const REDACTED: &str = "***";
+// The variables reqwest reads a proxy from when none is configured
+const PROXY_ENV_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+pub async fn start(config: Config) -> Result<CommandOutput> {
+    let config_file = utils::get_config_file();
+    let settings = resolved_config(&config).await?;
+
+    if let Some(config_file) = &config_file {
+        output::print_human(Paint::new(format!("# {}", config_file.display())).dimmed());
+    }
+    for setting in &settings {
+        output::print_human(format!(
+            "{} = {} {}",
+            setting.name,
+            setting.value,
+            Paint::new(format!("({})", setting.source)).dimmed()
+        ));
+    }
+
+    Ok(CommandOutput {
+        action: String::from("config"),
+        paths: config_file.into_iter().collect(),
+        details: json!({ "settings": settings }),
+        ..Default::default()
+    })
+}
+
+// Every setting with the value bob actually ends up using, unset ones show their default.
+// Tokens, header values and proxy passwords never leave this function in the clear
+pub async fn resolved_config(config: &Config) -> Result<Vec<ResolvedSetting>> {
+    let file_keys = read_file_keys().await?;
+    let values = match serde_json::to_value(config)? {
+        Value::Object(value) => value,
+        _ => return Err(anyhow!("Config didn't serialize to an object")),
+    };
+
+    let profile_keys = match (&config.active_profile, &config.profiles) {
+        (Some(name), Some(profiles)) => match profiles.get(name).map(serde_json::to_value) {
+            Some(Ok(Value::Object(value))) => value,
+            _ => Map::new(),
+        },
+        _ => Map::new(),
+    };
+
+    let mut settings = Vec::new();
+    for (name, value) in values {
+        let is_set = |keys: &Map<String, Value>| keys.get(&name).is_some_and(|key| !key.is_null());
+        let (value, source) = if name == "github_token" {
+            get_token_setting(config, is_set(&profile_keys), is_set(&file_keys)).await
+        } else if !value.is_null() && is_set(&profile_keys) {
+            (
+                redact(&name, value),
+                format!(
+                    "profile {}",
+                    config.active_profile.as_deref().unwrap_or_default()
+                ),
+            )
+        } else if !value.is_null() && file_keys.contains_key(&name) {
+            (redact(&name, value), String::from("file"))
+        } else {
+            get_default(&name, config)?
+        };
+        settings.push(ResolvedSetting {
+            name,
+            value,
+            source,
+        });
+    }
+
+    Ok(settings)
+}
+
+// The token can also come from the environment or gh, which beat or back up the config's
+async fn get_token_setting(config: &Config, in_profile: bool, in_file: bool) -> (Value, String) {
+    if std::env::var(net::TOKEN_ENV_VAR).is_ok_and(|token| !token.is_empty()) {
+        (json!(REDACTED), format!("env {}", net::TOKEN_ENV_VAR))
+    } else if config
+        .github_token
+        .as_ref()
+        .is_some_and(|token| !token.is_empty())
+    {
+        let source = match (in_profile, &config.active_profile) {
+            (true, Some(name)) => format!("profile {name}"),
+            _ if in_file => String::from("file"),
+            _ => String::from("default"),
+        };
+        (json!(REDACTED), source)
+    } else if net::get_github_token(config).await.is_some() {
+        (json!(REDACTED), String::from("gh"))
+    } else {
+        (Value::Null, String::from("default"))
+    }
+}
+
+// Only which keys are present matters, the values were already parsed into the config
+async fn read_file_keys() -> Result<Map<String, Value>> {
+    let config_file = match utils::get_config_file() {
+        Some(value) => value,
+        None => return Ok(Map::new()),
+    };
+    let content = match fs::read_to_string(&config_file).await {
+        Ok(value) => value,
+        Err(_) => return Ok(Map::new()),
+    };
+    match serde_json::from_str(&content)? {
+        Value::Object(value) => Ok(value),
+        _ => Ok(Map::new()),
+    }
+}
+
+fn get_default(name: &str, config: &Config) -> Result<(Value, String)> {
+    let data_dir_source = || -> Result<String> { Ok(utils::get_data_dir_source()?.to_owned()) };
+
+    let default = match name {
+        "downloads_dir" => (
+            json!(utils::get_downloads_location(config)?),
+            data_dir_source()?,
+        ),
+        "installation_location" => (
+            json!(utils::get_installation_folder(config)?),
+            data_dir_source()?,
+        ),
+        "state_dir" => (
+            json!(utils::get_downloads_location(config)?),
+            String::from("default, same as downloads_dir"),
+        ),
+        "staging_dir" => (
+            json!(utils::get_downloads_location(config)?),
+            String::from("default, same as downloads_dir"),
+        ),
+        "proxy" => match PROXY_ENV_VARS
+            .iter()
+            .find_map(|var| std::env::var(var).ok().map(|value| (var, value)))
+        {
+            Some((var, value)) => (redact("proxy", json!(value)), format!("env {var}")),
+            None => (Value::Null, String::from("default")),
+        },
+        "enable_nightly_info" => (json!(true), String::from("default")),
+        "binary_mode" => (json!("755"), String::from("default")),
+        "downloader" => (json!("reqwest"), String::from("default")),
+        "use_strategy" => (
+            json!(use_handler::get_default_use_strategy()),
+            String::from("default"),
+        ),
+        "github_api_url" => (json!(net::DEFAULT_API_URL), String::from("default")),
+        "github_download_url" => (json!(net::DEFAULT_DOWNLOAD_URL), String::from("default")),
+        "danger_accept_invalid_certs"
+        | "preflight_check"
+        | "low_priority"
+        | "prefer_appimage"
+        | "require_passing_nightly"
+        | "use_gh_auth"
+        | "install_desktop_entry"
+        | "gui"
+        | "preserve_nightly_metadata"
+        | "notify"
+        | "offline"
+        | "per_arch_dirs" => (json!(false), String::from("default")),
+        _ => (Value::Null, String::from("default")),
+    };
+    Ok(default)
+}
+
+fn redact(name: &str, value: Value) -> Value {
+    match (name, value) {
+        ("extra_headers", Value::Object(headers)) => Value::Object(
+            headers
+                .into_iter()
+                .map(|(header, _)| (header, json!(REDACTED)))
+                .collect(),
+        ),
+        ("github_token", Value::String(_)) => json!(REDACTED),
+        ("profiles", Value::Object(profiles)) => Value::Object(
+            profiles
+                .into_iter()
+                .map(|(profile, settings)| {
+                    let settings = match settings {
+                        Value::Object(settings) => Value::Object(
+                            settings
+                                .into_iter()
+                                .filter(|(_, value)| !value.is_null())
+                                .map(|(name, value)| {
+                                    let value = redact(&name, value);
+                                    (name, value)
+                                })
+                                .collect(),
+                        ),
+                        settings => settings,
+                    };
+                    (profile, settings)
+                })
+                .collect(),
+        ),
+        ("proxy", Value::String(proxy)) => match Url::parse(&proxy) {
+            Ok(mut url) if url.password().is_some() => {
+                let _ = url.set_password(Some(REDACTED));
+                json!(url.to_string())
+            }
+            _ => json!(proxy),
+        },
+        (_, value) => value,
+    }
+}
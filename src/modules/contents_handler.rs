@@ -0,0 +1,28 @@
+use crate::models::{CommandOutput, Config, InputVersion};
+
+use super::{output, utils};
+use anyhow::Result;
+
+pub async fn start(version: InputVersion, config: Config) -> Result<CommandOutput> {
+    let version_dir = utils::get_downloads_folder(&config)
+        .await?
+        .join(utils::get_version_dir_name(&version));
+
+    let files = utils::list_version_files(&version, &config).await?;
+    for path in &files {
+        let depth = path.components().count() - 1;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if version_dir.join(path).is_dir() {
+            output::print_human(format!("{}{name}/", "  ".repeat(depth)));
+        } else {
+            output::print_human(format!("{}{name}", "  ".repeat(depth)));
+        }
+    }
+
+    Ok(CommandOutput {
+        action: String::from("contents"),
+        versions: vec![version.tag_name],
+        paths: files,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,312 @@
+#[cfg(windows)]
+use super::gui;
+use super::utils;
+use crate::models::Config;
+use anyhow::Result;
+use std::path::PathBuf;
+#[cfg(not(windows))]
+use tracing::warn;
+#[cfg(any(target_os = "linux", windows))]
+use {
+    anyhow::anyhow,
+    std::path::Path,
+    tokio::fs,
+    tokio::process::Command,
+    tracing::{debug, info},
+};
+
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "nvim.desktop";
+// Marks the entries bob wrote, an nvim.desktop from anything else is never touched
+#[cfg(target_os = "linux")]
+const MANAGED_KEY: &str = "X-Bob-Managed=true";
+#[cfg(windows)]
+const SHORTCUT_FILE: &str = "Neovim (bob).lnk";
+#[cfg(windows)]
+const GUI_SHORTCUT_FILE: &str = "Neovim Qt (bob).lnk";
+
+// Entries point through the installation folder rather than a version directory, so they
+// keep launching whichever version is used after the next switch
+pub async fn install_entry(config: &Config) -> Result<Option<PathBuf>> {
+    let installation_dir = utils::get_installation_folder(config)?;
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            install_desktop_file(&installation_dir).await
+        } else if #[cfg(windows)] {
+            install_shortcuts(&installation_dir, config.gui == Some(true)).await
+        } else {
+            let _ = installation_dir;
+            warn!("Launcher entries are only added on Linux and Windows");
+            Ok(None)
+        }
+    }
+}
+
+// The entry remove_entry would remove, without touching it
+pub async fn find_entry() -> Result<Option<PathBuf>> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            let desktop_file = get_applications_dir()?.join(DESKTOP_FILE);
+            Ok(match fs::read_to_string(&desktop_file).await {
+                Ok(content) if content.lines().any(|line| line == MANAGED_KEY) => Some(desktop_file),
+                _ => None,
+            })
+        } else if #[cfg(windows)] {
+            let shortcut = get_start_menu_dir()?.join(SHORTCUT_FILE);
+            Ok(fs::metadata(&shortcut).await.is_ok().then_some(shortcut))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+pub async fn remove_entry() -> Result<Option<PathBuf>> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            remove_desktop_file().await
+        } else if #[cfg(windows)] {
+            remove_shortcuts().await
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_applications_dir() -> Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(value) => Ok(value.join("applications")),
+        None => Err(anyhow!("Couldn't find the applications directory")),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn install_desktop_file(installation_dir: &Path) -> Result<Option<PathBuf>> {
+    let applications_dir = get_applications_dir()?;
+    let desktop_file = applications_dir.join(DESKTOP_FILE);
+    if let Ok(content) = fs::read_to_string(&desktop_file).await {
+        if !content.lines().any(|line| line == MANAGED_KEY) {
+            warn!(
+                "{} wasn't written by bob, leaving it alone",
+                desktop_file.display()
+            );
+            return Ok(None);
+        }
+    }
+
+    let binary = quote_exec_arg(&installation_dir.join("bin").join("nvim"));
+    let icon = match find_icon(installation_dir).await {
+        Some(value) => value.to_string_lossy().into_owned(),
+        None => String::from("nvim"),
+    };
+    let content = format!(
+        "[Desktop Entry]
+Type=Application
+Name=Neovim
+GenericName=Text Editor
+Comment=Edit text files with the Neovim version bob manages
+TryExec={}
+Exec={binary} %F
+Terminal=true
+Icon={icon}
+Categories=Utility;TextEditor;
+Keywords=Text;editor;
+MimeType=text/plain;text/x-makefile;text/x-c;text/x-c++;text/x-python;text/x-shellscript;
+StartupNotify=false
+{MANAGED_KEY}
+",
+        installation_dir.join("bin").join("nvim").display()
+    );
+
+    fs::create_dir_all(&applications_dir).await?;
+    if let Err(error) = fs::write(&desktop_file, content).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            desktop_file.display()
+        ));
+    }
+    info!("Added a desktop entry at {}", desktop_file.display());
+    update_desktop_database(&applications_dir).await;
+    Ok(Some(desktop_file))
+}
+
+#[cfg(target_os = "linux")]
+async fn remove_desktop_file() -> Result<Option<PathBuf>> {
+    let applications_dir = get_applications_dir()?;
+    let desktop_file = applications_dir.join(DESKTOP_FILE);
+    match fs::read_to_string(&desktop_file).await {
+        Ok(content) if content.lines().any(|line| line == MANAGED_KEY) => {
+            fs::remove_file(&desktop_file).await?;
+            update_desktop_database(&applications_dir).await;
+            Ok(Some(desktop_file))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(target_os = "linux")]
+// Release archives ship the icon in a hicolor tree, a scalable one is preferred over the
+// largest bitmap
+async fn find_icon(installation_dir: &Path) -> Option<PathBuf> {
+    let icons_dir = installation_dir.join("share").join("icons").join("hicolor");
+    let scalable = icons_dir.join("scalable").join("apps").join("nvim.svg");
+    if fs::metadata(&scalable).await.is_ok() {
+        return Some(scalable);
+    }
+
+    let mut largest: Option<(u32, PathBuf)> = None;
+    let mut sizes = fs::read_dir(&icons_dir).await.ok()?;
+    while let Ok(Some(entry)) = sizes.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let size = match name.split_once('x').and_then(|(size, _)| size.parse().ok()) {
+            Some(value) => value,
+            None => continue,
+        };
+        let icon = entry.path().join("apps").join("nvim.png");
+        if largest.as_ref().is_none_or(|(current, _)| size > *current)
+            && fs::metadata(&icon).await.is_ok()
+        {
+            largest = Some((size, icon));
+        }
+    }
+    largest.map(|(_, icon)| icon)
+}
+
+#[cfg(target_os = "linux")]
+// Exec arguments with spaces or quotes have to be quoted per the desktop entry spec
+fn quote_exec_arg(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if !path.contains(|c: char| c.is_whitespace() || "\"'\\$`".contains(c)) {
+        return path.into_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        if "\"`$\\".contains(c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(target_os = "linux")]
+// Launchers pick up new entries on their own once the cache is refreshed, systems without the
+// tool rescan the directory anyway
+async fn update_desktop_database(applications_dir: &Path) {
+    match Command::new("update-desktop-database")
+        .arg(applications_dir)
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => debug!("Updated the desktop database"),
+        Ok(output) => warn!(
+            "update-desktop-database failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            debug!("update-desktop-database isn't installed, skipping it")
+        }
+        Err(error) => warn!("Couldn't run update-desktop-database: {error}"),
+    }
+}
+
+#[cfg(windows)]
+fn get_start_menu_dir() -> Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(value) => Ok(value
+            .join("Microsoft")
+            .join("Windows")
+            .join("Start Menu")
+            .join("Programs")),
+        None => Err(anyhow!("Couldn't find the Start Menu directory")),
+    }
+}
+
+// Writing a .lnk by hand means implementing the shell link format, WScript.Shell does it for
+// us and overwrites the shortcut when it's already there. The bin folder is registered in
+// PATH too, a version used before this was opted into may have skipped that
+#[cfg(windows)]
+async fn install_shortcuts(installation_dir: &Path, gui: bool) -> Result<Option<PathBuf>> {
+    let bin_dir = installation_dir.join("bin");
+    if utils::add_to_user_path(&bin_dir)? {
+        info!("Added {} to the user PATH", bin_dir.display());
+    }
+
+    let start_menu_dir = get_start_menu_dir()?;
+    fs::create_dir_all(&start_menu_dir).await?;
+    let shortcut = start_menu_dir.join(SHORTCUT_FILE);
+    let binary = find_launcher(&bin_dir, utils::get_binary_name())
+        .await
+        .unwrap_or_else(|| bin_dir.join(utils::get_binary_name()));
+    create_shortcut(&shortcut, &binary, "Neovim managed by bob").await?;
+    info!("Added Neovim to the Start Menu");
+
+    if let (true, Some(gui_binary)) = (gui, find_launcher(&bin_dir, gui::get_binary_name()).await) {
+        let gui_shortcut = start_menu_dir.join(GUI_SHORTCUT_FILE);
+        create_shortcut(&gui_shortcut, &gui_binary, "Neovim Qt managed by bob").await?;
+        info!("Added Neovim Qt to the Start Menu");
+    }
+    Ok(Some(shortcut))
+}
+
+// With the shim strategy the bin folder only has nvim.cmd and friends, shortcuts launch those
+// just the same
+#[cfg(windows)]
+async fn find_launcher(bin_dir: &Path, binary_name: &str) -> Option<PathBuf> {
+    let shim = Path::new(binary_name).with_extension("cmd");
+    for path in [bin_dir.join(binary_name), bin_dir.join(shim)] {
+        if fs::metadata(&path).await.is_ok() {
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[cfg(windows)]
+async fn create_shortcut(shortcut: &Path, target: &Path, description: &str) -> Result<()> {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let script = format!(
+        "$shortcut = (New-Object -ComObject WScript.Shell).CreateShortcut({}); \
+         $shortcut.TargetPath = {}; \
+         $shortcut.WorkingDirectory = $env:USERPROFILE; \
+         $shortcut.Description = {}; \
+         $shortcut.Save()",
+        quote(&shortcut.display().to_string()),
+        quote(&target.display().to_string()),
+        quote(description)
+    );
+
+    let output = match Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .await
+    {
+        Ok(value) => value,
+        Err(error) => return Err(anyhow!("Failed to run powershell, reason: {error}")),
+    };
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to create {}, reason: {}",
+            shortcut.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    debug!("Created {}", shortcut.display());
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn remove_shortcuts() -> Result<Option<PathBuf>> {
+    let start_menu_dir = get_start_menu_dir()?;
+    let gui_shortcut = start_menu_dir.join(GUI_SHORTCUT_FILE);
+    if fs::metadata(&gui_shortcut).await.is_ok() {
+        fs::remove_file(&gui_shortcut).await?;
+    }
+    let shortcut = start_menu_dir.join(SHORTCUT_FILE);
+    if fs::metadata(&shortcut).await.is_err() {
+        return Ok(None);
+    }
+    fs::remove_file(&shortcut).await?;
+    Ok(Some(shortcut))
+}
@@ -0,0 +1,471 @@
+use crate::enums::CheckStatus;
+use crate::models::{CommandOutput, Config, HealthCheck};
+
+use super::{env_handler, net, output, state, utils};
+use anyhow::Result;
+use reqwest::Client;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+use yansi::Paint;
+
+pub async fn start(client: &Client, config: Config, fix: bool) -> Result<CommandOutput> {
+    let checks = vec![
+        check_downloads_dir(&config).await,
+        check_installation_dir(&config).await,
+        check_path(&config).await,
+        check_active_version(&config).await,
+        check_state(&config, fix).await,
+        check_ownership(&config).await,
+        check_github(client).await,
+        check_token(&config).await,
+        check_build_tools(),
+        check_leftovers(&config, fix).await,
+    ];
+
+    for check in &checks {
+        let label = match check.status {
+            CheckStatus::Pass => Paint::green("pass"),
+            CheckStatus::Warn => Paint::yellow("warn"),
+            CheckStatus::Fail => Paint::red("fail"),
+        };
+        output::print_human(format!("[{label}] {}: {}", check.name, check.message));
+        if let Some(remedy) = &check.remedy {
+            output::print_human(format!("       {}", Paint::new(remedy).dimmed()));
+        }
+    }
+
+    let worst = checks
+        .iter()
+        .map(|check| check.status)
+        .max()
+        .unwrap_or(CheckStatus::Pass);
+    Ok(CommandOutput {
+        action: String::from("doctor"),
+        details: json!({ "checks": checks }),
+        exit_code: match worst {
+            CheckStatus::Pass => 0,
+            CheckStatus::Warn => 3,
+            CheckStatus::Fail => 11,
+        },
+        ..Default::default()
+    })
+}
+
+fn pass(name: &'static str, message: impl Into<String>) -> HealthCheck {
+    HealthCheck {
+        name,
+        status: CheckStatus::Pass,
+        message: message.into(),
+        remedy: None,
+    }
+}
+
+fn problem(
+    name: &'static str,
+    status: CheckStatus,
+    message: impl Into<String>,
+    remedy: impl Into<String>,
+) -> HealthCheck {
+    HealthCheck {
+        name,
+        status,
+        message: message.into(),
+        remedy: Some(remedy.into()),
+    }
+}
+
+// Permissions alone don't say much on every platform, actually creating a file does
+async fn is_writable(directory: &Path) -> bool {
+    let probe = directory.join(".bob-doctor");
+    let is_writable = fs::write(&probe, b"").await.is_ok();
+    let _ = fs::remove_file(&probe).await;
+    is_writable
+}
+
+async fn check_downloads_dir(config: &Config) -> HealthCheck {
+    const NAME: &str = "downloads";
+    let downloads_dir = match utils::get_downloads_folder(config).await {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Fail,
+                error.to_string(),
+                "Set downloads_dir in the config to a folder you can write to",
+            )
+        }
+    };
+    if !is_writable(&downloads_dir).await {
+        return problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} isn't writable", downloads_dir.display()),
+            "Fix the folder's permissions or set downloads_dir to a folder you can write to",
+        );
+    }
+    pass(NAME, format!("{} is writable", downloads_dir.display()))
+}
+
+// The installation folder itself is replaced on every switch, its parent is what has to be
+// writable
+async fn check_installation_dir(config: &Config) -> HealthCheck {
+    const NAME: &str = "installation";
+    let installation_dir = match utils::get_installation_folder(config) {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Fail,
+                error.to_string(),
+                "Set installation_location in the config",
+            )
+        }
+    };
+    let parent = match installation_dir.parent() {
+        Some(value) => value,
+        None => return pass(NAME, installation_dir.display().to_string()),
+    };
+    if fs::metadata(parent).await.is_err() {
+        return problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} doesn't exist", parent.display()),
+            format!(
+                "Create {} or set installation_location elsewhere",
+                parent.display()
+            ),
+        );
+    }
+    if !is_writable(parent).await {
+        return problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} isn't writable", parent.display()),
+            "Fix the folder's permissions or set installation_location elsewhere",
+        );
+    }
+
+    // What use_strategy put there last time, compared to what's there now
+    let install_mode = state::get_install_mode(config).await.ok().flatten();
+    let is_link = fs::symlink_metadata(&installation_dir)
+        .await
+        .ok()
+        .map(|metadata| metadata.file_type().is_symlink());
+    match (install_mode, is_link) {
+        (Some(mode), Some(false)) if mode == "symlink" => problem(
+            NAME,
+            CheckStatus::Warn,
+            format!(
+                "{} should be bob's link but is a real folder",
+                installation_dir.display()
+            ),
+            "Move it out of the way and run `bob use` again",
+        ),
+        (Some(mode), Some(true)) if mode != "symlink" => problem(
+            NAME,
+            CheckStatus::Warn,
+            format!(
+                "{} was set up with {mode} but is a link now",
+                installation_dir.display()
+            ),
+            "Run `bob use` again",
+        ),
+        (Some(mode), Some(_)) => pass(
+            NAME,
+            format!("{} is set up with {mode}", installation_dir.display()),
+        ),
+        _ => pass(
+            NAME,
+            format!("{} can be linked", installation_dir.display()),
+        ),
+    }
+}
+
+async fn check_path(config: &Config) -> HealthCheck {
+    const NAME: &str = "path";
+    let bin_dir = match utils::get_installation_folder(config) {
+        Ok(value) => value.join("bin"),
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Fail,
+                error.to_string(),
+                "Set installation_location in the config",
+            )
+        }
+    };
+    let is_in_path = std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir == bin_dir));
+    if !is_in_path {
+        // Set up but not effective yet is the usual state right after `bob env --apply`
+        let remedy = match env_handler::find_configured_entry(config).await {
+            Some(entry) => format!("{entry} already adds it, restart your shell to pick it up"),
+            None => String::from("Run `bob env --apply` to add it for your shell"),
+        };
+        return problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} isn't in PATH", bin_dir.display()),
+            remedy,
+        );
+    }
+
+    match utils::find_shadowing_nvim(config) {
+        Ok(Some(shadowing)) => problem(
+            NAME,
+            CheckStatus::Warn,
+            format!(
+                "{} comes before {} in PATH",
+                shadowing.display(),
+                bin_dir.display()
+            ),
+            format!(
+                "Uninstall the other nvim or move {} earlier in PATH",
+                bin_dir.display()
+            ),
+        ),
+        _ => pass(NAME, format!("{} is in PATH", bin_dir.display())),
+    }
+}
+
+async fn check_active_version(config: &Config) -> HealthCheck {
+    const NAME: &str = "active version";
+    let used = match utils::get_current_version(config).await {
+        Ok(value) => value,
+        Err(_) => {
+            return problem(
+                NAME,
+                CheckStatus::Warn,
+                "No version is used",
+                "Run `bob use stable` to pick one",
+            )
+        }
+    };
+
+    let binary = match utils::get_used_binary_path(config) {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Fail,
+                error.to_string(),
+                "Set installation_location in the config",
+            )
+        }
+    };
+    match Command::new(&binary).arg("--version").output().await {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next().unwrap_or_default();
+            pass(NAME, format!("{used} runs, {version}"))
+        }
+        Ok(output) => problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} exited with {}", binary.display(), output.status),
+            format!("Reinstall it with `bob uninstall {used}` and `bob use {used}`"),
+        ),
+        Err(error) => problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} can't be run, reason: {error}", binary.display()),
+            format!("Run `bob use {used}` to link it again"),
+        ),
+    }
+}
+
+async fn check_state(config: &Config, fix: bool) -> HealthCheck {
+    const NAME: &str = "state";
+    if let Err(error) = state::load(config).await {
+        let remedy = match state::get_state_file(config).await {
+            Ok(state_file) => format!("Remove {} and run `bob use` again", state_file.display()),
+            Err(_) => String::from("Run `bob use` again"),
+        };
+        return problem(NAME, CheckStatus::Fail, error.to_string(), remedy);
+    }
+
+    match state::tidy(config, fix).await {
+        Ok(problems) if problems.is_empty() => pass(NAME, "Bob's state matches what's on disk"),
+        Ok(problems) if fix => pass(
+            NAME,
+            format!("Cleared stale entries: {}", problems.join(", ")),
+        ),
+        Ok(problems) => problem(
+            NAME,
+            CheckStatus::Warn,
+            problems.join(", "),
+            "Run `bob doctor --fix` to clear the stale entries",
+        ),
+        Err(error) => problem(
+            NAME,
+            CheckStatus::Fail,
+            error.to_string(),
+            "Run `bob use` again",
+        ),
+    }
+}
+
+async fn check_ownership(config: &Config) -> HealthCheck {
+    const NAME: &str = "ownership";
+    let downloads_dir = match utils::get_downloads_folder(config).await {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Fail,
+                error.to_string(),
+                "Set downloads_dir in the config",
+            )
+        }
+    };
+    let foreign = match find_foreign_owned(&downloads_dir) {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Warn,
+                format!(
+                    "Couldn't inspect {}, reason: {error}",
+                    downloads_dir.display()
+                ),
+                "Check the folder's permissions",
+            )
+        }
+    };
+    match foreign.first() {
+        Some(first) => problem(
+            NAME,
+            CheckStatus::Warn,
+            format!(
+                "{} files in {} aren't owned by you (e.g. {}), probably from running bob as root",
+                foreign.len(),
+                downloads_dir.display(),
+                first.display()
+            ),
+            format!("sudo chown -R $(id -un) {}", downloads_dir.display()),
+        ),
+        None => pass(NAME, "Every file bob manages is owned by you"),
+    }
+}
+
+async fn check_github(client: &Client) -> HealthCheck {
+    const NAME: &str = "github";
+    match net::check_connectivity(client).await {
+        Ok(_) => pass(NAME, "api.github.com is reachable"),
+        Err(error) => problem(
+            NAME,
+            CheckStatus::Fail,
+            error.to_string(),
+            "Check your connection, or set proxy and ca_cert_path in the config when behind a proxy",
+        ),
+    }
+}
+
+async fn check_token(config: &Config) -> HealthCheck {
+    const NAME: &str = "token";
+    match net::get_github_token(config).await {
+        Some(_) => pass(NAME, "Requests are authenticated with a GitHub token"),
+        None => pass(
+            NAME,
+            "No GitHub token, API requests are limited to 60 an hour",
+        ),
+    }
+}
+
+// Only building from a commit hash needs these, so a missing one is never more than a warning
+fn check_build_tools() -> HealthCheck {
+    const NAME: &str = "build tools";
+    let mut missing = ["git", "cmake"]
+        .into_iter()
+        .filter(|program| !utils::is_in_path(program))
+        .collect::<Vec<_>>();
+    if cfg!(unix) && !utils::is_in_path("clang") && !utils::is_in_path("gcc") {
+        missing.push("clang or gcc");
+    }
+
+    if missing.is_empty() {
+        return pass(
+            NAME,
+            "Everything needed to build from a commit hash is installed",
+        );
+    }
+    problem(
+        NAME,
+        CheckStatus::Warn,
+        format!(
+            "{} not found, building from a commit hash won't work",
+            missing.join(", ")
+        ),
+        "Install them with your package manager if you install commit hashes",
+    )
+}
+
+async fn check_leftovers(config: &Config, fix: bool) -> HealthCheck {
+    const NAME: &str = "leftovers";
+    let leftovers = match utils::find_leftovers(config).await {
+        Ok(value) => value,
+        Err(error) => {
+            return problem(
+                NAME,
+                CheckStatus::Warn,
+                error.to_string(),
+                "Check the downloads folder's permissions",
+            )
+        }
+    };
+    if leftovers.is_empty() {
+        return pass(NAME, "Nothing left behind by interrupted runs");
+    }
+
+    if fix {
+        if let Err(error) = utils::clean_leftovers(config).await {
+            return problem(
+                NAME,
+                CheckStatus::Warn,
+                format!("Failed to remove leftovers, reason: {error}"),
+                "Remove them by hand",
+            );
+        }
+        return pass(NAME, format!("Removed {} leftovers", leftovers.len()));
+    }
+    problem(
+        NAME,
+        CheckStatus::Warn,
+        format!(
+            "{} files left behind by interrupted runs, e.g. {}",
+            leftovers.len(),
+            leftovers[0].display()
+        ),
+        "Run `bob doctor --fix` to remove them",
+    )
+}
+
+fn find_foreign_owned(directory: &Path) -> Result<Vec<PathBuf>> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::fs::MetadataExt;
+
+            let uid = unsafe { libc::geteuid() };
+            let mut foreign = Vec::new();
+            let mut pending = vec![directory.to_path_buf()];
+            while let Some(path) = pending.pop() {
+                let metadata = std::fs::symlink_metadata(&path)?;
+                if metadata.uid() != uid {
+                    foreign.push(path.clone());
+                }
+                if metadata.is_dir() {
+                    for entry in std::fs::read_dir(&path)? {
+                        pending.push(entry?.path());
+                    }
+                }
+            }
+            Ok(foreign)
+        } else {
+            // Ownership problems from elevated runs show up as ACLs on windows, which doctor doesn't inspect
+            let _ = directory;
+            Ok(Vec::new())
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use super::{progress, utils};
+use crate::models::Config;
+use anyhow::{anyhow, Result};
+use futures_util::future::BoxFuture;
+use futures_util::stream::StreamExt;
+use reqwest::Response;
+use std::cmp::min;
+use std::path::Path;
+use tokio::{fs, io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+pub const DOWNLOADERS: [&str; 2] = ["reqwest", "aria2c"];
+
+// How many connections aria2c opens to the server, GitHub's CDN doesn't throttle this few
+const ARIA2C_CONNECTIONS: u32 = 8;
+
+// The asset is found with a regular request first so the fallbacks between archive types work the
+// same for every backend, a backend then gets that response and writes the asset to destination.
+// Whatever it writes is unpacked the same way afterwards
+pub trait Downloader: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn download<'a>(
+        &'a self,
+        response: Response,
+        destination: &'a Path,
+        progress: &'a progress::Sink,
+    ) -> BoxFuture<'a, Result<()>>;
+}
+
+// Picked through the downloader setting, an external tool that isn't installed falls back to
+// reqwest rather than failing the install
+pub fn get(config: &Config) -> Box<dyn Downloader> {
+    match config.downloader.as_deref() {
+        Some("aria2c") if utils::is_in_path("aria2c") => Box::new(Aria2c {
+            proxy: config.proxy.clone(),
+            ca_cert_path: config.ca_cert_path.clone(),
+            check_certificate: config.danger_accept_invalid_certs != Some(true),
+        }),
+        Some("aria2c") => {
+            warn!("aria2c isn't in PATH, downloading with reqwest instead");
+            Box::new(Reqwest)
+        }
+        _ => Box::new(Reqwest),
+    }
+}
+
+pub struct Reqwest;
+
+impl Downloader for Reqwest {
+    fn name(&self) -> &'static str {
+        "reqwest"
+    }
+
+    fn download<'a>(
+        &'a self,
+        response: Response,
+        destination: &'a Path,
+        progress: &'a progress::Sink,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let total_size = response.content_length().unwrap();
+            let mut response_bytes = response.bytes_stream();
+            progress.start(total_size);
+
+            let mut file = fs::File::create(destination).await?;
+            let mut downloaded: u64 = 0;
+            while let Some(item) = response_bytes.next().await {
+                let chunk = item?;
+                file.write_all(&chunk).await?;
+                let new = min(downloaded + (chunk.len() as u64), total_size);
+                progress.advance(new - downloaded);
+                downloaded = new;
+            }
+            file.flush().await?;
+            Ok(())
+        })
+    }
+}
+
+// Segmented downloads over several connections, for large assets on links where a single
+// connection doesn't reach full speed. The proxy and certificate settings are passed along since
+// aria2c doesn't go through bob's client
+pub struct Aria2c {
+    proxy: Option<String>,
+    ca_cert_path: Option<String>,
+    check_certificate: bool,
+}
+
+impl Downloader for Aria2c {
+    fn name(&self) -> &'static str {
+        "aria2c"
+    }
+
+    fn download<'a>(
+        &'a self,
+        response: Response,
+        destination: &'a Path,
+        progress: &'a progress::Sink,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // Redirects were already followed, the final URL needs no authentication
+            let url = response.url().to_string();
+            let total_size = response.content_length().unwrap_or_default();
+            drop(response);
+            progress.start(total_size);
+
+            let (dir, file_name) = match (destination.parent(), destination.file_name()) {
+                (Some(dir), Some(file_name)) => (dir, file_name),
+                _ => return Err(anyhow!("Can't download to {}", destination.display())),
+            };
+            let connections = format!("{ARIA2C_CONNECTIONS}");
+            let mut command = Command::new("aria2c");
+            command
+                .args([
+                    "--quiet=true",
+                    "--allow-overwrite=true",
+                    "--auto-file-renaming=false",
+                ])
+                .args(["--max-connection-per-server", &connections])
+                .args(["--split", &connections])
+                .arg("--dir")
+                .arg(dir)
+                .arg("--out")
+                .arg(file_name);
+            // Through the environment so credentials in it don't end up in the logged command
+            if let Some(proxy) = &self.proxy {
+                command.env("all_proxy", proxy);
+            }
+            if let Some(path) = &self.ca_cert_path {
+                command.args(["--ca-certificate", path]);
+            }
+            if !self.check_certificate {
+                command.arg("--check-certificate=false");
+            }
+            debug!("Downloading with aria2c over {ARIA2C_CONNECTIONS} connections");
+
+            let result = utils::handle_subprocess(command.arg(&url)).await;
+            // aria2c keeps its own record of a partial download next to it
+            let control_file = format!("{}.aria2", destination.display());
+            if fs::metadata(&control_file).await.is_ok() {
+                fs::remove_file(&control_file).await?;
+            }
+            result?;
+
+            progress.advance(total_size);
+            Ok(())
+        })
+    }
+}
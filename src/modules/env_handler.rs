@@ -0,0 +1,284 @@
+use super::{output, utils};
+use crate::enums::BobError;
+use crate::models::{CommandOutput, Config};
+use anyhow::{anyhow, Result};
+use clap_complete::Shell;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::info;
+
+const ADDED_MARKER: &str = "# Added by bob";
+const SHELLS: [Shell; 5] = [
+    Shell::Bash,
+    Shell::Zsh,
+    Shell::Fish,
+    Shell::Elvish,
+    Shell::PowerShell,
+];
+
+pub async fn start(config: Config, shell: Option<Shell>, apply: bool) -> Result<CommandOutput> {
+    let shell = match shell.or_else(detect_shell) {
+        Some(value) => value,
+        None => {
+            return Err(BobError::Usage(String::from(
+                "Couldn't tell which shell you use, pass it with --shell",
+            ))
+            .into())
+        }
+    };
+    let bin_dir = utils::get_installation_folder(&config)?.join("bin");
+    let line = get_path_line(shell, &bin_dir)?;
+
+    let (file, changed) = if !apply {
+        output::print_human(&line);
+        (None, false)
+    } else if cfg!(windows) {
+        (None, apply_to_registry(&bin_dir)?)
+    } else {
+        let rc_file = get_rc_file(shell)?;
+        let changed = append_line(&rc_file, &line, &bin_dir).await?;
+        if changed {
+            info!(
+                "Added {} to PATH in {}, restart your shell or run: {line}",
+                bin_dir.display(),
+                rc_file.display()
+            );
+        } else {
+            info!(
+                "{} already adds {} to PATH",
+                rc_file.display(),
+                bin_dir.display()
+            );
+        }
+        (Some(rc_file), changed)
+    };
+
+    Ok(CommandOutput {
+        action: String::from("env"),
+        paths: file.iter().cloned().collect(),
+        details: json!({
+            "shell": shell.to_string(),
+            "line": line,
+            "file": file,
+            "changed": changed,
+        }),
+        ..Default::default()
+    })
+}
+
+// Where PATH is already set up for the user's shell, whether or not the running shell has picked
+// it up yet
+pub async fn find_configured_entry(config: &Config) -> Option<String> {
+    let bin_dir = utils::get_installation_folder(config).ok()?.join("bin");
+
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            match utils::is_in_user_path(&bin_dir) {
+                Ok(true) => Some(String::from("your user PATH")),
+                _ => None,
+            }
+        } else {
+            let rc_file = get_rc_file(detect_shell()?).ok()?;
+            let content = fs::read_to_string(&rc_file).await.ok()?;
+            content
+                .contains(&bin_dir.display().to_string())
+                .then(|| rc_file.display().to_string())
+        }
+    }
+}
+
+// Startup files holding the lines `env --apply` wrote and the ones that mention the bin directory
+// some other way, whichever shell they belong to. Windows keeps PATH in the registry instead
+pub async fn find_path_setup(config: &Config) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let bin_dir = utils::get_installation_folder(config)?.join("bin");
+    let bin_dir = bin_dir.display().to_string();
+    let mut added = Vec::new();
+    let mut manual = Vec::new();
+    if cfg!(windows) {
+        return Ok((added, manual));
+    }
+
+    for shell in SHELLS {
+        let rc_file = get_rc_file(shell)?;
+        if added.contains(&rc_file) || manual.contains(&rc_file) {
+            continue;
+        }
+        let content = match fs::read_to_string(&rc_file).await {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        match strip_added_lines(&content, &bin_dir) {
+            Some(_) => added.push(rc_file),
+            None if content.contains(&bin_dir) => manual.push(rc_file),
+            None => (),
+        }
+    }
+    Ok((added, manual))
+}
+
+// Returns whether the file still mentions the bin directory afterwards, a file bob created for
+// the purpose is removed once nothing else is left in it
+pub async fn remove_path_setup(config: &Config, rc_file: &Path) -> Result<bool> {
+    let bin_dir = utils::get_installation_folder(config)?.join("bin");
+    let bin_dir = bin_dir.display().to_string();
+    let content = fs::read_to_string(rc_file).await?;
+    let content = match strip_added_lines(&content, &bin_dir) {
+        Some(value) => value,
+        None => return Ok(content.contains(&bin_dir)),
+    };
+
+    if content.trim().is_empty() && rc_file.file_name().is_some_and(|name| name == "bob.fish") {
+        fs::remove_file(rc_file).await?;
+        return Ok(false);
+    }
+    if let Err(error) = fs::write(rc_file, &content).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            rc_file.display()
+        ));
+    }
+    Ok(content.contains(&bin_dir))
+}
+
+// Only the marker comment together with the PATH line below it is bob's, along with the blank
+// line append_line put before them
+fn strip_added_lines(content: &str, bin_dir: &str) -> Option<String> {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut changed = false;
+    let mut iter = content.lines().peekable();
+    while let Some(line) = iter.next() {
+        if line == ADDED_MARKER && iter.peek().is_some_and(|next| next.contains(bin_dir)) {
+            iter.next();
+            if lines.last().is_some_and(|last| last.is_empty()) {
+                lines.pop();
+            }
+            changed = true;
+            continue;
+        }
+        lines.push(line);
+    }
+    if !changed {
+        return None;
+    }
+
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    Some(content)
+}
+
+fn detect_shell() -> Option<Shell> {
+    if cfg!(windows) {
+        return Some(Shell::PowerShell);
+    }
+    let shell = std::env::var("SHELL").ok()?;
+    match Path::new(&shell).file_name()?.to_str()? {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "elvish" => Some(Shell::Elvish),
+        "pwsh" | "powershell" => Some(Shell::PowerShell),
+        _ => None,
+    }
+}
+
+fn get_path_line(shell: Shell, bin_dir: &Path) -> Result<String> {
+    let bin_dir = bin_dir.display();
+    let line = match shell {
+        Shell::Bash | Shell::Zsh => format!("export PATH=\"{bin_dir}:$PATH\""),
+        Shell::Fish => format!("set -gx PATH \"{bin_dir}\" $PATH"),
+        Shell::Elvish => format!("set paths = [\"{bin_dir}\" $@paths]"),
+        Shell::PowerShell => {
+            format!("$env:PATH = \"{bin_dir}\" + [IO.Path]::PathSeparator + $env:PATH")
+        }
+        _ => return Err(anyhow!("{shell} isn't supported yet")),
+    };
+    Ok(line)
+}
+
+fn get_config_home() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(get_home()?.join(".config"))
+}
+
+fn get_home() -> Result<PathBuf> {
+    match dirs::home_dir() {
+        Some(value) => Ok(value),
+        None => Err(anyhow!(
+            "Couldn't find your home directory, add the line printed by `bob env` yourself"
+        )),
+    }
+}
+
+// Login shells on macOS read .bash_profile instead of .bashrc
+fn get_rc_file(shell: Shell) -> Result<PathBuf> {
+    let rc_file = match shell {
+        Shell::Bash if cfg!(target_os = "macos") => get_home()?.join(".bash_profile"),
+        Shell::Bash => get_home()?.join(".bashrc"),
+        Shell::Zsh => match std::env::var_os("ZDOTDIR") {
+            Some(value) => PathBuf::from(value).join(".zshrc"),
+            None => get_home()?.join(".zshrc"),
+        },
+        Shell::Fish => get_config_home()?.join("fish/conf.d/bob.fish"),
+        Shell::Elvish => get_config_home()?.join("elvish/rc.elv"),
+        Shell::PowerShell => get_config_home()?.join("powershell/Microsoft.PowerShell_profile.ps1"),
+        _ => return Err(anyhow!("{shell} isn't supported yet")),
+    };
+    Ok(rc_file)
+}
+
+// Any mention of the bin directory counts as set up, users often write their own PATH lines
+async fn append_line(rc_file: &Path, line: &str, bin_dir: &Path) -> Result<bool> {
+    let content = match fs::read_to_string(rc_file).await {
+        Ok(value) => value,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => {
+            return Err(anyhow!(
+                "Failed to read {}, reason: {error}",
+                rc_file.display()
+            ))
+        }
+    };
+    if content.contains(&bin_dir.display().to_string()) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = rc_file.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let separator = if content.is_empty() {
+        ""
+    } else if content.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    let content = format!("{content}{separator}{ADDED_MARKER}\n{line}\n");
+    if let Err(error) = fs::write(rc_file, content).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            rc_file.display()
+        ));
+    }
+    Ok(true)
+}
+
+fn apply_to_registry(bin_dir: &Path) -> Result<bool> {
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            let changed = utils::add_to_user_path(bin_dir)?;
+            if changed {
+                info!("Added {} to your user PATH, open a new terminal to use it", bin_dir.display());
+            } else {
+                info!("{} is already in your user PATH", bin_dir.display());
+            }
+            Ok(changed)
+        } else {
+            Err(anyhow!("There's no registry to add {} to", bin_dir.display()))
+        }
+    }
+}
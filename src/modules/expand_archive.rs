@@ -1,66 +1,297 @@
 use anyhow::{anyhow, Result};
-use indicatif::{ProgressBar, ProgressStyle};
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use std::{fs, io};
+use tracing::{debug, warn};
 
+use crate::enums::BobError;
 use crate::models::LocalVersion;
+use crate::modules::progress::{self, ProgressSink};
+use crate::modules::utils;
 
-pub async fn start(file: LocalVersion) -> Result<()> {
+pub const STAGING_EXTENSION: &str = "extracting";
+
+// The archive is expanded into a staging directory and only moved over the version directory
+// once it's complete, so a failed extraction leaves the installed version alone. staging_dir
+// moves that directory elsewhere, e.g. a tmpfs, it defaults to the downloads folder itself
+pub async fn start(
+    file: LocalVersion,
+    strip_prefix: Option<String>,
+    staging_dir: Option<PathBuf>,
+    progress: progress::Sink,
+) -> Result<()> {
+    let version_dir = file.path.join(&file.file_name);
+    let staging = staging_dir
+        .unwrap_or_else(|| file.path.clone())
+        .join(format!("{}.{STAGING_EXTENSION}", file.file_name));
+    debug!(
+        "Expanding {}.{} in {}",
+        file.file_name,
+        file.file_format,
+        staging.display()
+    );
     let temp_file = file.clone();
-    match tokio::task::spawn_blocking(move || match expand(temp_file) {
-        Ok(_) => Ok(()),
-        Err(error) => Err(anyhow!(error)),
+    let temp_staging = staging.clone();
+    let result = match tokio::task::spawn_blocking(move || {
+        // Left behind by an extraction that was killed
+        if fs::metadata(&temp_staging).is_ok() {
+            fs::remove_dir_all(&temp_staging)?;
+        }
+        if temp_file.file_format == utils::APPIMAGE_FILE_TYPE {
+            expand_appimage(temp_file, &temp_staging, progress.as_ref())?;
+        } else {
+            expand(
+                temp_file,
+                &temp_staging,
+                strip_prefix.as_deref().map(Path::new),
+                progress.as_ref(),
+            )?;
+        }
+        move_into_place(&temp_staging, &version_dir)
     })
     .await
     {
-        Ok(_) => (),
-        Err(error) => return Err(anyhow!(error)),
+        Ok(value) => value,
+        Err(error) => Err(anyhow!(error)),
+    };
+    let archive = format!("{}.{}", file.file_name, file.file_format);
+    let archive_path = file.path.join(&archive);
+    if let Err(error) = result {
+        if fs::metadata(&staging).is_ok() {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+        }
+        // Extracting it again would fail the same way, the next install downloads it afresh
+        if let Err(error) = tokio::fs::remove_file(&archive_path).await {
+            debug!(
+                "Couldn't remove {}, reason: {error}",
+                archive_path.display()
+            );
+        }
+        return Err(BobError::ExtractionFailed {
+            archive,
+            reason: error.to_string(),
+        }
+        .into());
     }
-    tokio::fs::remove_file(format!(
-        "{}/{}.{}",
-        file.path, file.file_name, file.file_format
-    ))
-    .await?;
+    tokio::fs::remove_file(archive_path).await?;
+    Ok(())
+}
+
+// A staging directory on another filesystem can't be renamed into place, its contents are
+// copied over instead which costs back some of the time extracting there saved
+fn move_into_place(staging: &Path, version_dir: &Path) -> Result<()> {
+    if fs::metadata(version_dir).is_ok() {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                utils::retry_on_lock(version_dir, || fs::remove_dir_all(version_dir))?;
+            } else {
+                fs::remove_dir_all(version_dir)?;
+            }
+        }
+    }
+
+    match fs::rename(staging, version_dir) {
+        Ok(()) => Ok(()),
+        Err(error) if is_cross_device(&error) => {
+            warn!(
+                "{} is on a different filesystem than {}, copying the extracted files instead of moving them",
+                staging.display(),
+                version_dir.display()
+            );
+            utils::copy_dir(staging, version_dir)?;
+            fs::remove_dir_all(staging)?;
+            Ok(())
+        }
+        Err(error) => Err(anyhow!(
+            "Failed to move {} to {}, reason: {error}",
+            staging.display(),
+            version_dir.display()
+        )),
+    }
+}
+
+fn is_cross_device(error: &io::Error) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            // ERROR_NOT_SAME_DEVICE
+            error.raw_os_error() == Some(17)
+        } else {
+            error.raw_os_error() == Some(libc::EXDEV)
+        }
+    }
+}
+
+fn record_top_level(top_level: &mut Option<String>, entry: &Path) -> Result<()> {
+    let name = match entry.components().find_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    }) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    match top_level {
+        None => *top_level = Some(name),
+        Some(existing) if *existing == name => (),
+        Some(existing) => {
+            return Err(anyhow!(
+                "Archive has more than one top level entry, found {existing} and {name}"
+            ))
+        }
+    }
+    Ok(())
+}
+
+// Archives can come from any mirror or fork, an entry climbing out with .. or naming an absolute
+// path would otherwise be written wherever the user can write. Entries are resolved lexically,
+// the extraction only ever creates plain files and directories so there's no link to follow
+fn sanitize_entry_path(entry: &Path) -> Result<PathBuf> {
+    let mut sanitized = PathBuf::new();
+    for component in entry.components() {
+        match component {
+            Component::Normal(name) => sanitized.push(name),
+            Component::CurDir => (),
+            Component::ParentDir if sanitized.pop() => (),
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(anyhow!(
+                    "Refusing to extract archive entry {}, it points outside the version directory. The archive may have been tampered with",
+                    entry.display()
+                ))
+            }
+        }
+    }
+    Ok(sanitized)
+}
+
+// Where an entry goes inside the version directory, None for the wrapper directories themselves.
+// Without a configured prefix the one top level directory is stripped later by normalize_layout,
+// forks that nest their files deeper or name the wrapper differently can set the prefix instead
+fn get_entry_path(
+    entry: &Path,
+    strip_prefix: Option<&Path>,
+    top_level: &mut Option<String>,
+) -> Result<Option<PathBuf>> {
+    let entry = sanitize_entry_path(entry)?;
+    let prefix = match strip_prefix {
+        Some(value) => value,
+        None => {
+            record_top_level(top_level, &entry)?;
+            return Ok(Some(entry));
+        }
+    };
+
+    match entry.strip_prefix(prefix) {
+        Ok(relative) if relative.as_os_str().is_empty() => Ok(None),
+        Ok(relative) => Ok(Some(Path::new(utils::get_platform_name()).join(relative))),
+        Err(_) if prefix.starts_with(&entry) => Ok(None),
+        Err(_) => Err(anyhow!(
+            "Archive entry {} isn't inside archive_strip_prefix {}",
+            entry.display(),
+            prefix.display()
+        )),
+    }
+}
+
+// Entries that only differ by case overwrite each other on case-insensitive filesystems
+// (the default on macOS and Windows), which otherwise surfaces as a baffling io error
+fn check_case_collision(seen_entries: &mut HashMap<String, String>, entry: &Path) -> Result<()> {
+    if !cfg!(any(target_os = "macos", target_os = "windows")) {
+        return Ok(());
+    }
+    find_case_collision(seen_entries, entry)
+}
+
+fn find_case_collision(seen_entries: &mut HashMap<String, String>, entry: &Path) -> Result<()> {
+    let entry = entry.to_string_lossy().trim_end_matches('/').to_string();
+    match seen_entries.get(&entry.to_lowercase()) {
+        Some(existing) if *existing != entry => Err(anyhow!(
+            "Archive entries {existing} and {entry} only differ by case and can't both be extracted on a case-insensitive filesystem"
+        )),
+        Some(_) => Ok(()),
+        None => {
+            seen_entries.insert(entry.to_lowercase(), entry);
+            Ok(())
+        }
+    }
+}
+
+// Upstream renames its archive folders every now and then (nvim-osx64, nvim-macos, Neovim...),
+// so whatever the archive expanded to gets renamed to the name bob uses everywhere else
+fn normalize_layout(
+    downloaded_file: &LocalVersion,
+    version_dir: &Path,
+    top_level: Option<String>,
+) -> Result<()> {
+    let platform = utils::get_platform_name();
+    let top_level = match top_level {
+        Some(value) => value,
+        None => {
+            return Err(anyhow!(
+                "Archive {}.{} is empty",
+                downloaded_file.file_name,
+                downloaded_file.file_format
+            ))
+        }
+    };
+
+    if top_level != platform {
+        fs::rename(version_dir.join(&top_level), version_dir.join(platform))?;
+    }
+
+    let binary = version_dir
+        .join(platform)
+        .join("bin")
+        .join(utils::get_binary_name());
+    if !binary.is_file() {
+        return Err(anyhow!(
+            "Archive {}.{} doesn't contain bin/{}",
+            downloaded_file.file_name,
+            downloaded_file.file_format,
+            utils::get_binary_name()
+        ));
+    }
+    progress::emit("verify", 1, 1);
     Ok(())
 }
 
 // TODO: Refactor
 
 #[cfg(target_family = "windows")]
-fn expand(downloaded_file: LocalVersion) -> Result<()> {
+fn expand(
+    downloaded_file: LocalVersion,
+    target: &Path,
+    strip_prefix: Option<&Path>,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
     use zip::ZipArchive;
 
-    if fs::metadata(&downloaded_file.file_name).is_ok() {
-        fs::remove_dir_all(&downloaded_file.file_name)?;
-    }
-
-    let file = File::open(format!(
+    let file = File::open(downloaded_file.path.join(format!(
         "{}.{}",
         downloaded_file.file_name, downloaded_file.file_format
-    ))?;
+    )))?;
 
     let mut archive = ZipArchive::new(file)?;
     let totalsize: u64 = archive.len() as u64;
 
-    let pb = ProgressBar::new(totalsize);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}",
-            )
-            .progress_chars("█  "),
-    );
-    pb.set_message("Expanding archive");
+    progress.start(totalsize);
+    progress.message("Expanding archive");
 
-    std::fs::create_dir(downloaded_file.file_name.clone())?;
+    std::fs::create_dir_all(target)?;
 
     let mut downloaded: u64 = 0;
+    let mut top_level = None;
+    let mut seen_entries = HashMap::new();
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
-        let temp = &format!("{}/{}", downloaded_file.file_name, file.name());
-        let outpath = Path::new(temp);
+        check_case_collision(&mut seen_entries, Path::new(file.name()))?;
+        let entry_path = match get_entry_path(Path::new(file.name()), strip_prefix, &mut top_level)?
+        {
+            Some(value) => value,
+            None => continue,
+        };
+        let outpath = &target.join(entry_path);
 
         if file.is_dir() {
             fs::create_dir_all(outpath)?;
@@ -70,37 +301,37 @@ fn expand(downloaded_file: LocalVersion) -> Result<()> {
                     fs::create_dir_all(parent)?;
                 }
             }
-            let mut outfile = fs::File::create(outpath)?;
+            let mut outfile = utils::retry_on_lock(outpath, || fs::File::create(outpath))?;
             io::copy(&mut file, &mut outfile)?;
         }
         let new = min(downloaded + 1, totalsize);
+        progress.advance(new - downloaded);
         downloaded = new;
-        pb.set_position(new);
     }
-    pb.finish_with_message(format!(
-        "Finished unzipping to {}/{}",
-        downloaded_file.path, downloaded_file.file_name
-    ));
+    progress.message(&format!("Finished unzipping to {}", target.display()));
+    progress.finish();
 
+    if strip_prefix.is_some() {
+        top_level = Some(utils::get_platform_name().to_string());
+    }
+    normalize_layout(&downloaded_file, target, top_level)?;
     Ok(())
 }
 
 #[cfg(target_family = "unix")] // I don't know if its worth making both expand functions into one function, but the API difference will cause so much if statements
-fn expand(downloaded_file: LocalVersion) -> Result<()> {
+fn expand(
+    downloaded_file: LocalVersion,
+    target: &Path,
+    strip_prefix: Option<&Path>,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
     use flate2::read::GzDecoder;
-    use std::os::unix::fs::PermissionsExt;
     use tar::Archive;
 
-    use crate::modules::utils;
-
-    if fs::metadata(&downloaded_file.file_name).is_ok() {
-        fs::remove_dir_all(&downloaded_file.file_name)?;
-    }
-
-    let file = match File::open(format!(
+    let file = match File::open(downloaded_file.path.join(format!(
         "{}.{}",
         downloaded_file.file_name, downloaded_file.file_format
-    )) {
+    ))) {
         Ok(value) => value,
         Err(error) => {
             return Err(anyhow!(
@@ -114,22 +345,22 @@ fn expand(downloaded_file: LocalVersion) -> Result<()> {
     let mut archive = Archive::new(decompress_stream);
 
     let totalsize = 1692; // hard coding this is pretty unwise, but you cant get the length of an archive in tar-rs unlike zip-rs
-    let pb = ProgressBar::new(totalsize);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}",
-            )
-            .progress_chars("█  "),
-    );
-    pb.set_message("Expanding archive");
+    progress.start(totalsize);
+    progress.message("Expanding archive");
 
     let mut downloaded: u64 = 0;
+    let mut top_level = None;
+    let mut seen_entries = HashMap::new();
     for file in archive.entries()? {
         match file {
             Ok(mut file) => {
-                let temp = &format!("{}/{}", downloaded_file.file_name, file.path()?.display());
-                let outpath = Path::new(temp);
+                check_case_collision(&mut seen_entries, &file.path()?)?;
+                let entry_path = match get_entry_path(&file.path()?, strip_prefix, &mut top_level)?
+                {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let outpath = &target.join(entry_path);
 
                 let file_name = format!("{}", file.path()?.display()); // file.path()?.is_dir() always returns false... weird
                 if file_name.ends_with('/') {
@@ -144,26 +375,348 @@ fn expand(downloaded_file: LocalVersion) -> Result<()> {
                     io::copy(&mut file, &mut outfile)?;
                 }
                 let new = min(downloaded + 1, totalsize);
+                progress.advance(new - downloaded);
                 downloaded = new;
-                pb.set_position(new);
             }
-            Err(error) => println!("{error}"),
+            Err(error) => crate::modules::output::print_human(error),
         }
     }
-    pb.finish_with_message(format!(
-        "Finished expanding to {}/{}",
-        downloaded_file.path, downloaded_file.file_name
+    progress.message(&format!("Finished expanding to {}", target.display()));
+    progress.finish();
+    if strip_prefix.is_some() {
+        top_level = Some(utils::get_platform_name().to_string());
+    }
+    normalize_layout(&downloaded_file, target, top_level)
+}
+
+// AppImages extract to squashfs-root/usr, which has the same bin/share layout as the tarball
+#[cfg(target_family = "unix")]
+fn expand_appimage(
+    downloaded_file: LocalVersion,
+    version_dir: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::create_dir_all(version_dir)?;
+
+    let appimage = downloaded_file.path.join(format!(
+        "{}.{}",
+        downloaded_file.file_name, downloaded_file.file_format
     ));
-    if fs::metadata(format!("{}/nvim-osx64", downloaded_file.file_name)).is_ok() {
-        fs::rename(
-            format!("{}/nvim-osx64", downloaded_file.file_name),
+    fs::set_permissions(&appimage, fs::Permissions::from_mode(0o755))?;
+
+    progress.start(1);
+    progress.message("Extracting AppImage");
+    let output = std::process::Command::new(&appimage)
+        .arg("--appimage-extract")
+        .current_dir(version_dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to extract {}, reason: {}",
+            appimage.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    progress.advance(1);
+    progress.finish();
+
+    let extracted = version_dir.join("squashfs-root");
+    fs::rename(
+        extracted.join("usr"),
+        version_dir.join(utils::get_platform_name()),
+    )?;
+    fs::remove_dir_all(extracted)?;
+
+    normalize_layout(
+        &downloaded_file,
+        version_dir,
+        Some(utils::get_platform_name().to_string()),
+    )
+}
+
+#[cfg(target_family = "windows")]
+fn expand_appimage(
+    downloaded_file: LocalVersion,
+    _version_dir: &Path,
+    _progress: &dyn ProgressSink,
+) -> Result<()> {
+    Err(anyhow!(
+        "{}.{} is an AppImage, which can't be used on windows",
+        downloaded_file.file_name,
+        downloaded_file.file_format
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+    use std::io::{Cursor, Write};
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn build_zip(entries: &[&str]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for entry in entries {
+            writer.start_file(*entry, FileOptions::default()).unwrap();
+            writer.write_all(b"").unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn find_collision(archive: Vec<u8>) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(archive))?;
+        let mut seen_entries = HashMap::new();
+        for i in 0..archive.len() {
+            find_case_collision(&mut seen_entries, Path::new(archive.by_index(i)?.name()))?;
+        }
+        Ok(())
+    }
+
+    // Lays entries out the way expand does, directories end with a /
+    fn lay_out(version_dir: &Path, entries: &[&str], strip_prefix: Option<&str>) -> Result<()> {
+        let strip_prefix = strip_prefix.map(Path::new);
+        let mut top_level = None;
+        for entry in entries {
+            let path = match get_entry_path(Path::new(entry), strip_prefix, &mut top_level)? {
+                Some(value) => version_dir.join(value),
+                None => continue,
+            };
+            if entry.ends_with('/') {
+                fs::create_dir_all(path)?;
+            } else {
+                fs::create_dir_all(path.parent().unwrap())?;
+                fs::write(path, "")?;
+            }
+        }
+        if strip_prefix.is_some() {
+            top_level = Some(utils::get_platform_name().to_string());
+        }
+        let downloaded_file = LocalVersion {
+            file_name: String::from("v0.9.5"),
+            file_format: utils::get_file_type().to_string(),
+            path: version_dir.to_path_buf(),
+        };
+        normalize_layout(&downloaded_file, version_dir, top_level)
+    }
+
+    fn binary_path(version_dir: &Path) -> PathBuf {
+        version_dir
+            .join(utils::get_platform_name())
+            .join("bin")
+            .join(utils::get_binary_name())
+    }
+
+    #[test]
+    fn any_wrapper_name_is_renamed_to_the_platform() {
+        let dir = TestDir::new("expand-wrappers");
+        let binary = format!("bin/{}", utils::get_binary_name());
+        for wrapper in [
+            "nvim-osx64",
             "nvim-macos",
-        )?;
+            "Neovim",
+            "nvim-linux64",
+            "nvim-win64",
+        ] {
+            let version_dir = dir.path().join(wrapper);
+            let entries = [
+                format!("{wrapper}/"),
+                format!("{wrapper}/bin/"),
+                format!("{wrapper}/{binary}"),
+                format!("{wrapper}/share/nvim/runtime/doc/tags"),
+            ];
+            let entries = entries.iter().map(String::as_str).collect::<Vec<_>>();
+            lay_out(&version_dir, &entries, None).unwrap();
+            assert!(binary_path(&version_dir).is_file(), "{wrapper}");
+            assert!(!version_dir.join(wrapper).exists() || wrapper == utils::get_platform_name());
+        }
+    }
+
+    #[test]
+    fn strip_prefix_drops_nested_wrappers() {
+        let dir = TestDir::new("expand-strip-prefix");
+        let version_dir = dir.path().join("v0.9.5");
+        let binary = format!("fork-1.2/build/nvim/bin/{}", utils::get_binary_name());
+        lay_out(
+            &version_dir,
+            &[
+                "fork-1.2/",
+                "fork-1.2/build/",
+                "fork-1.2/build/nvim/",
+                &binary,
+                "fork-1.2/build/nvim/share/nvim/runtime/doc/tags",
+            ],
+            Some("fork-1.2/build/nvim"),
+        )
+        .unwrap();
+        assert!(binary_path(&version_dir).is_file());
+        assert!(version_dir
+            .join(utils::get_platform_name())
+            .join("share/nvim/runtime/doc/tags")
+            .is_file());
+        assert!(!version_dir.join("fork-1.2").exists());
+    }
+
+    #[test]
+    fn entries_outside_the_prefix_are_refused() {
+        let dir = TestDir::new("expand-outside-prefix");
+        let error = lay_out(
+            &dir.path().join("v0.9.5"),
+            &["fork/build/nvim/bin/nvim", "fork/README.md"],
+            Some("fork/build/nvim"),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("isn't inside archive_strip_prefix"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn several_top_level_entries_are_refused() {
+        let dir = TestDir::new("expand-top-levels");
+        let error = lay_out(
+            &dir.path().join("v0.9.5"),
+            &["nvim-linux64/bin/nvim", "nvim-macos/bin/nvim"],
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("more than one top level entry"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn entries_differing_by_case_collide() {
+        let archive = build_zip(&[
+            "nvim/share/nvim/runtime/doc/Tags",
+            "nvim/share/nvim/runtime/doc/tags",
+        ]);
+        let error = find_collision(archive).unwrap_err().to_string();
+        assert!(error.contains("only differ by case"), "{error}");
+    }
+
+    #[test]
+    fn distinct_entries_dont_collide() {
+        let archive = build_zip(&["nvim/bin/nvim", "nvim/share/nvim/runtime/doc/tags"]);
+        assert!(find_collision(archive).is_ok());
+    }
+
+    #[test]
+    fn entries_climbing_out_are_refused() {
+        for entry in [
+            "../evil",
+            "nvim-linux64/../../evil",
+            "nvim-linux64/bin/../../../evil",
+            "./../evil",
+        ] {
+            let error = sanitize_entry_path(Path::new(entry)).err().unwrap();
+            assert!(error.to_string().contains("points outside"), "{entry}");
+        }
+    }
+
+    #[test]
+    fn dots_inside_the_version_directory_resolve() {
+        for (entry, expected) in [
+            ("nvim-linux64/share/../bin/nvim", "nvim-linux64/bin/nvim"),
+            ("./nvim-linux64/bin/./nvim", "nvim-linux64/bin/nvim"),
+            ("nvim-linux64/bin/..", "nvim-linux64"),
+        ] {
+            assert_eq!(
+                sanitize_entry_path(Path::new(entry)).unwrap(),
+                PathBuf::from(expected),
+                "{entry}"
+            );
+        }
+    }
+
+    #[test]
+    fn absolute_entries_are_refused() {
+        let mut top_level = None;
+        for entry in ["/etc/passwd", "/nvim-linux64/bin/nvim"] {
+            assert!(sanitize_entry_path(Path::new(entry)).is_err(), "{entry}");
+            assert!(
+                get_entry_path(Path::new(entry), None, &mut top_level).is_err(),
+                "{entry}"
+            );
+            assert!(
+                get_entry_path(Path::new(entry), Some(Path::new("nvim")), &mut top_level).is_err(),
+                "{entry}"
+            );
+        }
+        assert_eq!(top_level, None);
+    }
+
+    // Only Windows reads a drive or UNC prefix out of a path, elsewhere the whole name is a single
+    // file name that stays inside the version directory
+    #[test]
+    fn drive_prefixed_entries_stay_inside() {
+        for entry in [
+            r"C:\Windows\evil.exe",
+            r"C:evil.exe",
+            r"\\server\share\evil.exe",
+        ] {
+            let result = sanitize_entry_path(Path::new(entry));
+            if cfg!(windows) {
+                assert!(result.is_err(), "{entry}");
+            } else {
+                let sanitized = result.unwrap();
+                assert!(sanitized.is_relative(), "{entry}");
+                assert_eq!(sanitized.components().count(), 1, "{entry}");
+            }
+        }
+    }
+
+    // tar::Builder refuses .. in names, so the header is written by hand like a crafted archive
+    #[cfg(unix)]
+    fn build_tarball(entries: &[&str]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for entry in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..entry.len()].copy_from_slice(entry.as_bytes());
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, &b"evil"[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_extractions_remove_the_archive() {
+        use std::sync::Arc;
+
+        let dir = TestDir::new("expand-failed");
+        let downloads_dir = dir.path().join("downloads");
+        fs::create_dir_all(&downloads_dir).unwrap();
+        fs::write(
+            downloads_dir.join("v0.9.4.tar.gz"),
+            build_tarball(&["nvim-linux64/bin/nvim", "nvim-linux64/../../evil"]),
+        )
+        .unwrap();
+
+        let error = start(
+            LocalVersion {
+                file_name: String::from("v0.9.4"),
+                file_format: String::from("tar.gz"),
+                path: downloads_dir.clone(),
+            },
+            None,
+            None,
+            Arc::new(progress::NoProgress),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("points outside"), "{error}");
+        assert!(!dir.path().join("evil").exists());
+        assert_eq!(fs::read_dir(&downloads_dir).unwrap().count(), 0);
     }
-    let platform = utils::get_platform_name();
-    let file = &format!("{}/{platform}/bin/nvim", downloaded_file.file_name);
-    let mut perms = fs::metadata(file)?.permissions();
-    perms.set_mode(0o111);
-    fs::set_permissions(file, perms)?;
-    Ok(())
 }
@@ -0,0 +1,134 @@
+use super::{net, progress, utils};
+use crate::models::{Config, UpstreamVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+const NEOVIM_QT_REPO: &str = "equalsraf/neovim-qt";
+const NEOVIM_QT_ASSET: &str = "neovim-qt.zip";
+
+pub fn get_binary_name() -> &'static str {
+    if cfg!(windows) {
+        "nvim-qt.exe"
+    } else {
+        "nvim-qt"
+    }
+}
+
+// Versions installed before the archive layout was normalized still keep everything in Neovim
+pub fn get_bin_dir(version_dir: &Path) -> PathBuf {
+    let legacy = version_dir.join("Neovim");
+    if legacy.is_dir() {
+        return legacy.join("bin");
+    }
+    version_dir.join(utils::get_platform_name()).join("bin")
+}
+
+pub fn has_gui(version_dir: &Path) -> bool {
+    get_bin_dir(version_dir).join(get_binary_name()).is_file()
+}
+
+// Older Windows zips bundled nvim-qt, newer ones don't. The separate neovim-qt release is
+// unpacked into the version's own folder, so it's switched together with nvim by the same link
+pub async fn ensure_installed(client: &Client, config: &Config, version_dir: &Path) -> Result<()> {
+    if config.gui != Some(true) || has_gui(version_dir) {
+        return Ok(());
+    }
+    if !cfg!(windows) {
+        warn!("gui only installs neovim-qt on Windows, install it through your package manager instead");
+        return Ok(());
+    }
+
+    let release: UpstreamVersion = progress::with_spinner(
+        "Looking for neovim-qt…",
+        net::get_json_from(
+            client,
+            &net::repo_api_url(NEOVIM_QT_REPO, "releases/latest"),
+        ),
+    )
+    .await?;
+    let asset = match release
+        .assets
+        .iter()
+        .find(|asset| asset.name == NEOVIM_QT_ASSET)
+    {
+        Some(value) => value,
+        None => {
+            return Err(anyhow!(
+                "neovim-qt {} has no {NEOVIM_QT_ASSET}",
+                release.tag_name
+            ))
+        }
+    };
+
+    let download_progress = progress::new_sink("download", progress::BYTES_TEMPLATE);
+    let archive = net::download_asset(client, asset, download_progress).await?;
+    let target = match get_bin_dir(version_dir).parent() {
+        Some(value) => value.to_path_buf(),
+        None => return Err(anyhow!("Couldn't find where to put neovim-qt")),
+    };
+    match tokio::task::spawn_blocking(move || extract(archive, &target)).await {
+        Ok(value) => value?,
+        Err(error) => return Err(anyhow!(error)),
+    }
+    info!(
+        "Installed neovim-qt {} alongside {}",
+        release.tag_name,
+        version_dir.display()
+    );
+    Ok(())
+}
+
+// The zip nests bin/ and share/ under a folder of its own, whatever is under it is laid over the
+// version without replacing any of nvim's own files
+fn extract(archive: Vec<u8>, target: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive))?;
+    let gui_binary = Path::new("bin").join(get_binary_name());
+    let root = (0..archive.len()).find_map(|index| {
+        let file = archive.by_index(index).ok()?;
+        let path = file.enclosed_name()?;
+        if path.ends_with(&gui_binary) {
+            path.parent()?.parent().map(Path::to_path_buf)
+        } else {
+            None
+        }
+    });
+    let root = match root {
+        Some(value) => value,
+        None => {
+            return Err(anyhow!(
+                "{NEOVIM_QT_ASSET} doesn't contain {}",
+                gui_binary.display()
+            ))
+        }
+    };
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let relative = match file
+            .enclosed_name()
+            .and_then(|path| path.strip_prefix(&root).ok())
+        {
+            Some(value) if value.components().next().is_some() => value.to_path_buf(),
+            _ => continue,
+        };
+        let destination = target.join(relative);
+        if file.is_dir() {
+            fs::create_dir_all(&destination)?;
+            continue;
+        }
+        if destination.exists() {
+            debug!("Keeping {}", destination.display());
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = fs::File::create(&destination)?;
+        io::copy(&mut file, &mut output)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use super::{output, utils};
+use crate::enums::Version;
+use crate::models::{
+    CommandOutput, Config, InputVersion, Provenance, ReleaseAsset, UpstreamVersion,
+};
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::fs;
+use yansi::Paint;
+
+// Everything the release page shows, without the assets for other platforms. --local adds what's
+// on disk for an installed version, commit builds have no release so only that part applies
+pub async fn start(
+    client: &Client,
+    config: Config,
+    version: InputVersion,
+    local: bool,
+) -> Result<CommandOutput> {
+    let mut text = Vec::new();
+    let mut details = json!({});
+
+    if matches!(version.version, Version::Hash(_)) {
+        text.push(format!(
+            "{} is a commit build, only releases have release information",
+            version.tag_name
+        ));
+    } else {
+        let release = utils::get_release(client, &version.tag_name).await?;
+        text.extend(describe_release(&release));
+        details["release"] = json!({
+            "tag": release.tag_name,
+            "published_at": release.published_at,
+            "prerelease": release.prerelease,
+            "commit": utils::get_commit_sha(&release),
+            "assets": get_platform_assets(&release)
+                .iter()
+                .map(|asset| json!({ "name": asset.name, "size": asset.size }))
+                .collect::<Vec<_>>(),
+            "body": release.body,
+        });
+    }
+
+    if local || matches!(version.version, Version::Hash(_)) {
+        let (lines, local_details) = describe_local(&config, &version).await?;
+        text.push(String::new());
+        text.extend(lines);
+        details["local"] = local_details;
+    }
+
+    output::print_paged(text.join("\n").trim_end()).await;
+
+    Ok(CommandOutput {
+        action: String::from("info"),
+        versions: vec![version.tag_name],
+        details,
+        ..Default::default()
+    })
+}
+
+fn describe_release(release: &UpstreamVersion) -> Vec<String> {
+    let mut lines = vec![Paint::new(&release.tag_name).bold().to_string()];
+    lines.push(format!(
+        "Published:  {}",
+        format_date(&release.published_at)
+    ));
+    lines.push(format!(
+        "Prerelease: {}",
+        if release.prerelease { "yes" } else { "no" }
+    ));
+    if let Some(sha) = utils::get_commit_sha(release) {
+        lines.push(format!("Commit:     {sha}"));
+    }
+
+    let assets = get_platform_assets(release);
+    if assets.is_empty() {
+        lines.push(String::from("Assets:     none for this platform"));
+    } else {
+        lines.push(String::from("Assets:"));
+        let width = assets.iter().map(|asset| asset.name.len()).max();
+        for asset in assets {
+            lines.push(format!(
+                "  {:<width$}  {}",
+                asset.name,
+                utils::format_size(asset.size),
+                width = width.unwrap_or_default()
+            ));
+        }
+    }
+
+    lines.push(String::new());
+    match release.body.as_deref().map(str::trim) {
+        Some(body) if !body.is_empty() => lines.extend(render_markdown(body)),
+        _ => lines.push(String::from("Published without release notes")),
+    }
+    lines
+}
+
+// Release notes are written for GitHub, headings and emphasis are kept readable in a terminal and
+// code blocks are indented instead of fenced
+fn render_markdown(body: &str) -> Vec<String> {
+    let bold = Regex::new(r"\*\*([^*]+)\*\*").ok();
+    let code = Regex::new(r"`([^`]+)`").ok();
+    let mut in_code_block = false;
+    let mut lines = Vec::new();
+
+    for line in body.lines() {
+        let line = line.trim_end();
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(format!("    {}", Paint::new(line).dimmed()));
+            continue;
+        }
+
+        let heading = line.trim_start_matches('#');
+        if heading.len() < line.len() && heading.starts_with(' ') {
+            lines.push(Paint::new(heading.trim()).bold().underline().to_string());
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        let mut line = match line
+            .trim_start()
+            .strip_prefix("- ")
+            .or_else(|| line.trim_start().strip_prefix("* "))
+        {
+            Some(item) => format!("{}• {item}", " ".repeat(indent)),
+            None => line.to_owned(),
+        };
+        if let Some(bold) = &bold {
+            line = bold
+                .replace_all(&line, |captures: &regex::Captures| {
+                    Paint::new(&captures[1]).bold().to_string()
+                })
+                .into_owned();
+        }
+        if let Some(code) = &code {
+            line = code
+                .replace_all(&line, |captures: &regex::Captures| {
+                    Paint::cyan(&captures[1]).to_string()
+                })
+                .into_owned();
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// The archive bob would download, the AppImage on Linux and whatever sits next to them
+fn get_platform_assets(release: &UpstreamVersion) -> Vec<&ReleaseAsset> {
+    let mut names = vec![utils::get_asset_name(utils::get_file_type())];
+    if cfg!(target_os = "linux") {
+        names.push(utils::get_asset_name(utils::APPIMAGE_FILE_TYPE));
+    }
+    release
+        .assets
+        .iter()
+        .filter(|asset| {
+            names
+                .iter()
+                .any(|name| asset.name.starts_with(name.as_str()))
+        })
+        .collect()
+}
+
+async fn describe_local(config: &Config, version: &InputVersion) -> Result<(Vec<String>, Value)> {
+    let version_dir = match utils::find_version_dir(&version.tag_name, config).await? {
+        Some(value) if utils::is_version_installed(&version.tag_name, config).await? => value,
+        _ => {
+            return Ok((
+                vec![format!("{} isn't installed", version.tag_name)],
+                json!({ "installed": false }),
+            ))
+        }
+    };
+
+    // The version directory is created when the archive is moved into place, extracted files
+    // keep the dates they had in the archive
+    let installed_at: Option<DateTime<Utc>> = fs::metadata(&version_dir)
+        .await?
+        .modified()
+        .ok()
+        .map(DateTime::from);
+    let size = utils::get_dir_size(&version_dir).await?;
+    let provenance: Option<Provenance> =
+        match fs::read_to_string(version_dir.join(utils::PROVENANCE_FILE)).await {
+            Ok(content) => serde_json::from_str(&content).ok(),
+            Err(_) => None,
+        };
+    let nightly_commit = match version.version {
+        Version::Nightly => utils::get_local_nightly(config)
+            .await
+            .ok()
+            .and_then(|nightly| utils::get_commit_sha(&nightly).map(str::to_owned)),
+        _ => None,
+    };
+
+    let mut lines = vec![Paint::new("Installed").bold().to_string()];
+    lines.push(format!("Path:       {}", version_dir.display()));
+    if let Some(installed_at) = installed_at {
+        lines.push(format!(
+            "Date:       {}",
+            installed_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        ));
+    }
+    lines.push(format!("Size:       {}", utils::format_size(size)));
+    if let Some(sha) = &nightly_commit {
+        lines.push(format!("Built from: {sha}"));
+    }
+    let source = match (&provenance, &version.version) {
+        (Some(provenance), _) => format!(
+            "assembled from {} ({}) with the {} runtime",
+            provenance.binary.display(),
+            provenance.binary_version,
+            provenance.runtime
+        ),
+        (None, Version::Hash(_)) => String::from("built from source"),
+        (None, _) => String::from("release archive"),
+    };
+    lines.push(format!("Source:     {source}"));
+
+    Ok((
+        lines,
+        json!({
+            "installed": true,
+            "path": version_dir,
+            "installed_at": installed_at,
+            "size": size,
+            "commit": nightly_commit,
+            "provenance": provenance,
+        }),
+    ))
+}
+
+fn format_date(date: &str) -> String {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(value) => value
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Err(_) if date.is_empty() => String::from("unknown"),
+        Err(_) => date.to_owned(),
+    }
+}
@@ -1,18 +1,19 @@
 use super::utils;
-use crate::enums::{InstallResult, PostDownloadVersionType, VersionType};
-use crate::models::{Config, InputVersion, LocalVersion, UpstreamVersion};
-use crate::modules::expand_archive;
+use crate::enums::{BobError, CommitStatus, InstallResult, PostDownloadVersionType, Version};
+use crate::models::{Config, InputVersion, LocalVersion, Provenance, RepoCommit, UpstreamVersion};
 use crate::modules::utils::handle_subprocess;
+use crate::modules::{downloader, expand_archive, gui, manifest, net, output, progress};
 use anyhow::{anyhow, Result};
-use futures_util::stream::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
+use chrono::Utc;
+use regex::Regex;
 use reqwest::Client;
 use std::cmp::min;
-use std::env;
-use std::path::Path;
-use tokio::io::AsyncWriteExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::{self, Instant};
 use tokio::{fs, process::Command};
-use tracing::info;
+use tracing::{debug, info, warn};
 use yansi::Paint;
 
 pub async fn start(
@@ -20,16 +21,50 @@ pub async fn start(
     client: &Client,
     config: &Config,
 ) -> Result<InstallResult> {
+    run(version, client, config, false).await
+}
+
+// Installs the version again even though it's installed, an up to date nightly included. The
+// files are replaced the same way an update replaces them, the old ones stay until the new ones
+// are ready
+pub async fn reinstall(
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+) -> Result<InstallResult> {
+    run(version, client, config, true).await
+}
+
+async fn run(
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+    force: bool,
+) -> Result<InstallResult> {
+    if let Some(true) = config.low_priority {
+        utils::lower_process_priority();
+    }
+
+    if config.preflight_check == Some(true) && !net::is_offline() {
+        net::check_connectivity(client).await?;
+    }
+
     let root = match utils::get_downloads_folder(config).await {
         Ok(value) => value,
         Err(error) => return Err(anyhow!(error)),
     };
-    env::set_current_dir(&root)?;
     let root = root.as_path();
 
     let is_version_installed = utils::is_version_installed(&version.tag_name, config).await?;
 
-    let nightly_version = if version.tag_name == "nightly" {
+    let nightly_version = if version.tag_name == "nightly" && net::is_offline() {
+        // Without upstream's metadata bob.json is recreated from the binary when it's needed
+        if is_version_installed && !force {
+            info!("Offline mode, not looking for nightly updates");
+            return Ok(InstallResult::NightlyIsUpdated);
+        }
+        None
+    } else if version.tag_name == "nightly" {
         let upstream_nightly = match utils::get_upstream_nightly(client).await {
             Ok(value) => value,
             Err(error) => return Err(error),
@@ -38,54 +73,420 @@ pub async fn start(
             info!("Looking for nightly updates...");
             let local_nightly = utils::get_local_nightly(config).await?;
 
-            match config.enable_nightly_info {
-                Some(boolean) if boolean => {
-                    print_commits(client, &local_nightly, &upstream_nightly).await?
-                }
-                None => print_commits(client, &local_nightly, &upstream_nightly).await?,
-                _ => (),
+            if config.enable_nightly_info != Some(false) && !output::is_silent() {
+                print_commits(client, &local_nightly, &upstream_nightly).await?
             }
 
-            if local_nightly.published_at == upstream_nightly.published_at {
+            if utils::is_same_nightly(&local_nightly, &upstream_nightly) && !force {
                 return Ok(InstallResult::NightlyIsUpdated);
             }
         }
+        if let Some(true) = config.require_passing_nightly {
+            check_nightly_passed(client, &upstream_nightly).await?;
+        }
         Some(upstream_nightly)
     } else {
-        if is_version_installed {
+        if is_version_installed && !force {
             return Ok(InstallResult::VersionAlreadyInstalled);
         }
         None
     };
 
-    let downloaded_file = match download_version(client, version, root, config).await {
-        Ok(value) => value,
-        Err(error) => return Err(anyhow!(error)),
+    let preserved_nightly = match &nightly_version {
+        Some(upstream_nightly) => get_preserved_nightly(root, upstream_nightly, config).await,
+        None => None,
     };
 
+    // Builds install straight into the version directory, so a reinstalled one keeps the old
+    // build aside until the new one is in place
+    let previous_build = match version.version {
+        Version::Hash(_) if is_version_installed => Some(set_aside(root, version).await?),
+        _ => None,
+    };
+
+    let download_progress = progress::new_sink("download", progress::BYTES_TEMPLATE);
+    let downloaded_file =
+        match download_version(client, version, root, config, download_progress).await {
+            Ok(value) => value,
+            Err(error) => {
+                if let Some(previous_build) = &previous_build {
+                    restore(root, version, previous_build).await?;
+                }
+                return Err(anyhow!(error));
+            }
+        };
+    if let Some(previous_build) = previous_build {
+        utils::remove_dir(&previous_build, removal_progress()).await?;
+    }
+
     if let PostDownloadVersionType::Standard(downloaded_file) = downloaded_file {
-        if let Err(error) = expand_archive::start(downloaded_file).await {
+        let strip_prefix = config.archive_strip_prefix.clone();
+        let staging_dir = config.staging_dir.as_ref().map(PathBuf::from);
+        let extract_progress = progress::new_sink("extract", progress::SIMPLE_TEMPLATE);
+        if let Err(error) =
+            expand_archive::start(downloaded_file, strip_prefix, staging_dir, extract_progress)
+                .await
+        {
             return Err(anyhow!(error));
         }
+        #[cfg(unix)]
+        set_binary_mode(&root.join(&version.tag_name), config).await?;
+        gui::ensure_installed(client, config, &root.join(&version.tag_name)).await?;
+    }
+
+    // A fresh download replaces whatever binary install --binary put in its place
+    let provenance_file = root.join(&version.tag_name).join(utils::PROVENANCE_FILE);
+    if fs::metadata(&provenance_file).await.is_ok() {
+        fs::remove_file(provenance_file).await?;
     }
+    manifest::record(&root.join(utils::get_version_dir_name(version))).await?;
 
-    if let Some(nightly_version) = nightly_version {
+    if let Some(nightly_version) = preserved_nightly.or(nightly_version) {
         let nightly_string = serde_json::to_string(&nightly_version)?;
-        let mut file = match fs::File::create("nightly/bob.json").await {
+        let nightly_file = root.join("nightly").join("bob.json");
+        let mut file = match fs::File::create(&nightly_file).await {
             Ok(value) => value,
             Err(error) => {
                 return Err(anyhow!(
-                    "Failed to create file nightly/bob.json, reason: {error}"
+                    "Failed to create file {}, reason: {error}",
+                    nightly_file.display()
                 ))
             }
         };
         file.write_all(nightly_string.as_bytes()).await?;
     }
+    progress::emit("finalize", 1, 1);
     Ok(InstallResult::InstallationSuccess(
         root.display().to_string(),
     ))
 }
 
+async fn set_aside(root: &Path, version: &InputVersion) -> Result<PathBuf> {
+    let version_dir = root.join(utils::get_version_dir_name(version));
+    let previous_build = root.join(format!("{}.previous", utils::get_version_dir_name(version)));
+    if fs::metadata(&previous_build).await.is_ok() {
+        fs::remove_dir_all(&previous_build).await?;
+    }
+    fs::rename(&version_dir, &previous_build).await?;
+    debug!("Moved the old build aside to {}", previous_build.display());
+    Ok(previous_build)
+}
+
+async fn restore(root: &Path, version: &InputVersion, previous_build: &Path) -> Result<()> {
+    let version_dir = root.join(utils::get_version_dir_name(version));
+    if fs::metadata(&version_dir).await.is_ok() {
+        fs::remove_dir_all(&version_dir).await?;
+    }
+    fs::rename(previous_build, &version_dir).await?;
+    warn!(
+        "Kept the previous build of {} since the new one failed",
+        version.tag_name
+    );
+    Ok(())
+}
+
+// Extracting wipes the version directory, so with preserve_nightly_metadata the old bob.json is
+// read beforehand and written back when it describes the same commit. Reinstalling a pinned
+// nightly would otherwise move its dates forward every time
+async fn get_preserved_nightly(
+    root: &Path,
+    upstream_nightly: &UpstreamVersion,
+    config: &Config,
+) -> Option<UpstreamVersion> {
+    if config.preserve_nightly_metadata != Some(true) {
+        return None;
+    }
+    let nightly_file = root.join("nightly").join("bob.json");
+    let file = fs::read_to_string(&nightly_file).await.ok()?;
+    let existing: UpstreamVersion = serde_json::from_str(&file).ok()?;
+    if utils::get_commit_sha(&existing).is_none()
+        || !utils::is_same_nightly(&existing, upstream_nightly)
+    {
+        return None;
+    }
+    debug!(
+        "Keeping {} since it describes the same nightly",
+        nightly_file.display()
+    );
+    Some(existing)
+}
+
+#[cfg(unix)]
+async fn set_binary_mode(version_dir: &Path, config: &Config) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let binary = version_dir
+        .join(utils::get_platform_name())
+        .join("bin")
+        .join("nvim");
+    let mode = config.get_binary_mode()?;
+    if let Err(error) = fs::set_permissions(&binary, std::fs::Permissions::from_mode(mode)).await {
+        return Err(anyhow!(
+            "Failed to set the mode of {} to {mode:o}, reason: {error}",
+            binary.display()
+        ));
+    }
+    debug!("Set the mode of {} to {mode:o}", binary.display());
+    Ok(())
+}
+
+// People building nvim themselves still want the official runtime of the matching release, so
+// the release is installed as usual and its binary is swapped for theirs
+pub async fn assemble(
+    version: &InputVersion,
+    binary: &Path,
+    client: &Client,
+    config: &Config,
+) -> Result<PathBuf> {
+    if let Version::Hash(_) = version.version {
+        return Err(anyhow!(
+            "{} is built from source already, pair the binary with a release or nightly runtime",
+            version.tag_name
+        ));
+    }
+
+    let binary_version = get_binary_version(binary).await?;
+    check_runtime_compatible(version, &binary_version)?;
+
+    if !utils::is_version_installed(&version.tag_name, config).await? {
+        start(version, client, config).await?;
+    }
+    let version_dir = match utils::find_version_dir(&version.tag_name, config).await? {
+        Some(value) => value,
+        None => return Err(BobError::NotInstalled(version.tag_name.clone()).into()),
+    };
+
+    // Copied next to the target first so a running nvim is never left with half a binary
+    let bin_dir = version_dir.join(utils::get_platform_name()).join("bin");
+    let target = bin_dir.join(utils::get_binary_name());
+    let temp_target = bin_dir.join(utils::get_partial_download_name(utils::get_binary_name()));
+    if let Err(error) = fs::copy(binary, &temp_target).await {
+        let _ = fs::remove_file(&temp_target).await;
+        return Err(anyhow!(
+            "Failed to copy {} into {}, reason: {error}",
+            binary.display(),
+            bin_dir.display()
+        ));
+    }
+    fs::rename(&temp_target, &target).await?;
+    debug!("Copied {} to {}", binary.display(), target.display());
+
+    let provenance = Provenance {
+        binary: fs::canonicalize(binary).await?,
+        binary_version,
+        runtime: version.tag_name.clone(),
+        assembled_at: Utc::now(),
+    };
+    fs::write(
+        version_dir.join(utils::PROVENANCE_FILE),
+        serde_json::to_string_pretty(&provenance)?,
+    )
+    .await?;
+    manifest::record(&version_dir).await?;
+    info!(
+        "Paired {} with the runtime of {}",
+        provenance.binary_version, version.tag_name
+    );
+
+    Ok(version_dir)
+}
+
+async fn get_binary_version(binary: &Path) -> Result<String> {
+    let output = match Command::new(binary).arg("--version").output().await {
+        Ok(value) if value.status.success() => value,
+        Ok(_) | Err(_) => {
+            return Err(anyhow!(
+                "{} can't be run, it has to be an nvim binary built for this system",
+                binary.display()
+            ))
+        }
+    };
+    let output = String::from_utf8_lossy(&output.stdout);
+    match output.lines().next() {
+        Some(line) if line.starts_with("NVIM v") => Ok(line.trim().to_string()),
+        _ => Err(anyhow!("{} isn't an nvim binary", binary.display())),
+    }
+}
+
+// The runtime changes along with the binary every minor release, only patch releases can be
+// mixed. Nightly runtimes follow master so they're only paired with dev builds
+fn check_runtime_compatible(version: &InputVersion, binary_version: &str) -> Result<()> {
+    let captures =
+        match Regex::new(r"^NVIM v([0-9]+)\.([0-9]+)\.([0-9]+)(-dev)?")?.captures(binary_version) {
+            Some(value) => value,
+            None => return Err(anyhow!("Couldn't read the version of {binary_version}")),
+        };
+    let is_dev = captures.get(4).is_some();
+
+    match &version.version {
+        Version::Nightly if !is_dev => Err(anyhow!(
+            "{binary_version} is a release build, it can only be paired with the runtime of its own release"
+        )),
+        Version::Release(release)
+            if captures[1] != release.major.to_string()
+                || captures[2] != release.minor.to_string() =>
+        {
+            Err(anyhow!(
+                "{binary_version} can't use the runtime of {}, their major and minor versions have to match",
+                version.tag_name
+            ))
+        }
+        Version::Release(release) => {
+            if is_dev || captures[3] != release.patch.to_string() {
+                warn!(
+                    "{binary_version} isn't exactly {}, the runtime should still work with it",
+                    version.tag_name
+                );
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Same checks start does before downloading anything, a version that isn't installed at all
+// counts as an available update
+pub async fn is_update_available(
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+) -> Result<bool> {
+    if !utils::is_version_installed(&version.tag_name, config).await? {
+        return Ok(true);
+    }
+    if version.tag_name != "nightly" {
+        return Ok(false);
+    }
+
+    let upstream_nightly = utils::get_upstream_nightly(client).await?;
+    let local_nightly = utils::get_local_nightly(config).await?;
+    Ok(!utils::is_same_nightly(&local_nightly, &upstream_nightly))
+}
+
+// Whether updating would replace the build nvim runs right now: a used nightly once upstream
+// published another commit, a used release when default_version follows stable and a newer
+// release is out. Commit builds and other releases are pinned and never move
+pub async fn is_active_update_pending(client: &Client, config: &Config) -> Result<bool> {
+    let used = match utils::get_current_version(config).await {
+        Ok(value) => value,
+        Err(error) if matches!(error.downcast_ref(), Some(BobError::NotInstalled(_))) => {
+            return Ok(false)
+        }
+        Err(error) => return Err(error),
+    };
+
+    match Version::parse(&used) {
+        Some(Version::Nightly) => {
+            let upstream_nightly = utils::get_upstream_nightly(client).await?;
+            let local_nightly = utils::get_local_nightly(config).await?;
+            Ok(!utils::is_same_nightly(&local_nightly, &upstream_nightly))
+        }
+        Some(Version::Release(_)) if config.default_version.as_deref() == Some("stable") => {
+            let stable = utils::parse_version_type(client, "stable").await?;
+            Ok(!utils::is_same_version(&stable.tag_name, &used))
+        }
+        _ => Ok(false),
+    }
+}
+
+const MIN_POLL_DELAY: Duration = Duration::from_secs(60);
+const MAX_POLL_DELAY: Duration = Duration::from_secs(15 * 60);
+
+// Polls with a doubling delay until upstream publishes a nightly the installed one isn't,
+// a missing local nightly counts as out of date right away
+pub async fn wait_for_newer_nightly(
+    client: &Client,
+    config: &Config,
+    timeout: Duration,
+) -> Result<()> {
+    if !utils::is_version_installed("nightly", config).await? {
+        return Ok(());
+    }
+    let local_nightly = utils::get_local_nightly(config).await?;
+
+    let started = Instant::now();
+    let mut delay = MIN_POLL_DELAY;
+    loop {
+        let upstream_nightly = utils::get_upstream_nightly(client).await?;
+        if !utils::is_same_nightly(&local_nightly, &upstream_nightly) {
+            return Ok(());
+        }
+
+        let remaining = timeout.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Err(anyhow!(
+                "No nightly newer than the installed one was published within {} minutes",
+                timeout.as_secs() / 60
+            ));
+        }
+
+        let wait = min(delay, remaining);
+        info!(
+            "Upstream nightly matches the installed one, checking again in {} seconds",
+            wait.as_secs()
+        );
+        time::sleep(wait).await;
+        delay = min(delay * 2, MAX_POLL_DELAY);
+    }
+}
+
+const PASSING_COMMIT_LOOKBACK: usize = 10;
+
+// Missing CI data only warns, require_passing_nightly is meant to skip known bad builds and not
+// to block installs whenever GitHub doesn't report anything
+async fn check_nightly_passed(client: &Client, upstream: &UpstreamVersion) -> Result<()> {
+    let sha = match utils::get_commit_sha(upstream) {
+        Some(value) => value,
+        None => {
+            warn!("Couldn't tell which commit the upstream nightly was built from, skipping the CI check");
+            return Ok(());
+        }
+    };
+
+    let status = match utils::get_commit_status(client, sha).await {
+        Ok(CommitStatus::Passing) => return Ok(()),
+        Ok(CommitStatus::Unknown) => {
+            warn!("No CI results were reported for nightly commit {sha}, installing it anyway");
+            return Ok(());
+        }
+        Err(error) => {
+            warn!(
+                "Couldn't check CI results for nightly commit {sha}, installing it anyway: {error}"
+            );
+            return Ok(());
+        }
+        Ok(CommitStatus::Pending) => "is still running",
+        Ok(CommitStatus::Failing) => "failed",
+    };
+
+    let mut message = format!(
+        "CI for nightly commit {sha} {status}, not installing it since require_passing_nightly is set"
+    );
+    if let Some(passing) = find_last_passing_commit(client, sha).await {
+        message.push_str(&format!(
+            ", {passing} is the most recent commit that passed and can be built with `bob install {passing}`"
+        ));
+    }
+    Err(anyhow!(message))
+}
+
+async fn find_last_passing_commit(client: &Client, sha: &str) -> Option<String> {
+    let commits: Vec<RepoCommit> = net::get_json(
+        client,
+        &format!("commits?sha={sha}&per_page={}", PASSING_COMMIT_LOOKBACK + 1),
+    )
+    .await
+    .ok()?;
+
+    for commit in commits.into_iter().skip(1) {
+        let sha = commit.sha?;
+        if let Ok(CommitStatus::Passing) = utils::get_commit_status(client, &sha).await {
+            return Some(sha.get(..7).unwrap_or(&sha).to_string());
+        }
+    }
+    None
+}
+
 async fn print_commits(
     client: &Client,
     local: &UpstreamVersion,
@@ -93,16 +494,35 @@ async fn print_commits(
 ) -> Result<()> {
     let commits =
         utils::get_commits_for_nightly(client, &local.published_at, &upstream.published_at).await?;
+    print_commit_list(&commits);
+    Ok(())
+}
 
+pub fn print_commit_list(commits: &[RepoCommit]) {
     for commit in commits {
-        println!(
-            "| {} {}\n",
-            Paint::blue(commit.commit.author.name).bold(),
-            commit.commit.message.replace('\n', "\n| ")
-        );
-    }
+        let author = &commit.commit.author;
+        let mut attribution = if author.name.is_empty() {
+            String::from("unknown author")
+        } else {
+            Paint::blue(&author.name).bold().to_string()
+        };
+        if let Some(date) = author.date {
+            attribution.push_str(&format!(", {}", utils::format_relative_time(date)));
+        }
 
-    Ok(())
+        let mut lines = commit.commit.message.lines();
+        output::print_human(format!(
+            "| {} — {attribution}",
+            lines.next().unwrap_or_default()
+        ));
+        for line in lines {
+            output::print_human(format!("| {line}"));
+        }
+        if let Some(url) = &commit.html_url {
+            output::print_human(format!("| {}", Paint::new(url).dimmed()));
+        }
+        output::print_human("");
+    }
 }
 
 async fn download_version(
@@ -110,69 +530,193 @@ async fn download_version(
     version: &InputVersion,
     root: &Path,
     config: &Config,
+    progress: progress::Sink,
 ) -> Result<PostDownloadVersionType> {
-    match version.version_type {
-        VersionType::Standard => {
-            let response = send_request(client, &version.tag_name).await;
+    match version.version {
+        Version::Release(_) | Version::Nightly => {
+            if let Some((source, file_type)) = find_in_mirror(&version.tag_name, config).await {
+                return copy_from_mirror(&source, file_type, version, root, progress).await;
+            }
+            net::ensure_online(&format!(
+                "download {}, it isn't in local_mirror_dir",
+                version.tag_name
+            ))?;
+
+            let response = send_request(client, &version.tag_name, config).await;
 
             match response {
-                Ok(response) => {
+                Ok((response, file_type)) => {
                     if response.status() == 200 {
-                        let total_size = response.content_length().unwrap();
-                        let mut response_bytes = response.bytes_stream();
-
-                        // Progress Bar Setup
-                        let pb = ProgressBar::new(total_size);
-                        pb.set_style(ProgressStyle::default_bar()
-                    .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-                    .progress_chars("█  "));
-                        pb.set_message(format!("Downloading version: {}", version.tag_name));
-
-                        let file_type = utils::get_file_type();
-                        let mut file =
-                            tokio::fs::File::create(format!("{}.{file_type}", version.tag_name))
-                                .await?;
-
-                        let mut downloaded: u64 = 0;
-
-                        while let Some(item) = response_bytes.next().await {
-                            let chunk = item.map_err(|_| anyhow!("hello"))?;
-                            file.write_all(&chunk).await?;
-                            let new = min(downloaded + (chunk.len() as u64), total_size);
-                            downloaded = new;
-                            pb.set_position(new);
+                        let downloader = downloader::get(config);
+                        progress.message(&format!("Downloading version: {}", version.tag_name));
+
+                        // The archive only gets its final name once it's complete, so a
+                        // truncated one is never mistaken for a finished download
+                        let partial_path =
+                            root.join(utils::get_partial_download_name(&version.tag_name));
+                        let archive_path = root.join(format!("{}.{file_type}", version.tag_name));
+                        debug!(
+                            "Downloading into {} with {}",
+                            partial_path.display(),
+                            downloader.name()
+                        );
+
+                        if let Err(error) = downloader
+                            .download(response, &partial_path, &progress)
+                            .await
+                        {
+                            let _ = tokio::fs::remove_file(&partial_path).await;
+                            return Err(BobError::DownloadFailed {
+                                version: version.tag_name.clone(),
+                                reason: error.to_string(),
+                            }
+                            .into());
                         }
+                        tokio::fs::rename(&partial_path, &archive_path).await?;
 
-                        pb.finish_with_message(format!(
-                            "Downloaded version {} to {}/{}.{file_type}",
+                        progress.message(&format!(
+                            "Downloaded version {} to {}",
                             version.tag_name,
-                            root.display(),
-                            version.tag_name
+                            archive_path.display()
                         ));
+                        progress.finish();
 
                         Ok(PostDownloadVersionType::Standard(LocalVersion {
                             file_name: version.tag_name.to_owned(),
                             file_format: file_type.to_string(),
-                            path: root.display().to_string(),
+                            path: root.to_path_buf(),
                         }))
+                    } else if response.status() == 404 {
+                        Err(utils::version_not_found(client, &version.tag_name).await)
                     } else {
-                        Err(anyhow!("Please provide an existing neovim version"))
+                        Err(BobError::DownloadFailed {
+                            version: version.tag_name.clone(),
+                            reason: format!("GitHub responded with {}", response.status()),
+                        }
+                        .into())
                     }
                 }
-                Err(error) => Err(anyhow!(error)),
+                Err(error) => Err(BobError::DownloadFailed {
+                    version: version.tag_name.clone(),
+                    reason: error.to_string(),
+                }
+                .into()),
+            }
+        }
+        Version::Hash(_) => handle_building_from_source(version, root).await,
+    }
+}
+
+// Air-gapped setups pre-stage release archives as <local_mirror_dir>/<tag>/<asset name>, an
+// archive that isn't there is downloaded from GitHub as usual
+async fn find_in_mirror(tag: &str, config: &Config) -> Option<(PathBuf, &'static str)> {
+    let mirror = PathBuf::from(config.local_mirror_dir.as_ref()?);
+    for file_type in get_file_types(config) {
+        let path = mirror.join(tag).join(utils::get_asset_name(file_type));
+        if fs::metadata(&path)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            return Some((path, file_type));
+        }
+    }
+    debug!("{tag} isn't in the mirror at {}", mirror.display());
+    None
+}
+
+async fn copy_from_mirror(
+    source: &Path,
+    file_type: &'static str,
+    version: &InputVersion,
+    root: &Path,
+    progress: progress::Sink,
+) -> Result<PostDownloadVersionType> {
+    verify_mirror_checksum(source).await?;
+
+    let partial_path = root.join(utils::get_partial_download_name(&version.tag_name));
+    let archive_path = root.join(format!("{}.{file_type}", version.tag_name));
+    progress.start(fs::metadata(source).await?.len());
+    progress.message(&format!(
+        "Copying version {} from {}",
+        version.tag_name,
+        source.display()
+    ));
+
+    let result: std::io::Result<()> = async {
+        let mut reader = fs::File::open(source).await?;
+        let mut writer = fs::File::create(&partial_path).await?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
             }
+            writer.write_all(&buffer[..read]).await?;
+            progress.advance(read as u64);
         }
-        VersionType::Hash => handle_building_from_source(version, config).await,
+        writer.flush().await
+    }
+    .await;
+    if let Err(error) = result {
+        let _ = fs::remove_file(&partial_path).await;
+        return Err(anyhow!(
+            "Failed to copy {} from the mirror, reason: {error}",
+            source.display()
+        ));
     }
+    fs::rename(&partial_path, &archive_path).await?;
+    progress.message(&format!(
+        "Copied version {} to {}",
+        version.tag_name,
+        archive_path.display()
+    ));
+    progress.finish();
+
+    Ok(PostDownloadVersionType::Standard(LocalVersion {
+        file_name: version.tag_name.to_owned(),
+        file_format: file_type.to_string(),
+        path: root.to_path_buf(),
+    }))
+}
+
+// Mirrors are usually filled straight from the release page, so the .sha256sum files upstream
+// publishes next to each archive are checked when they were copied along
+async fn verify_mirror_checksum(source: &Path) -> Result<()> {
+    let mut expected = None;
+    for extension in ["sha256sum", "sha256"] {
+        let checksum_file = PathBuf::from(format!("{}.{extension}", source.display()));
+        if let Ok(content) = fs::read_to_string(&checksum_file).await {
+            expected = content.split_whitespace().next().map(str::to_owned);
+            break;
+        }
+    }
+    let expected = match expected {
+        Some(value) => value,
+        None => {
+            debug!("No checksum next to {}, it's used as is", source.display());
+            return Ok(());
+        }
+    };
+
+    let actual = utils::sha256_file(source).await?;
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(anyhow!(
+            "Checksum of {} doesn't match, expected {expected} but got {actual}",
+            source.display()
+        ));
+    }
+    debug!("Checksum of {} matches {expected}", source.display());
+    Ok(())
 }
 
 async fn handle_building_from_source(
     version: &InputVersion,
-    config: &Config,
+    root: &Path,
 ) -> Result<PostDownloadVersionType> {
+    net::ensure_online(&format!("fetch the sources of {}", version.tag_name))?;
     cfg_if::cfg_if! {
         if #[cfg(windows)] {
-            if env::var("VisualStudioVersion").is_err() {
+            if std::env::var("VisualStudioVersion").is_err() {
                 return Err(anyhow!("Please make sure you are using Developer PowerShell/Command Prompt for VS"));
             }
 
@@ -204,15 +748,15 @@ async fn handle_building_from_source(
             }
         }
     }
-    let (mut child, is_installed) = if fs::metadata("neovim-git").await.is_err() {
-        // check if neovim-git
-        // directory exists
-        // to clone repo, else
-        // git pull changes
-        let child = match Command::new("git")
+    // Everything happens in neovim-git under the downloads folder, bob's own working directory
+    // is left alone
+    let repository = root.join("neovim-git");
+    let mut child = if fs::metadata(&repository).await.is_err() {
+        match Command::new("git")
             .arg("clone")
-            .arg("https://github.com/neovim/neovim")
-            .arg("neovim-git")
+            .arg(net::repository_url())
+            .arg(&repository)
+            .stdout(output::child_stdout())
             .spawn()
         {
             Ok(value) => value,
@@ -224,75 +768,274 @@ async fn handle_building_from_source(
                 }
                 _ => return Err(anyhow!("Failed to clone neovim's repository")),
             },
-        };
-        (child, false)
+        }
     } else {
-        env::set_current_dir("neovim-git")?; // cd into neovim-git
-        let child = match Command::new("git").arg("pull").spawn() {
+        match Command::new("git")
+            .arg("pull")
+            .current_dir(&repository)
+            .stdout(output::child_stdout())
+            .spawn()
+        {
             Ok(value) => value,
             Err(_) => return Err(anyhow!("Failed to pull upstream updates")),
-        };
-        (child, true)
+        }
     };
     child.wait().await?;
-    if !is_installed {
-        env::set_current_dir("neovim-git")?; // cd into neovim-git
-    }
     Command::new("git")
         .arg("checkout")
         .arg(&version.tag_name)
+        .current_dir(&repository)
+        .stdout(output::child_stdout())
         .spawn()?
         .wait()
         .await?;
 
-    if fs::metadata("build").await.is_ok() {
-        utils::remove_dir("build").await?;
+    let build_dir = repository.join("build");
+    if fs::metadata(&build_dir).await.is_ok() {
+        utils::remove_dir(&build_dir, removal_progress()).await?;
     }
-    fs::create_dir("build").await?;
+    fs::create_dir(&build_dir).await?;
 
-    let mut downloads_location = utils::get_downloads_folder(config).await?;
-    downloads_location.push(&version.tag_name[0..7]);
-    downloads_location.push(utils::get_platform_name());
+    let downloads_location = root
+        .join(utils::get_version_dir_name(version))
+        .join(utils::get_platform_name());
 
     cfg_if::cfg_if! {
         if #[cfg(windows)] {
-            if fs::metadata(".deps").await.is_ok() {
-                utils::remove_dir(".deps").await?;
+            let deps_dir = repository.join(".deps");
+            if fs::metadata(&deps_dir).await.is_ok() {
+                utils::remove_dir(&deps_dir, removal_progress()).await?;
             }
-            fs::create_dir(".deps").await?;
-            env::set_current_dir(".deps")?;
-            handle_subprocess(Command::new("cmake").arg("../cmake.deps")).await?;
-            handle_subprocess(Command::new("cmake").arg("--build").arg(".")).await?;
-
-            let current_dir = env::current_dir()?;
-            let parent = current_dir.parent().unwrap();
-            env::set_current_dir(parent.join("build"))?;
-
-            handle_subprocess(Command::new("cmake").arg("..")).await?;
-            handle_subprocess(Command::new("cmake").arg("--build").arg(".")).await?;
-            handle_subprocess(Command::new("cmake").arg("--install").arg(".").arg("--prefix").arg(downloads_location)).await?;
+            fs::create_dir(&deps_dir).await?;
+            handle_subprocess(Command::new("cmake").arg("../cmake.deps").current_dir(&deps_dir)).await?;
+            handle_subprocess(Command::new("cmake").arg("--build").arg(".").current_dir(&deps_dir)).await?;
+
+            handle_subprocess(Command::new("cmake").arg("..").current_dir(&build_dir)).await?;
+            handle_subprocess(Command::new("cmake").arg("--build").arg(".").current_dir(&build_dir)).await?;
+            handle_subprocess(Command::new("cmake").arg("--install").arg(".").arg("--prefix").arg(downloads_location).current_dir(&build_dir)).await?;
         } else {
             let location_arg = format!(
                 "CMAKE_INSTALL_PREFIX={}",
                 downloads_location.to_string_lossy()
             );
-            handle_subprocess(Command::new("make").arg(&location_arg).arg("CMAKE_BUILD_TYPE=RelWithDebInfo")).await?;
-            handle_subprocess(Command::new("make").arg("install")).await?;
+            handle_subprocess(Command::new("make").arg(&location_arg).arg("CMAKE_BUILD_TYPE=RelWithDebInfo").current_dir(&repository)).await?;
+            handle_subprocess(Command::new("make").arg("install").current_dir(&repository)).await?;
         }
     }
     Ok(PostDownloadVersionType::Hash)
 }
 
-async fn send_request(client: &Client, version: &str) -> Result<reqwest::Response, reqwest::Error> {
-    let platform = utils::get_platform_name();
+fn removal_progress() -> progress::Sink {
+    progress::new_sink("remove", progress::ITEMS_TEMPLATE)
+}
+
+// Some Linux releases only ship the AppImage, so a missing asset falls through to the next one,
+// prefer_appimage decides which of the two is tried first
+fn get_file_types(config: &Config) -> Vec<&'static str> {
     let file_type = utils::get_file_type();
-    let request_url = format!(
-        "https://github.com/neovim/neovim/releases/download/{version}/{platform}.{file_type}",
-    );
+    if !cfg!(target_os = "linux") {
+        return vec![file_type];
+    }
+    match config.prefer_appimage {
+        Some(true) => vec![utils::APPIMAGE_FILE_TYPE, file_type],
+        _ => vec![file_type, utils::APPIMAGE_FILE_TYPE],
+    }
+}
+
+async fn send_request(
+    client: &Client,
+    version: &str,
+    config: &Config,
+) -> Result<(reqwest::Response, &'static str)> {
+    let mut fallbacks = get_file_types(config).into_iter();
+    let mut file_type = fallbacks.next().unwrap_or(utils::get_file_type());
+    loop {
+        let request_url = net::download_url(version, &utils::get_asset_name(file_type));
+        let response = net::send(client.get(request_url)).await?;
+        match fallbacks.next() {
+            Some(fallback) if response.status() == 404 => file_type = fallback,
+            _ => {
+                let is_found = response.status().is_success();
+                if is_found && file_type == utils::APPIMAGE_FILE_TYPE {
+                    info!("Using the AppImage for {version}, it's extracted into the same layout as the tarball");
+                } else if is_found && cfg!(target_os = "linux") {
+                    info!("Using the tarball for {version}");
+                }
+                return Ok((response, file_type));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+    use std::sync::Arc;
+
+    fn input_version(tag: &str) -> InputVersion {
+        InputVersion {
+            tag_name: tag.to_string(),
+            version: Version::parse(tag).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn mirror_copies_only_get_their_name_once_complete() {
+        let dir = TestDir::new("install-mirror-copy");
+        let source = dir.path().join("nvim-linux64.tar.gz");
+        std::fs::write(&source, "archive").unwrap();
+        let root = dir.path().join("downloads");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let version = input_version("v0.9.5");
+        let downloaded = copy_from_mirror(
+            &source,
+            "tar.gz",
+            &version,
+            &root,
+            Arc::new(progress::NoProgress),
+        )
+        .await
+        .unwrap();
+        let local = match downloaded {
+            PostDownloadVersionType::Standard(value) => value,
+            PostDownloadVersionType::Hash => panic!("a release isn't built"),
+        };
+        assert_eq!(
+            (local.file_name.as_str(), local.path.as_path()),
+            ("v0.9.5", root.as_path())
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("v0.9.5.tar.gz")).unwrap(),
+            "archive"
+        );
+        assert!(!root.join("v0.9.5.download").exists());
+    }
+
+    #[tokio::test]
+    async fn failed_mirror_copies_leave_nothing_behind() {
+        let dir = TestDir::new("install-mirror-failed");
+        // A directory can't be read as an archive, the copy fails after it started
+        let source = dir.path().join("nvim-linux64.tar.gz");
+        std::fs::create_dir_all(&source).unwrap();
+        let root = dir.path().join("downloads");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let version = input_version("v0.9.5");
+        let result = copy_from_mirror(
+            &source,
+            "tar.gz",
+            &version,
+            &root,
+            Arc::new(progress::NoProgress),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn mismatching_mirror_checksums_are_refused() {
+        let dir = TestDir::new("install-mirror-checksum");
+        let source = dir.path().join("nvim-linux64.tar.gz");
+        std::fs::write(&source, "archive").unwrap();
+        std::fs::write(
+            dir.path().join("nvim-linux64.tar.gz.sha256sum"),
+            "0000000000000000000000000000000000000000000000000000000000000000  nvim-linux64.tar.gz",
+        )
+        .unwrap();
+        let root = dir.path().join("downloads");
+        std::fs::create_dir_all(&root).unwrap();
 
-    client
-        .get(request_url)
-        .header("user-agent", "bob")
-        .send()
+        let error = copy_from_mirror(
+            &source,
+            "tar.gz",
+            &input_version("v0.9.5"),
+            &root,
+            Arc::new(progress::NoProgress),
+        )
         .await
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("doesn't match"), "{error}");
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    fn plant_binary(version_dir: &Path, mode: u32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin_dir = version_dir.join(utils::get_platform_name()).join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        let binary = bin_dir.join("nvim");
+        std::fs::write(&binary, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(mode)).unwrap();
+        binary
+    }
+
+    #[cfg(unix)]
+    fn mode_of(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o7777
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn binary_mode_defaults_to_755() {
+        let dir = TestDir::new("install-binary-mode-default");
+        let binary = plant_binary(dir.path(), 0o600);
+
+        set_binary_mode(dir.path(), &dir.config()).await.unwrap();
+        assert_eq!(mode_of(&binary), 0o755);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn binary_mode_follows_the_config() {
+        let dir = TestDir::new("install-binary-mode-config");
+        let binary = plant_binary(dir.path(), 0o600);
+
+        for (configured, expected) in [("750", 0o750), ("0o700", 0o700), (" 0755 ", 0o755)] {
+            let config = Config {
+                binary_mode: Some(configured.to_string()),
+                ..dir.config()
+            };
+            set_binary_mode(dir.path(), &config).await.unwrap();
+            assert_eq!(mode_of(&binary), expected, "{configured}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn invalid_binary_modes_leave_the_binary_alone() {
+        let dir = TestDir::new("install-binary-mode-invalid");
+        let binary = plant_binary(dir.path(), 0o600);
+
+        for configured in ["rwx", "", "789", "17777"] {
+            let config = Config {
+                binary_mode: Some(configured.to_string()),
+                ..dir.config()
+            };
+            let error = set_binary_mode(dir.path(), &config).await.err().unwrap();
+            assert!(error.to_string().contains("octal mode"), "{error}");
+            assert_eq!(mode_of(&binary), 0o600);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn missing_binaries_are_reported() {
+        let dir = TestDir::new("install-binary-mode-missing");
+
+        let error = set_binary_mode(dir.path(), &dir.config())
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            error.to_string().contains("Failed to set the mode"),
+            "{error}"
+        );
+    }
 }
@@ -0,0 +1,68 @@
+use super::progress;
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::{self, format::Writer, time::FormatTime};
+use tracing_subscriber::prelude::*;
+
+// Only bob's own events are logged, the http stack is far too chatty to be useful. The library
+// and the binary each log under their own crate name
+const TARGETS: [&str; 2] = [env!("CARGO_CRATE_NAME"), "bob"];
+
+fn bob_targets(level: LevelFilter) -> Targets {
+    Targets::new().with_targets(TARGETS.map(|target| (target, level)))
+}
+
+// Keeps the short local timestamps bob has always shown instead of full UTC ones
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, writer: &mut Writer<'_>) -> std::fmt::Result {
+        write!(writer, "{}", Local::now().format("%b %d %H:%M:%S%.3f"))
+    }
+}
+
+pub fn init(verbose: u8, quiet: bool, log_file: Option<&str>) -> Result<()> {
+    let console_level = match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let console = fmt::layer()
+        .with_target(false)
+        .with_timer(LocalTime)
+        .with_writer(|| progress::LogWriter)
+        .with_filter(bob_targets(console_level));
+
+    // The file always gets everything, whatever the console shows
+    let file = match log_file {
+        Some(path) => {
+            let file = match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(value) => value,
+                Err(error) => {
+                    return Err(anyhow!("Failed to open log file {path}, reason: {error}"))
+                }
+            };
+            let layer = fmt::layer()
+                .with_ansi(false)
+                .with_timer(LocalTime)
+                .with_writer(Mutex::new(file))
+                .with_filter(bob_targets(LevelFilter::TRACE));
+            Some(layer)
+        }
+        None => None,
+    };
+
+    let subscriber = tracing_subscriber::registry().with(console).with(file);
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok(())
+}
+
+// Errors from before the flags were read still have to be shown somewhere
+pub fn init_default() {
+    let _ = init(0, false, None);
+}
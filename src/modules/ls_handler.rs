@@ -1,50 +1,283 @@
-use crate::models::Config;
+use crate::enums::Version;
+use crate::models::{CommandOutput, Config, UpstreamVersion};
 
-use super::utils;
+use super::{gui, output, state, utils};
 use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::cmp::Ordering;
 use std::fs;
+use std::path::Path;
+use tracing::debug;
 use yansi::Paint;
 
-pub async fn start(config: Config) -> Result<()> {
+const STALE_NIGHTLY_DAYS: u64 = 14;
+
+struct ListedVersion {
+    name: String,
+    version: Option<Version>,
+    nightly: Option<UpstreamVersion>,
+    custom_binary: bool,
+    gui: bool,
+    pinned: bool,
+}
+
+pub async fn start(config: Config, verbose: bool, unused_only: bool) -> Result<CommandOutput> {
     let downloads_dir = match utils::get_downloads_folder(&config).await {
         Ok(value) => value,
         Err(error) => return Err(anyhow!(error)),
     };
     if cfg!(target_os = "macos") {
-        println!("Downloads dir is: {}", downloads_dir.display());
+        output::print_human(format!("Downloads dir is: {}", downloads_dir.display()));
+    }
+
+    if !utils::has_any_installed(&config).await? {
+        return Err(anyhow!("There are no versions installed"));
     }
 
-    let paths = fs::read_dir(downloads_dir)?
+    let paths = fs::read_dir(&downloads_dir)?
         .filter_map(|e| e.ok())
         .map(|entry| entry.path())
         .collect::<Vec<_>>();
     const VERSION_MAX_LEN: usize = 7;
 
-    if paths.is_empty() {
-        return Err(anyhow!("There are no versions installed"));
-    }
-
-    println!("Version | Status");
-    println!("{}+{}", "-".repeat(7 + 1), "-".repeat(10));
+    let pinned = state::get_pinned(&config).await?;
+    let mut versions = paths
+        .iter()
+        .filter(|path| path.is_dir())
+        .filter_map(|path| match path.file_name()?.to_str() {
+            Some(name) => Some(ListedVersion {
+                name: name.to_owned(),
+                version: Version::parse(name),
+                nightly: read_nightly_info(path),
+                custom_binary: path.join(utils::PROVENANCE_FILE).is_file(),
+                gui: gui::has_gui(path),
+                pinned: pinned.iter().any(|tag| utils::is_same_version(tag, name)),
+            }),
+            None => {
+                debug!("Skipping {} as its name isn't valid UTF-8", path.display());
+                None
+            }
+        })
+        .filter(|version| version.name != "neovim-git" && version.name != utils::CACHE_DIR)
+        .collect::<Vec<_>>();
+    versions.sort_by(compare_listed);
 
-    for path in paths {
-        let path_name = path.file_name().unwrap().to_str().unwrap();
-        if path_name == "neovim-git" {
-            continue;
+    let unused = state::unused_versions(&config).await?;
+    if unused_only {
+        versions.retain(|version| unused.contains(&version.name));
+        if versions.is_empty() {
+            output::print_human("Every installed version has been used at least once");
         }
+    }
+
+    if !versions.is_empty() {
+        output::print_human("Version | Status");
+        output::print_human(format!("{}+{}", "-".repeat(7 + 1), "-".repeat(10)));
+    }
 
+    let mut used = None;
+    for version in &versions {
+        let path_name = version.name.as_str();
         let width = (VERSION_MAX_LEN - path_name.len()) + 1;
-        if path.is_dir() {
-            if utils::is_version_used(path_name, &config).await {
-                println!("{path_name}{}| {}", " ".repeat(width), Paint::green("Used"));
-            } else {
-                println!(
-                    "{path_name}{}| {}",
-                    " ".repeat(width),
-                    Paint::yellow("Installed")
-                );
+        let mut custom = String::new();
+        if version.pinned {
+            custom.push_str(&format!(" {}", Paint::new("(pinned)").dimmed()));
+        }
+        if version.custom_binary {
+            custom.push_str(&format!(" {}", Paint::new("(custom binary)").dimmed()));
+        }
+        if verbose {
+            let gui = if version.gui { "(gui)" } else { "(no gui)" };
+            custom.push_str(&format!(" {}", Paint::new(gui).dimmed()));
+        }
+        if utils::is_version_used(path_name, &config).await {
+            used = Some(path_name);
+            // A .bob-version file or BOB_NVIM_VERSION picks what runs, the link stays as it was
+            if !utils::is_version_linked(path_name, &config).await {
+                custom.push_str(&format!(" {}", Paint::new("(override)").dimmed()));
             }
+            output::print_human(format!(
+                "{path_name}{}| {}{custom}",
+                " ".repeat(width),
+                Paint::green("Used")
+            ));
+        } else {
+            output::print_human(format!(
+                "{path_name}{}| {}{custom}",
+                " ".repeat(width),
+                Paint::yellow("Installed")
+            ));
         }
     }
-    Ok(())
+
+    if unused_only && !versions.is_empty() {
+        output::print_human(format!(
+            "{}",
+            Paint::new("These were never used, run `bob uninstall <version>` to free their space")
+                .dimmed()
+        ));
+    }
+
+    let nightly_age_days = match utils::nightly_age(&config).await {
+        Ok(age) => Some(age.as_secs() / (24 * 60 * 60)),
+        Err(_) => None,
+    };
+    if let Some(days) = nightly_age_days {
+        if days >= STALE_NIGHTLY_DAYS {
+            output::print_human(format!(
+                "{}",
+                Paint::yellow(format!(
+                    "nightly is {days} days old, run `bob install nightly` to update it"
+                ))
+            ));
+        }
+    }
+
+    let missing = state::get_missing_used(&config).await?;
+    if let Some(missing) = &missing {
+        output::print_human(Paint::red(format!(
+            "active version {missing} is missing from disk"
+        )));
+    }
+
+    Ok(CommandOutput {
+        action: String::from("list"),
+        details: json!({
+            "used": used,
+            "missing_used": missing,
+            "nightly_age_days": nightly_age_days,
+            "custom_binary": versions
+                .iter()
+                .filter(|version| version.custom_binary)
+                .map(|version| &version.name)
+                .collect::<Vec<_>>(),
+            "gui": versions
+                .iter()
+                .filter(|version| version.gui)
+                .map(|version| &version.name)
+                .collect::<Vec<_>>(),
+            "unused": unused,
+            "pinned": versions
+                .iter()
+                .filter(|version| version.pinned)
+                .map(|version| &version.name)
+                .collect::<Vec<_>>(),
+        }),
+        paths: versions
+            .iter()
+            .map(|version| downloads_dir.join(&version.name))
+            .collect(),
+        versions: versions.into_iter().map(|version| version.name).collect(),
+        ..Default::default()
+    })
+}
+
+fn read_nightly_info(path: &Path) -> Option<UpstreamVersion> {
+    let file = fs::read_to_string(path.join("bob.json")).ok()?;
+    serde_json::from_str(&file).ok()
+}
+
+// Releases are ordered by semver, nightlies come after them ordered by date and commit so the
+// output stays stable across runs
+fn compare_listed(a: &ListedVersion, b: &ListedVersion) -> Ordering {
+    match (&a.nightly, &b.nightly) {
+        (Some(first), Some(second)) => {
+            utils::compare_nightlies(first, second).then_with(|| a.name.cmp(&b.name))
+        }
+        _ => a.version.cmp(&b.version).then_with(|| a.name.cmp(&b.name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+
+    fn write_nightly(root: &Path, name: &str, published_at: &str, sha: &str) -> ListedVersion {
+        let path = root.join(name);
+        fs::create_dir_all(&path).unwrap();
+        let info = UpstreamVersion {
+            tag_name: String::from("nightly"),
+            published_at: published_at.to_owned(),
+            target_commitish: Some(sha.to_owned()),
+            ..Default::default()
+        };
+        fs::write(path.join("bob.json"), serde_json::to_string(&info).unwrap()).unwrap();
+        ListedVersion {
+            name: name.to_owned(),
+            version: Version::parse(name),
+            nightly: read_nightly_info(&path),
+            custom_binary: false,
+            gui: false,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn same_date_nightlies_are_ordered_by_commit() {
+        let dir = TestDir::new("ls-same-date-nightlies");
+        let date = "2024-01-01T00:00:00Z";
+        let names = |versions: &[ListedVersion]| {
+            versions
+                .iter()
+                .map(|version| version.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut forward = vec![
+            write_nightly(
+                dir.path(),
+                "nightly-b",
+                date,
+                "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            ),
+            write_nightly(
+                dir.path(),
+                "nightly-a",
+                date,
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            ),
+        ];
+        let mut backward = vec![
+            write_nightly(
+                dir.path(),
+                "nightly-a",
+                date,
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            ),
+            write_nightly(
+                dir.path(),
+                "nightly-b",
+                date,
+                "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            ),
+        ];
+        forward.sort_by(compare_listed);
+        backward.sort_by(compare_listed);
+
+        assert_eq!(names(&forward), ["nightly-a", "nightly-b"]);
+        assert_eq!(names(&forward), names(&backward));
+    }
+
+    #[test]
+    fn the_commit_only_breaks_ties_between_dates() {
+        let dir = TestDir::new("ls-nightly-dates");
+        let mut versions = [
+            write_nightly(
+                dir.path(),
+                "older",
+                "2024-01-01T00:00:00Z",
+                "ffffffffffffffffffffffffffffffffffffffff",
+            ),
+            write_nightly(
+                dir.path(),
+                "newer",
+                "2024-02-01T00:00:00Z",
+                "0000000000000000000000000000000000000000",
+            ),
+        ];
+        versions.reverse();
+        versions.sort_by(compare_listed);
+        assert_eq!(versions[0].name, "older");
+    }
 }
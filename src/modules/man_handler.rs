@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use clap::Command;
+use clap_mangen::Man;
+use roff::{italic, roman, Roff};
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::fs;
+
+const FILES: [(&str, &str); 8] = [
+    (
+        "~/.config/bob/config.json",
+        "Configuration file, every option in it is optional.",
+    ),
+    (
+        "~/.local/share/bob/",
+        "Downloads directory holding one directory per installed version, changed with downloads_dir.",
+    ),
+    (
+        "~/.local/share/neovim/",
+        "Link to the used version, its bin directory is what goes in PATH, changed with installation_location.",
+    ),
+    (
+        "~/.local/share/bob/state.json",
+        "The version selected with bob use, kept in state_dir instead when it's set.",
+    ),
+    (
+        "~/.local/share/bob/nightly/bob.json",
+        "Release metadata of the installed nightly, used to tell whether a newer nightly is out.",
+    ),
+    (
+        "~/.local/share/bob/remote_versions.json",
+        "Cached list of upstream releases used by the shell completions.",
+    ),
+    (
+        "~/.local/share/bob/bisect.json",
+        "The bisect in progress, kept in state_dir when it's set and removed once bob bisect finishes.",
+    ),
+    (
+        "~/.local/share/bob/cache/notes/",
+        "Release notes fetched with bob notes, kept as <tag>.md so they can be read offline.",
+    ),
+];
+
+pub async fn start(
+    command: Command,
+    subcommand: Option<String>,
+    out_dir: Option<PathBuf>,
+) -> Result<()> {
+    let pages = get_pages(command);
+
+    if let Some(out_dir) = out_dir {
+        fs::create_dir_all(&out_dir).await?;
+        for (page, is_main) in &pages {
+            let mut content = Vec::new();
+            render_page(page, *is_main, &mut content)?;
+            fs::write(out_dir.join(format!("{}.1", page.get_name())), content).await?;
+        }
+        return Ok(());
+    }
+
+    let name = match subcommand {
+        Some(subcommand) => format!("bob-{subcommand}"),
+        None => String::from("bob"),
+    };
+    let (page, is_main) = match pages.iter().find(|(page, _)| page.get_name() == name) {
+        Some(value) => value,
+        None => return Err(anyhow!("There is no man page for {name}")),
+    };
+    render_page(page, *is_main, &mut std::io::stdout())?;
+    Ok(())
+}
+
+// Pages are named the way git names its own, `man bob-install` documents `bob install`
+fn get_pages(command: Command) -> Vec<(Command, bool)> {
+    // The command is named after the package, the installed binary is bob
+    let mut command = command.name("bob");
+    command.build();
+    let version = command.get_version().unwrap_or_default().to_string();
+
+    let mut pages = vec![(command.clone(), true)];
+    for subcommand in command.get_subcommands() {
+        if subcommand.is_hide_set() || subcommand.get_name() == "help" {
+            continue;
+        }
+        let page = subcommand
+            .clone()
+            .name(format!("bob-{}", subcommand.get_name()))
+            .version(version.clone());
+        pages.push((page, false));
+    }
+    pages
+}
+
+fn render_page(page: &Command, is_main: bool, writer: &mut dyn Write) -> Result<()> {
+    let man = Man::new(page.clone());
+    man.render_title(writer)?;
+    man.render_name_section(writer)?;
+    man.render_synopsis_section(writer)?;
+    man.render_description_section(writer)?;
+    if page.get_arguments().any(|argument| !argument.is_hide_set()) {
+        man.render_options_section(writer)?;
+    }
+    if is_main {
+        man.render_subcommands_section(writer)?;
+        render_files_section(writer)?;
+    }
+    man.render_version_section(writer)?;
+    Ok(())
+}
+
+fn render_files_section(writer: &mut dyn Write) -> Result<()> {
+    let mut roff = Roff::new();
+    roff.control("SH", ["FILES"]);
+    roff.text([roman(
+        "Paths are the Linux defaults, macOS uses the same data paths and keeps the config in ~/Library/Application Support/bob/config.json.",
+    )]);
+    for (path, description) in FILES {
+        roff.control("TP", []);
+        roff.text([italic(path)]);
+        roff.text([roman(description)]);
+    }
+    roff.to_writer(writer)?;
+    Ok(())
+}
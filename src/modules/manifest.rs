@@ -0,0 +1,305 @@
+use super::utils;
+use crate::models::{CachedHash, HashCache, Manifest};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::fs;
+use tracing::debug;
+
+// Bob rewrites these next to the installation later on, they change without anything being wrong
+const UNTRACKED_FILES: [&str; 4] = [
+    "bob.json",
+    utils::PROVENANCE_FILE,
+    utils::MANIFEST_FILE,
+    utils::HASH_CACHE_FILE,
+];
+
+pub enum Verification {
+    Checked {
+        files: usize,
+        missing: Vec<String>,
+        modified: Vec<String>,
+        extra: Vec<String>,
+        // Files hashed again because their size or modification time changed, or that had no
+        // cached hash yet, against files whose cached hash was reused
+        revalidated: usize,
+        cached: usize,
+    },
+    Unverifiable(String),
+}
+
+struct HashedTree {
+    hashes: BTreeMap<String, String>,
+    cache: HashCache,
+    revalidated: usize,
+    cached: usize,
+}
+
+impl Verification {
+    pub fn is_failed(&self) -> bool {
+        match self {
+            Verification::Checked {
+                missing,
+                modified,
+                extra,
+                ..
+            } => !missing.is_empty() || !modified.is_empty() || !extra.is_empty(),
+            Verification::Unverifiable(_) => false,
+        }
+    }
+}
+
+pub async fn record(version_dir: &Path) -> Result<()> {
+    let root = version_dir.to_path_buf();
+    let tree =
+        tokio::task::spawn_blocking(move || hash_tree(&root, &HashCache::default())).await??;
+    let manifest = Manifest {
+        recorded_at: Utc::now(),
+        files: tree.hashes,
+    };
+    fs::write(
+        version_dir.join(utils::MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .await?;
+    save_cache(version_dir, &tree.cache).await;
+    debug!(
+        "Recorded the manifest of {} with {} files",
+        version_dir.display(),
+        manifest.files.len()
+    );
+    Ok(())
+}
+
+// A missing or unreadable cache only means every file gets hashed again
+async fn load_cache(version_dir: &Path) -> HashCache {
+    match fs::read_to_string(version_dir.join(utils::HASH_CACHE_FILE)).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => HashCache::default(),
+    }
+}
+
+async fn save_cache(version_dir: &Path, cache: &HashCache) {
+    let result = match serde_json::to_string(cache) {
+        Ok(content) => fs::write(version_dir.join(utils::HASH_CACHE_FILE), content)
+            .await
+            .map_err(anyhow::Error::from),
+        Err(error) => Err(error.into()),
+    };
+    if let Err(error) = result {
+        debug!(
+            "Couldn't save the hash cache of {}, reason: {error}",
+            version_dir.display()
+        );
+    }
+}
+
+// Linked builds are files bob doesn't own, whatever manages them may change them at any time
+pub async fn verify(version_dir: &Path) -> Result<Verification> {
+    let platform_dir = version_dir.join(utils::get_platform_name());
+    if fs::symlink_metadata(&platform_dir)
+        .await
+        .is_ok_and(|metadata| metadata.file_type().is_symlink())
+    {
+        let target = fs::read_link(&platform_dir).await?;
+        return Ok(Verification::Unverifiable(format!(
+            "linked to {}",
+            target.display()
+        )));
+    }
+
+    let manifest: Manifest = match fs::read_to_string(version_dir.join(utils::MANIFEST_FILE)).await
+    {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(value) => value,
+            Err(error) => {
+                return Err(anyhow!(
+                    "Failed to read the manifest of {}, reason: {error}",
+                    version_dir.display()
+                ))
+            }
+        },
+        Err(_) => {
+            return Ok(Verification::Unverifiable(String::from(
+                "installed before bob recorded manifests, reinstall it to record one",
+            )))
+        }
+    };
+
+    let root = version_dir.to_path_buf();
+    let cache = load_cache(version_dir).await;
+    let tree = tokio::task::spawn_blocking(move || hash_tree(&root, &cache)).await??;
+    save_cache(version_dir, &tree.cache).await;
+    debug!(
+        "Hashed {} files of {} again, {} were cached",
+        tree.revalidated,
+        version_dir.display(),
+        tree.cached
+    );
+    let actual = tree.hashes;
+    let missing = manifest
+        .files
+        .keys()
+        .filter(|path| !actual.contains_key(*path))
+        .cloned()
+        .collect();
+    let modified = manifest
+        .files
+        .iter()
+        .filter(|(path, hash)| actual.get(*path).is_some_and(|actual| actual != *hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+    let extra = actual
+        .keys()
+        .filter(|path| !manifest.files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    Ok(Verification::Checked {
+        files: manifest.files.len(),
+        missing,
+        modified,
+        extra,
+        revalidated: tree.revalidated,
+        cached: tree.cached,
+    })
+}
+
+// Paths always use / so a manifest reads the same on every platform. A file whose size and
+// modification time match its cache entry keeps the cached hash, anything else is hashed again
+fn hash_tree(root: &Path, cache: &HashCache) -> Result<HashedTree> {
+    let (files, _) = utils::collect_dir_entries(root)?;
+    let mut tree = HashedTree {
+        hashes: BTreeMap::new(),
+        cache: HashCache::default(),
+        revalidated: 0,
+        cached: 0,
+    };
+
+    for file in files {
+        let relative = file
+            .strip_prefix(root)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if UNTRACKED_FILES.contains(&relative.as_str()) {
+            continue;
+        }
+
+        let metadata = std::fs::symlink_metadata(&file)?;
+        if metadata.file_type().is_symlink() {
+            let hash = format!("symlink:{}", std::fs::read_link(&file)?.display());
+            tree.hashes.insert(relative, hash);
+            continue;
+        }
+
+        let size = metadata.len();
+        let modified = metadata.modified()?;
+        let hash = match cache.files.get(&relative) {
+            Some(entry) if entry.size == size && entry.modified == modified => {
+                tree.cached += 1;
+                entry.hash.clone()
+            }
+            _ => {
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut std::fs::File::open(&file)?, &mut hasher)?;
+                tree.revalidated += 1;
+                format!("{:x}", hasher.finalize())
+            }
+        };
+        tree.cache.files.insert(
+            relative.clone(),
+            CachedHash {
+                size,
+                modified,
+                hash: hash.clone(),
+            },
+        );
+        tree.hashes.insert(relative, hash);
+    }
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+
+    fn plant_install(dir: &TestDir) -> std::path::PathBuf {
+        let version_dir = dir.path().join("v0.9.5");
+        let bin_dir = version_dir.join(utils::get_platform_name()).join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join(utils::get_binary_name()), "binary").unwrap();
+        std::fs::write(bin_dir.join("xxd"), "xxd").unwrap();
+        version_dir
+    }
+
+    fn counts(verification: &Verification) -> (usize, usize, usize) {
+        match verification {
+            Verification::Checked {
+                modified,
+                revalidated,
+                cached,
+                ..
+            } => (modified.len(), *revalidated, *cached),
+            Verification::Unverifiable(reason) => panic!("unverifiable: {reason}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn unchanged_files_arent_hashed_again() {
+        let dir = TestDir::new("manifest-cached");
+        let version_dir = plant_install(&dir);
+        record(&version_dir).await.unwrap();
+
+        assert_eq!(counts(&verify(&version_dir).await.unwrap()), (0, 0, 2));
+
+        // Same size and modification time, only a rehash could notice the new content
+        let binary = version_dir
+            .join(utils::get_platform_name())
+            .join("bin")
+            .join(utils::get_binary_name());
+        let modified = std::fs::metadata(&binary).unwrap().modified().unwrap();
+        std::fs::write(&binary, "BINARY").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&binary)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(counts(&verify(&version_dir).await.unwrap()), (0, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn changed_files_are_hashed_again() {
+        let dir = TestDir::new("manifest-revalidated");
+        let version_dir = plant_install(&dir);
+        record(&version_dir).await.unwrap();
+
+        let xxd = version_dir
+            .join(utils::get_platform_name())
+            .join("bin")
+            .join("xxd");
+        std::fs::write(&xxd, "patched xxd").unwrap();
+        let verification = verify(&version_dir).await.unwrap();
+        assert!(verification.is_failed());
+        assert_eq!(counts(&verification), (1, 1, 1));
+
+        // The cache now holds the new hash, the file is still reported against the manifest
+        assert_eq!(counts(&verify(&version_dir).await.unwrap()), (1, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn a_broken_cache_hashes_everything() {
+        let dir = TestDir::new("manifest-broken-cache");
+        let version_dir = plant_install(&dir);
+        record(&version_dir).await.unwrap();
+        std::fs::write(version_dir.join(utils::HASH_CACHE_FILE), "{").unwrap();
+
+        assert_eq!(counts(&verify(&version_dir).await.unwrap()), (0, 2, 0));
+        assert_eq!(counts(&verify(&version_dir).await.unwrap()), (0, 0, 2));
+    }
+}
@@ -0,0 +1,342 @@
+use super::{manifest, output, prompt, state, use_handler, utils};
+use crate::enums::Version;
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tokio::{fs, process::Command};
+use tracing::{debug, info, warn};
+
+struct Imported {
+    tag: String,
+    source: PathBuf,
+    path: PathBuf,
+}
+
+struct Skipped {
+    source: PathBuf,
+    reason: String,
+}
+
+// Every directory under the known locations (and --from) holding bin/nvim is a candidate, the
+// tag comes from what the binary reports rather than the directory name. Originals are only
+// ever read, copied trees are independent of them and linked ones point back at them
+pub async fn start(config: Config, from: Vec<PathBuf>, link: bool) -> Result<CommandOutput> {
+    let downloads_dir = utils::get_downloads_folder(&config).await?;
+
+    let mut candidates = Vec::new();
+    for location in get_locations(from)? {
+        candidates.extend(find_candidates(&location).await);
+    }
+    if candidates.is_empty() {
+        info!("Found no Neovim installations to import");
+    }
+
+    let mut imported: Vec<Imported> = Vec::new();
+    let mut skipped = Vec::new();
+    for source in candidates {
+        let tag = match probe_tag(&source).await {
+            Ok(value) => value,
+            Err(error) => {
+                skipped.push(Skipped {
+                    source,
+                    reason: error.to_string(),
+                });
+                continue;
+            }
+        };
+        if imported.iter().any(|version| version.tag == tag)
+            || utils::is_version_installed(&tag, &config).await?
+        {
+            skipped.push(Skipped {
+                source,
+                reason: format!("{tag} is already installed"),
+            });
+            continue;
+        }
+
+        let version_dir = downloads_dir.join(&tag);
+        match import(&source, &version_dir, link).await {
+            Ok(()) => imported.push(Imported {
+                tag,
+                source,
+                path: version_dir,
+            }),
+            Err(error) => {
+                // Only what this run created is removed, the source is never touched
+                if fs::metadata(&version_dir).await.is_ok() {
+                    fs::remove_dir_all(&version_dir).await?;
+                }
+                skipped.push(Skipped {
+                    source,
+                    reason: error.to_string(),
+                });
+            }
+        }
+    }
+
+    for version in &imported {
+        output::print_human(format!(
+            "Imported {} from {}",
+            version.tag,
+            version.source.display()
+        ));
+    }
+    for version in &skipped {
+        output::print_human(format!(
+            "Skipped {}: {}",
+            version.source.display(),
+            version.reason
+        ));
+    }
+    if imported.iter().any(|version| version.tag == "nightly") {
+        warn!("The imported nightly has no record of when it was published, running `bob install nightly` replaces it with the latest one");
+    }
+    info!(
+        "Imported {} and skipped {} installations",
+        imported.len(),
+        skipped.len()
+    );
+
+    Ok(CommandOutput {
+        action: String::from("migrate"),
+        versions: imported.iter().map(|version| version.tag.clone()).collect(),
+        paths: imported
+            .iter()
+            .map(|version| version.path.clone())
+            .collect(),
+        details: json!({
+            "mode": if link { "link" } else { "copy" },
+            "imported": imported
+                .iter()
+                .map(|version| json!({
+                    "version": version.tag,
+                    "source": version.source,
+                    "path": version.path,
+                }))
+                .collect::<Vec<_>>(),
+            "skipped": skipped
+                .iter()
+                .map(|version| json!({
+                    "source": version.source,
+                    "reason": version.reason,
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        ..Default::default()
+    })
+}
+
+// asdf's neovim plugin and nvm style managers keep one directory per version, --from adds
+// directories of the user's own
+fn get_locations(from: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let home_dir = match dirs::home_dir() {
+        Some(value) => value,
+        None => return Err(anyhow!("Couldn't get the home directory")),
+    };
+    let asdf_dir = match std::env::var_os("ASDF_DATA_DIR") {
+        Some(value) => PathBuf::from(value),
+        None => home_dir.join(".asdf"),
+    };
+
+    let mut locations = vec![
+        asdf_dir.join("installs").join("neovim"),
+        home_dir.join(".neovim-versions"),
+    ];
+    locations.extend(from);
+    Ok(locations)
+}
+
+// A location can be a Neovim tree itself or a directory of them
+async fn find_candidates(location: &Path) -> Vec<PathBuf> {
+    if has_binary(location).await {
+        return vec![location.to_path_buf()];
+    }
+
+    let mut entries = match fs::read_dir(location).await {
+        Ok(value) => value,
+        Err(error) => {
+            debug!("Not looking in {}, reason: {error}", location.display());
+            return Vec::new();
+        }
+    };
+    let mut candidates = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.is_dir() && has_binary(&path).await {
+            candidates.push(path);
+        }
+    }
+    candidates.sort();
+    candidates
+}
+
+async fn has_binary(tree: &Path) -> bool {
+    fs::metadata(tree.join("bin").join(utils::get_binary_name()))
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+// Dev builds are whatever nightly was when they were made, so they become bob's nightly
+async fn probe_tag(tree: &Path) -> Result<String> {
+    let binary = tree.join("bin").join(utils::get_binary_name());
+    let output = match Command::new(&binary).arg("--version").output().await {
+        Ok(value) if value.status.success() => value,
+        _ => {
+            return Err(anyhow!(
+                "{} can't be run, it has to be an nvim binary built for this system",
+                binary.display()
+            ))
+        }
+    };
+    let output = String::from_utf8_lossy(&output.stdout);
+    let first_line = output.lines().next().unwrap_or_default();
+    let captures = match Regex::new(r"^NVIM v([0-9]+\.[0-9]+\.[0-9]+)(-dev)?")?.captures(first_line)
+    {
+        Some(value) => value,
+        None => return Err(anyhow!("{} isn't an nvim binary", binary.display())),
+    };
+
+    if captures.get(2).is_some() {
+        Ok(String::from("nightly"))
+    } else {
+        Ok(format!("v{}", &captures[1]))
+    }
+}
+
+async fn import(source: &Path, version_dir: &Path, link: bool) -> Result<()> {
+    fs::create_dir_all(version_dir).await?;
+    let target = version_dir.join(utils::get_platform_name());
+    let source = fs::canonicalize(source).await?;
+
+    if link {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                let result = std::os::windows::fs::symlink_dir(&source, &target);
+            } else {
+                let result = std::os::unix::fs::symlink(&source, &target);
+            }
+        }
+        if let Err(error) = result {
+            return Err(anyhow!(
+                "Failed to link {} to {}, reason: {error}",
+                target.display(),
+                source.display()
+            ));
+        }
+        debug!("Linked {} to {}", target.display(), source.display());
+        return Ok(());
+    }
+
+    let copy_target = target.clone();
+    tokio::task::spawn_blocking(move || utils::copy_dir(&source, &copy_target)).await??;
+    debug!("Copied {}", target.display());
+    manifest::record(version_dir).await
+}
+
+// Versions installed before per_arch_dirs was turned on stay in the shared downloads folder
+// where no architecture looks anymore, they're moved into this architecture's folder once the
+// user agrees. Another architecture's folder is never touched, only what has no owner yet
+pub async fn offer_arch_migration(config: &Config) -> Result<()> {
+    if !utils::is_per_arch(config) {
+        return Ok(());
+    }
+    let shared_dir = utils::get_shared_downloads_location(config)?;
+    let legacy_entries = find_legacy_entries(&shared_dir).await;
+    if legacy_entries.is_empty() {
+        return Ok(());
+    }
+
+    if !prompt::can_confirm() {
+        warn!(
+            "{} holds versions installed before per_arch_dirs was turned on, run bob in a terminal or pass --yes to move them to {}",
+            shared_dir.display(),
+            utils::get_arch_dir_name()
+        );
+        return Ok(());
+    }
+    if !prompt::confirm(&prompt::ARCH_MIGRATION)? {
+        info!("Left them in {}", shared_dir.display());
+        return Ok(());
+    }
+
+    let arch_dir = utils::get_downloads_folder(config).await?;
+    for name in &legacy_entries {
+        let target = arch_dir.join(name);
+        if fs::symlink_metadata(&target).await.is_ok() {
+            warn!("Not moving {name}, {} already exists", target.display());
+            continue;
+        }
+        fs::rename(shared_dir.join(name), &target).await?;
+        debug!("Moved {name} to {}", target.display());
+    }
+
+    // A configured state_dir is shared the same way the downloads folder is
+    let legacy_config = Config {
+        per_arch_dirs: Some(false),
+        ..config.clone()
+    };
+    let state_dir = state::get_state_dir(config).await?;
+    fs::create_dir_all(&state_dir).await?;
+    for path in state::get_state_files(&legacy_config).await? {
+        let target = state_dir.join(path.file_name().unwrap_or_default());
+        if fs::metadata(&target).await.is_err() {
+            fs::rename(&path, &target).await?;
+        }
+    }
+
+    // The shared installation folder links, copies or points into the folder the versions just
+    // left
+    use_handler::remove_installation(&utils::get_shared_installation_folder(config)?, None).await?;
+    if let Some(used) = state::load(config).await?.used {
+        if let Some(version) = Version::parse(&used) {
+            let version = InputVersion {
+                tag_name: used,
+                version,
+            };
+            if utils::is_version_installed(&version.tag_name, config).await? {
+                use_handler::relink(&version, config).await?;
+            }
+        }
+    }
+
+    info!(
+        "Moved {} entries to {}",
+        legacy_entries.len(),
+        arch_dir.display()
+    );
+    warn!(
+        "nvim is now linked from {}, run `bob env --apply` to put it in PATH",
+        utils::get_installation_folder(config)?
+            .join("bin")
+            .display()
+    );
+    Ok(())
+}
+
+// Version folders, the source checkout and the cache, the per-architecture folders sitting next
+// to them all fail to parse as a version
+async fn find_legacy_entries(shared_dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut entries = match fs::read_dir(shared_dir).await {
+        Ok(value) => value,
+        Err(_) => return names,
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_dir = entry.file_type().await.is_ok_and(|value| value.is_dir());
+        match entry.file_name().into_string() {
+            Ok(name)
+                if is_dir
+                    && (name == "neovim-git"
+                        || name == utils::CACHE_DIR
+                        || Version::parse(&name).is_some()) =>
+            {
+                names.push(name)
+            }
+            _ => (),
+        }
+    }
+    names.sort();
+    names
+}
@@ -1,8 +1,41 @@
+pub mod benchmark_handler;
+pub mod bisect_handler;
+pub mod changelog_handler;
 pub mod cli;
+pub mod complete_handler;
+pub mod config_handler;
+pub mod contents_handler;
+pub mod desktop;
+pub mod doctor_handler;
+pub mod downloader;
+pub mod env_handler;
 pub mod erase_handler;
 mod expand_archive;
+pub mod gui;
+pub mod info_handler;
 pub mod install_handler;
+pub mod logging;
 pub mod ls_handler;
+pub mod man_handler;
+pub mod manifest;
+pub mod migrate_handler;
+pub mod net;
+pub mod notes_handler;
+pub mod notify;
+pub mod open_handler;
+pub mod output;
+pub mod pin_handler;
+pub mod progress;
+pub mod prompt;
+pub mod reinstall_handler;
+pub mod report_handler;
+pub mod reset_handler;
+pub mod self_update_handler;
+pub mod state;
+pub mod test_handler;
+#[cfg(test)]
+pub mod test_support;
 pub mod uninstall_handler;
 pub mod use_handler;
 pub mod utils;
+pub mod verify_handler;
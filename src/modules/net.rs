@@ -0,0 +1,490 @@
+use super::progress::Sink;
+use super::utils;
+use crate::enums::BobError;
+use crate::models::{Config, ReleaseAsset};
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+use futures_util::stream::StreamExt;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, RETRY_AFTER, USER_AGENT,
+};
+use reqwest::{Certificate, Client, Proxy, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::fs;
+use tracing::{debug, trace, warn};
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_DOWNLOAD_URL: &str = "https://github.com";
+const NEOVIM_REPO: &str = "neovim/neovim";
+const BOB_REPO: &str = "MordechaiHadad/bob";
+
+// Where bob talks to GitHub, GitHub Enterprise mirrors and local test servers only differ in
+// these two hosts, the paths under them stay the same
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+    pub api: String,
+    pub download: String,
+}
+
+impl Endpoints {
+    pub fn new(api: &str, download: &str) -> Endpoints {
+        Endpoints {
+            api: api.trim_end_matches('/').to_owned(),
+            download: download.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Endpoints {
+        Endpoints::new(
+            config.github_api_url.as_deref().unwrap_or(DEFAULT_API_URL),
+            config
+                .github_download_url
+                .as_deref()
+                .unwrap_or(DEFAULT_DOWNLOAD_URL),
+        )
+    }
+}
+
+static ENDPOINTS: RwLock<Option<Endpoints>> = RwLock::new(None);
+
+pub fn set_endpoints(endpoints: Endpoints) {
+    if let Ok(mut current) = ENDPOINTS.write() {
+        *current = Some(endpoints);
+    }
+}
+
+fn get_endpoints() -> Endpoints {
+    match ENDPOINTS.read() {
+        Ok(current) => current
+            .clone()
+            .unwrap_or_else(|| Endpoints::new(DEFAULT_API_URL, DEFAULT_DOWNLOAD_URL)),
+        Err(_) => Endpoints::new(DEFAULT_API_URL, DEFAULT_DOWNLOAD_URL),
+    }
+}
+
+pub fn repo_api_url(repo: &str, path: &str) -> String {
+    format!("{}/repos/{repo}/{path}", get_endpoints().api)
+}
+
+pub fn bob_api_url(path: &str) -> String {
+    repo_api_url(BOB_REPO, path)
+}
+
+pub fn download_url(version: &str, asset: &str) -> String {
+    format!(
+        "{}/{NEOVIM_REPO}/releases/download/{version}/{asset}",
+        get_endpoints().download
+    )
+}
+
+pub fn repository_url() -> String {
+    format!("{}/{NEOVIM_REPO}", get_endpoints().download)
+}
+
+pub fn validate_endpoint(name: &str, url: &str) -> Result<()> {
+    match Url::parse(url) {
+        Ok(value) if matches!(value.scheme(), "http" | "https") => Ok(()),
+        _ => Err(anyhow!("{name} ({url}) has to be an http or https URL")),
+    }
+}
+
+const OFFLINE_ENV_VAR: &str = "BOB_OFFLINE";
+
+// Set in offline mode to where versions can still come from, the downloads directory and
+// local_mirror_dir
+static OFFLINE_DIRS: RwLock<Option<Vec<PathBuf>>> = RwLock::new(None);
+
+// BOB_OFFLINE=1 turns it on for a single run without touching the config
+pub fn is_offline_configured(config: &Config) -> bool {
+    std::env::var(OFFLINE_ENV_VAR).is_ok_and(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+        || config.offline == Some(true)
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE_DIRS.read().is_ok_and(|dirs| dirs.is_some())
+}
+
+pub fn get_offline_dirs() -> Vec<PathBuf> {
+    match OFFLINE_DIRS.read() {
+        Ok(dirs) => dirs.clone().unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn set_offline(config: &Config) -> Result<()> {
+    let dirs = if is_offline_configured(config) {
+        let mut dirs = vec![utils::get_downloads_location(config)?];
+        dirs.extend(config.local_mirror_dir.as_ref().map(PathBuf::from));
+        Some(dirs)
+    } else {
+        None
+    };
+    if let Ok(mut current) = OFFLINE_DIRS.write() {
+        *current = dirs;
+    }
+    Ok(())
+}
+
+pub fn ensure_online(action: &str) -> Result<()> {
+    if is_offline() {
+        return Err(BobError::Offline(action.to_owned()).into());
+    }
+    Ok(())
+}
+
+// Every command builds its client first, so this is also where the configured endpoints are
+// picked up for the rest of the run
+pub async fn create_client(config: &Config) -> Result<Client> {
+    set_endpoints(Endpoints::from_config(config));
+    set_offline(config)?;
+
+    let mut builder = Client::builder()
+        .user_agent("bob")
+        .connect_timeout(Duration::from_secs(30));
+
+    if let Some(proxy) = &config.proxy {
+        let proxy = match Proxy::all(proxy) {
+            Ok(value) => value,
+            Err(error) => return Err(anyhow!("Invalid proxy {proxy}, reason: {error}")),
+        };
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &config.ca_cert_path {
+        let bundle = match fs::read(path).await {
+            Ok(value) => value,
+            Err(error) => {
+                return Err(anyhow!(
+                    "Failed to read CA certificate bundle {path}, reason: {error}"
+                ))
+            }
+        };
+        let certificate = match Certificate::from_pem(&bundle) {
+            Ok(value) => value,
+            Err(error) => {
+                return Err(anyhow!(
+                    "Failed to load CA certificate bundle {path}, reason: {error}"
+                ))
+            }
+        };
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    let mut headers = match &config.extra_headers {
+        Some(extra_headers) => parse_extra_headers(extra_headers)?,
+        None => HeaderMap::new(),
+    };
+    if let Some(token) = get_github_token(config).await {
+        let value = match HeaderValue::from_str(&format!("Bearer {token}")) {
+            Ok(value) => value,
+            Err(_) => return Err(anyhow!("The GitHub token contains invalid characters")),
+        };
+        headers.insert(AUTHORIZATION, value);
+    }
+    builder = builder.default_headers(headers);
+
+    if let Some(true) = config.danger_accept_invalid_certs {
+        warn!(
+            "TLS certificate validation is disabled, downloads are not protected against tampering"
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder.build()?)
+}
+
+// Headers bob sets itself always win, configuring them here would only break requests in
+// confusing ways
+const RESERVED_HEADERS: [HeaderName; 3] = [USER_AGENT, ACCEPT, AUTHORIZATION];
+
+fn parse_extra_headers(extra_headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in extra_headers {
+        let header_name = match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(value) => value,
+            Err(_) => return Err(anyhow!("Invalid header name {name} in extra_headers")),
+        };
+        if RESERVED_HEADERS.contains(&header_name) {
+            warn!("Ignoring {name} from extra_headers, bob sets it itself");
+            continue;
+        }
+        let header_value = match HeaderValue::from_str(value) {
+            Ok(value) => value,
+            Err(_) => return Err(anyhow!("Invalid value for header {name} in extra_headers")),
+        };
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+pub const TOKEN_ENV_VAR: &str = "GITHUB_TOKEN";
+
+// An explicit token always wins, GITHUB_TOKEN over the config's. gh's login is only borrowed
+// when it's opted into
+pub async fn get_github_token(config: &Config) -> Option<String> {
+    if let Ok(token) = std::env::var(TOKEN_ENV_VAR) {
+        if !token.is_empty() {
+            return Some(token);
+        }
+    }
+    if let Some(token) = &config.github_token {
+        if !token.is_empty() {
+            return Some(token.clone());
+        }
+    }
+    if let Some(true) = config.use_gh_auth {
+        return read_gh_token().await;
+    }
+    None
+}
+
+fn get_gh_config_dir() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(path));
+    }
+    if let Ok(path) = std::env::var("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(path).join("gh"));
+    }
+    if cfg!(windows) {
+        return dirs::config_dir().map(|path| path.join("GitHub CLI"));
+    }
+    dirs::home_dir().map(|path| path.join(".config").join("gh"))
+}
+
+// hosts.yml is a flat map of hosts to their settings, pulling in a YAML parser for one key
+// isn't worth it. Newer gh versions keep the token in the system keyring, then there's
+// nothing to find here and requests are just sent unauthenticated
+async fn read_gh_token() -> Option<String> {
+    let hosts_file = get_gh_config_dir()?.join("hosts.yml");
+    let content = match fs::read_to_string(&hosts_file).await {
+        Ok(value) => value,
+        Err(error) => {
+            debug!("Couldn't read {}: {error}", hosts_file.display());
+            return None;
+        }
+    };
+
+    let mut in_github = false;
+    for line in content.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            in_github = line.trim_end() == "github.com:";
+            continue;
+        }
+        if !in_github {
+            continue;
+        }
+        if let Some(token) = line.trim().strip_prefix("oauth_token:") {
+            let token = token.trim().trim_matches(|c| c == '"' || c == '\'');
+            if !token.is_empty() {
+                return Some(token.to_owned());
+            }
+        }
+    }
+    debug!("No github.com token in {}", hosts_file.display());
+    None
+}
+
+pub async fn check_connectivity(client: &Client) -> Result<()> {
+    // Any response at all means GitHub is reachable, even an error status
+    match send(
+        client
+            .head(format!("{}/zen", get_endpoints().api))
+            .timeout(Duration::from_secs(5)),
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(error) => Err(BobError::Network(error.to_string()).into()),
+    }
+}
+
+// Every request goes through here so -v shows what bob fetched and how long it took
+// In offline mode nothing leaves the machine, whatever asked for the request fails right away
+pub async fn send(request: RequestBuilder) -> Result<Response> {
+    if is_offline() {
+        let url = match request.build() {
+            Ok(value) => value.url().to_string(),
+            Err(error) => return Err(error.into()),
+        };
+        return Err(BobError::Offline(format!("fetch {url}")).into());
+    }
+
+    let started = Instant::now();
+    let result = request.send().await;
+    let elapsed = started.elapsed().as_millis();
+    match &result {
+        Ok(response) => debug!("{} {} in {elapsed}ms", response.status(), response.url()),
+        Err(error) => debug!("Request failed after {elapsed}ms: {error}"),
+    }
+    Ok(result?)
+}
+
+// Release assets are small enough to keep in memory until they're verified and unpacked
+pub async fn download_asset(
+    client: &Client,
+    asset: &ReleaseAsset,
+    progress: Sink,
+) -> Result<Vec<u8>> {
+    let response = send(client.get(&asset.browser_download_url))
+        .await?
+        .error_for_status()?;
+    let total_size = response.content_length().unwrap_or(asset.size);
+    progress.start(total_size);
+    progress.message(&format!("Downloading {}", asset.name));
+
+    let mut bytes = Vec::with_capacity(total_size as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(value) => value,
+            Err(error) => {
+                return Err(BobError::DownloadFailed {
+                    version: asset.name.clone(),
+                    reason: error.to_string(),
+                }
+                .into())
+            }
+        };
+        bytes.extend_from_slice(&chunk);
+        progress.advance(chunk.len() as u64);
+    }
+    progress.message(&format!("Downloaded {}", asset.name));
+    progress.finish();
+    Ok(bytes)
+}
+
+pub async fn get_json<T: DeserializeOwned>(client: &Client, path: &str) -> Result<T> {
+    let url = format!("{}/repos/{NEOVIM_REPO}/{path}", get_endpoints().api);
+    get_json_from(client, &url).await
+}
+
+pub async fn get_json_from<T: DeserializeOwned>(client: &Client, url: &str) -> Result<T> {
+    let url = url.to_owned();
+    let response = send_api_request(client, &url).await?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(BobError::Api {
+            url,
+            status: status.as_u16(),
+            message: describe_error_body(&body),
+        }
+        .into());
+    }
+    parse_json_body(url, status, &body)
+}
+
+// A proxy's error page or a changed API would otherwise only show up as serde complaining about
+// some line and column, the status and the start of the body tell what was actually sent
+fn parse_json_body<T: DeserializeOwned>(url: String, status: StatusCode, body: &str) -> Result<T> {
+    let error = match serde_json::from_str(body) {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+    debug!("Unexpected response body from {url}: {body}");
+
+    let message = match serde_json::from_str::<serde_json::Value>(body) {
+        Err(_) if body.trim().is_empty() => String::from("the response was empty"),
+        Err(_) => format!(
+            "the response isn't JSON, it starts with: {}",
+            utils::get_excerpt(body)
+        ),
+        Ok(value) => match value.get("message").and_then(|message| message.as_str()) {
+            Some(message) => format!("GitHub answered \"{message}\" instead of the expected data"),
+            None => format!(
+                "the response doesn't have the expected shape ({error}), it starts with: {}",
+                utils::get_excerpt(body)
+            ),
+        },
+    };
+    Err(BobError::Api {
+        url,
+        status: status.as_u16(),
+        message,
+    }
+    .into())
+}
+
+// GitHub's error documents carry a message field which says way more than the raw body
+fn describe_error_body(body: &str) -> String {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(body) {
+        if let Some(message) = value.get("message").and_then(|message| message.as_str()) {
+            return message.to_string();
+        }
+    }
+    utils::get_excerpt(body)
+}
+
+// Longest we're willing to sit on a secondary rate limit before giving up
+const MAX_RETRY_AFTER: u64 = 60;
+
+async fn send_api_request(client: &Client, url: &str) -> Result<Response> {
+    let request = || {
+        client
+            .get(url)
+            .header(ACCEPT, "application/vnd.github.v3+json")
+    };
+    let mut response = send(request()).await?;
+
+    if let Some(retry_after) = get_retry_after(&response) {
+        let wait = retry_after.min(MAX_RETRY_AFTER);
+        warn!("Hit GitHub's secondary rate limit, retrying in {wait} seconds");
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+        response = send(request()).await?;
+    }
+
+    if let Some(remaining) = response.headers().get("x-ratelimit-remaining") {
+        trace!(
+            "{} GitHub API requests left",
+            remaining.to_str().unwrap_or("?")
+        );
+    }
+
+    if !matches!(
+        response.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return Ok(response);
+    }
+
+    let headers = response.headers();
+    if headers
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        == Some("0")
+    {
+        let reset = headers
+            .get("x-ratelimit-reset")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok())
+            .and_then(|value| Utc.timestamp_opt(value, 0).single());
+        return Err(BobError::RateLimited { reset }.into());
+    }
+    if get_retry_after(&response).is_some() {
+        return Err(BobError::SecondaryRateLimited.into());
+    }
+
+    Ok(response)
+}
+
+fn get_retry_after(response: &Response) -> Option<u64> {
+    if !matches!(
+        response.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,92 @@
+use super::{output, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::debug;
+
+const NOTES_DIR: &str = "notes";
+
+pub async fn start(
+    client: &Client,
+    config: Config,
+    version: InputVersion,
+) -> Result<CommandOutput> {
+    let notes = fetch_release_notes(client, &config, &version.tag_name).await?;
+    if notes.trim().is_empty() {
+        output::print_human(format!(
+            "{} was published without release notes",
+            version.tag_name
+        ));
+    } else {
+        output::print_human(notes.trim_end());
+    }
+
+    Ok(CommandOutput {
+        action: String::from("notes"),
+        versions: vec![version.tag_name],
+        details: json!({ "notes": notes }),
+        ..Default::default()
+    })
+}
+
+// Release notes never change once a release is out, so a cached copy is used without asking
+// GitHub. Nightly's are rewritten with every build, they're fetched first and the cached copy
+// is only a fallback for when GitHub can't be reached. Empty notes come back as an empty string
+pub async fn fetch_release_notes(client: &Client, config: &Config, tag: &str) -> Result<String> {
+    let version = match Version::parse(tag) {
+        Some(Version::Hash(_)) => {
+            return Err(anyhow!(
+                "{tag} is a commit build, only releases have release notes"
+            ))
+        }
+        Some(value) => value,
+        None => return Err(BobError::InvalidVersion(tag.to_owned()).into()),
+    };
+    let cache_file = get_notes_dir(config)?.join(format!("{tag}.md"));
+
+    if !matches!(version, Version::Nightly) {
+        if let Ok(notes) = fs::read_to_string(&cache_file).await {
+            debug!("Using cached release notes from {}", cache_file.display());
+            return Ok(notes);
+        }
+    }
+
+    let release = match utils::get_release(client, tag).await {
+        Ok(value) => value,
+        Err(error) => match fs::read_to_string(&cache_file).await {
+            Ok(notes) => {
+                debug!("Couldn't fetch the release notes of {tag}, using the cached ones: {error}");
+                return Ok(notes);
+            }
+            Err(_) => return Err(error),
+        },
+    };
+    let notes = release.body.unwrap_or_default();
+    if !notes.trim().is_empty() {
+        save_notes(&cache_file, &notes).await?;
+    }
+    Ok(notes)
+}
+
+fn get_notes_dir(config: &Config) -> Result<PathBuf> {
+    Ok(utils::get_cache_dir(config)?.join(NOTES_DIR))
+}
+
+async fn save_notes(cache_file: &Path, notes: &str) -> Result<()> {
+    if let Some(parent) = cache_file.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let temp_file = cache_file.with_extension("md.tmp");
+    fs::write(&temp_file, notes).await?;
+    if let Err(error) = fs::rename(&temp_file, cache_file).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            cache_file.display()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::models::Config;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+use tracing::debug;
+
+// A notification daemon that's stuck shouldn't keep a timer's run from finishing
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Sent through whatever the platform ships with, notify-send on Linux, osascript on MacOS and a
+// PowerShell toast on Windows. Headless machines have no notification service, so failing to
+// send one is only logged and never fails the command
+pub async fn send(config: &Config, summary: &str, body: &str) {
+    if config.notify != Some(true) {
+        return;
+    }
+
+    let mut command = get_command(summary, body);
+    command.kill_on_drop(true);
+    match time::timeout(TIMEOUT, command.output()).await {
+        Ok(Ok(output)) if output.status.success() => debug!("Sent notification: {summary}"),
+        Ok(Ok(output)) => debug!(
+            "Couldn't send a notification, reason: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(Err(error)) => debug!("Couldn't send a notification, reason: {error}"),
+        Err(_) => debug!(
+            "Gave up sending a notification after {}s",
+            TIMEOUT.as_secs()
+        ),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn get_command(summary: &str, body: &str) -> Command {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut command = Command::new("osascript");
+    command.args([
+        "-e",
+        &format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(summary)
+        ),
+    ]);
+    command
+}
+
+#[cfg(windows)]
+fn get_command(summary: &str, body: &str) -> Command {
+    // Toasts need a registered app id, PowerShell's own is always there
+    const APP_ID: &str =
+        "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $template.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($template.CreateTextNode({})) > $null; \
+         $text.Item(1).AppendChild($template.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($template))",
+        quote(summary),
+        quote(body),
+        quote(APP_ID)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn get_command(summary: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=bob", summary, body]);
+    command
+}
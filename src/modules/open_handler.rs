@@ -0,0 +1,88 @@
+use super::{net, output, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, warn};
+
+// Pages live under the same host release archives come from, so a github_download_url pointing
+// at an Enterprise instance or mirror opens pages there as well
+pub async fn start(
+    client: &Client,
+    config: Config,
+    version: Option<InputVersion>,
+    changelog: bool,
+) -> Result<CommandOutput> {
+    let (url, versions) = match version {
+        _ if changelog => (get_changelog_url(client, &config).await?, Vec::new()),
+        Some(version) => (get_page_url(&version), vec![version.tag_name]),
+        None => {
+            return Err(
+                BobError::Usage(String::from("Give a version to open or pass --changelog")).into(),
+            )
+        }
+    };
+
+    let opened = match utils::open_in_default_app(&url) {
+        Ok(()) => {
+            info!("Opened {url}");
+            true
+        }
+        Err(error) => {
+            warn!("{error}");
+            output::print_human(&url);
+            false
+        }
+    };
+
+    Ok(CommandOutput {
+        action: String::from("open"),
+        versions,
+        details: json!({
+            "url": url,
+            "opened": opened,
+        }),
+        ..Default::default()
+    })
+}
+
+fn get_page_url(version: &InputVersion) -> String {
+    match &version.version {
+        Version::Hash(hash) => format!("{}/commit/{hash}", net::repository_url()),
+        _ => format!(
+            "{}/releases/tag/{}",
+            net::repository_url(),
+            version.tag_name
+        ),
+    }
+}
+
+// Everything that went into nightly between the installed build and the latest one
+async fn get_changelog_url(client: &Client, config: &Config) -> Result<String> {
+    if !utils::is_version_installed("nightly", config).await? {
+        return Err(BobError::NotInstalled(String::from("nightly")).into());
+    }
+    let local = utils::get_local_nightly(config).await?;
+    let upstream = utils::get_upstream_nightly(client).await?;
+
+    let (local_sha, upstream_sha) = match (
+        utils::get_commit_sha(&local),
+        utils::get_commit_sha(&upstream),
+    ) {
+        (Some(local_sha), Some(upstream_sha)) => (local_sha, upstream_sha),
+        (None, _) => {
+            return Err(anyhow!(
+                "The installed nightly doesn't record its commit, run `bob install nightly` to update it first"
+            ))
+        }
+        (_, None) => return Err(anyhow!("Upstream nightly doesn't name the commit it was built from")),
+    };
+    if utils::is_same_nightly(&local, &upstream) {
+        info!("The installed nightly is the latest one");
+    }
+    Ok(format!(
+        "{}/compare/{local_sha}...{upstream_sha}",
+        net::repository_url()
+    ))
+}
@@ -0,0 +1,128 @@
+use crate::enums;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::Display;
+use std::future::Future;
+use std::io::{IsTerminal, Write};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+static JSON_OUTPUT: OnceLock<()> = OnceLock::new();
+
+tokio::task_local! {
+    static SILENT: ();
+}
+
+// Once enabled stdout only ever carries the one JSON object describing the result
+pub fn enable_json() {
+    let _ = JSON_OUTPUT.set(());
+}
+
+pub fn is_json() -> bool {
+    JSON_OUTPUT.get().is_some()
+}
+
+// Library users get results back as values, nothing is written to the stdout they own while
+// one of their calls runs. The CLI linked into the same process keeps printing
+pub async fn silenced<F: Future>(future: F) -> F::Output {
+    SILENT.scope((), future).await
+}
+
+pub fn is_silent() -> bool {
+    SILENT.try_with(|_| ()).is_ok()
+}
+
+pub fn print_human(text: impl Display) {
+    if is_silent() {
+        return;
+    }
+    if is_json() {
+        eprintln!("{text}");
+    } else {
+        println!("{text}");
+    }
+}
+
+// Goes through $PAGER when stdout is a terminal, less by default with -F so text that fits on one
+// screen is printed as is. Windows has no usable default, it's only paged when PAGER is set
+pub async fn print_paged(text: &str) {
+    if is_json() || !std::io::stdout().is_terminal() {
+        print_human(text);
+        return;
+    }
+    let pager = match std::env::var("PAGER") {
+        Ok(value) if !value.trim().is_empty() => value,
+        _ if cfg!(windows) => {
+            print_human(text);
+            return;
+        }
+        _ => String::from("less"),
+    };
+
+    let mut words = pager.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or_default());
+    command.args(words).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.spawn() {
+        Ok(value) => value,
+        Err(_) => {
+            print_human(text);
+            return;
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early (q before the end) is not an error
+        let _ = stdin.write_all(text.as_bytes()).await;
+        let _ = stdin.write_all(b"\n").await;
+    }
+    let _ = child.wait().await;
+}
+
+pub fn print_json(value: &impl Serialize) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    serde_json::to_writer(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+pub fn print_error(error: &anyhow::Error) {
+    let _ = print_json(&json!({
+        "error": {
+            "kind": enums::get_error_kind(error),
+            "message": error.to_string(),
+        }
+    }));
+}
+
+// Build tools print their progress on stdout, which would corrupt the JSON result
+pub fn child_stdout() -> Stdio {
+    if is_silent() {
+        Stdio::null()
+    } else if is_json() {
+        Stdio::from(std::io::stderr())
+    } else {
+        Stdio::inherit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn silence_ends_with_the_call() {
+        assert!(!is_silent());
+        silenced(async {
+            assert!(is_silent());
+            tokio::task::yield_now().await;
+            assert!(is_silent());
+        })
+        .await;
+        assert!(!is_silent());
+    }
+}
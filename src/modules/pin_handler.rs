@@ -0,0 +1,35 @@
+use super::{state, utils};
+use crate::enums::BobError;
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::Result;
+use serde_json::json;
+use tracing::info;
+
+// Unpinning doesn't need the version on disk, that's how a pin left behind by a version removed
+// by hand is cleared
+pub async fn start(config: Config, version: InputVersion, pin: bool) -> Result<CommandOutput> {
+    if pin && !utils::is_version_installed(&version.tag_name, &config).await? {
+        return Err(BobError::NotInstalled(version.tag_name).into());
+    }
+
+    let changed = state::set_pinned(&config, &version.tag_name, pin).await?;
+    match (pin, changed) {
+        (true, true) => info!(
+            "Pinned {}, it won't be removed until unpinned",
+            version.tag_name
+        ),
+        (true, false) => info!("{} is already pinned", version.tag_name),
+        (false, true) => info!("Unpinned {}", version.tag_name),
+        (false, false) => info!("{} isn't pinned", version.tag_name),
+    }
+
+    Ok(CommandOutput {
+        action: String::from(if pin { "pin" } else { "unpin" }),
+        versions: vec![version.tag_name],
+        details: json!({
+            "pinned": pin,
+            "changed": changed,
+        }),
+        ..Default::default()
+    })
+}
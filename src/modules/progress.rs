@@ -0,0 +1,269 @@
+use super::output;
+use anyhow::{anyhow, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+pub const BYTES_TEMPLATE: &str = "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})";
+pub const ITEMS_TEMPLATE: &str = "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({per_sec}, {eta})";
+pub const SIMPLE_TEMPLATE: &str =
+    "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}";
+
+static MULTI_PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+static EVENT_STREAM: OnceLock<Mutex<File>> = OnceLock::new();
+
+tokio::task_local! {
+    // Scoped to one library call, so concurrent calls each hear only their own events
+    static LISTENER: Listener;
+}
+
+pub type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+#[derive(Serialize, Debug)]
+pub struct Event<'a> {
+    pub phase: &'a str,
+    pub done: u64,
+    pub total: u64,
+}
+
+// Has to run before the first bar is created, bars are hidden once events are streamed
+pub fn init_event_stream(path: &str) -> Result<()> {
+    let file = match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(value) => value,
+        Err(error) => {
+            return Err(anyhow!(
+                "Failed to open progress file {path}, reason: {error}"
+            ))
+        }
+    };
+    let _ = EVENT_STREAM.set(Mutex::new(file));
+    Ok(())
+}
+
+// Library users get the same events the progress file does while the future they passed the
+// listener along with runs
+pub async fn with_listener<F: Future>(listener: Listener, future: F) -> F::Output {
+    LISTENER.scope(listener, future).await
+}
+
+fn current_listener() -> Option<Listener> {
+    LISTENER.try_with(Arc::clone).ok()
+}
+
+pub fn emit(phase: &str, done: u64, total: u64) {
+    send(current_listener().as_ref(), phase, done, total);
+}
+
+fn send(listener: Option<&Listener>, phase: &str, done: u64, total: u64) {
+    let event = Event { phase, done, total };
+    if let Some(listener) = listener {
+        listener(&event);
+    }
+
+    let stream = match EVENT_STREAM.get() {
+        Some(value) => value,
+        None => return,
+    };
+    let mut line = match serde_json::to_string(&event) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    line.push('\n');
+    // A frontend that went away shouldn't take the installation down with it
+    if let Ok(mut file) = stream.lock() {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+// Every bar goes through one MultiProgress so concurrent operations don't draw over each other,
+// it's also the only place deciding whether bars are drawn at all
+pub fn multi_progress() -> &'static MultiProgress {
+    MULTI_PROGRESS.get_or_init(|| {
+        if is_interactive() && EVENT_STREAM.get().is_none() {
+            MultiProgress::new()
+        } else {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        }
+    })
+}
+
+pub fn is_interactive() -> bool {
+    std::io::stderr().is_terminal()
+}
+
+// Long running operations report through this instead of drawing bars themselves, so they work
+// the same from the CLI, a library user or a test
+pub trait ProgressSink: Send + Sync {
+    fn start(&self, total: u64);
+    fn advance(&self, delta: u64);
+    fn message(&self, message: &str);
+    fn finish(&self);
+}
+
+pub type Sink = Arc<dyn ProgressSink>;
+
+#[cfg(test)]
+pub struct NoProgress;
+
+#[cfg(test)]
+impl ProgressSink for NoProgress {
+    fn start(&self, _total: u64) {}
+    fn advance(&self, _delta: u64) {}
+    fn message(&self, _message: &str) {}
+    fn finish(&self) {}
+}
+
+// Wraps a progress bar so every update is also reported to the event stream, updates are only
+// emitted when the percentage changes to keep the stream from flooding on large downloads
+pub struct Bar {
+    bar: ProgressBar,
+    phase: &'static str,
+    last_percent: AtomicU64,
+    // Taken when the bar is created, updates often come from blocking threads outside the call
+    listener: Option<Listener>,
+}
+
+impl Bar {
+    fn report(&self) {
+        let position = self.bar.position();
+        let total = self.bar.length().unwrap_or_default();
+        let percent = position * 100 / total.max(1);
+        if self.last_percent.swap(percent, Ordering::Relaxed) != percent {
+            send(self.listener.as_ref(), self.phase, position, total);
+        }
+    }
+}
+
+impl ProgressSink for Bar {
+    fn start(&self, total: u64) {
+        self.bar.set_length(total);
+        self.bar.set_position(0);
+        self.report();
+    }
+
+    fn advance(&self, delta: u64) {
+        self.bar.inc(delta);
+        self.report();
+    }
+
+    fn message(&self, message: &str) {
+        self.bar.set_message(message.to_owned());
+    }
+
+    fn finish(&self) {
+        self.bar.finish();
+        let total = self.bar.length().unwrap_or_default();
+        send(self.listener.as_ref(), self.phase, total, total);
+    }
+}
+
+// The only place deciding how progress is shown, multi_progress hides the bars when stderr
+// isn't a terminal or events are streamed while the events themselves always go out
+pub fn new_sink(phase: &'static str, template: &str) -> Sink {
+    let pb = add_bar(ProgressBar::new(0));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(template)
+            .unwrap()
+            .progress_chars("█  "),
+    );
+    Arc::new(Bar {
+        bar: pb,
+        phase,
+        last_percent: AtomicU64::new(u64::MAX),
+        listener: current_listener(),
+    })
+}
+
+// Bars are for the terminal bob owns, library calls draw nothing and leave it to their listener
+fn add_bar(bar: ProgressBar) -> ProgressBar {
+    if output::is_silent() {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+        return bar;
+    }
+    multi_progress().add(bar)
+}
+
+// Shown while waiting on requests that don't report any progress, cleared once the future resolves
+pub async fn with_spinner<F: Future>(message: &'static str, future: F) -> F::Output {
+    let spinner = add_bar(ProgressBar::new_spinner());
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let output = future.await;
+    spinner.finish_and_clear();
+    output
+}
+
+// Log lines are written with the bars suspended, otherwise they tear whatever is being drawn
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // With --json stdout is reserved for the result
+        if output::is_json() {
+            multi_progress().suspend(|| std::io::stderr().write_all(buf))?;
+        } else {
+            multi_progress().suspend(|| std::io::stdout().write_all(buf))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if output::is_json() {
+            std::io::stderr().flush()
+        } else {
+            std::io::stdout().flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn listeners_only_hear_events_of_their_own_call() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let listener: Listener = Arc::new(move |event: &Event| {
+            seen.lock()
+                .unwrap()
+                .push((event.phase.to_owned(), event.done, event.total));
+        });
+
+        with_listener(listener, async {
+            emit("resolve", 1, 1);
+            let sink = new_sink("extract", SIMPLE_TEMPLATE);
+            tokio::task::spawn_blocking(move || {
+                sink.start(2);
+                sink.advance(2);
+                sink.finish();
+            })
+            .await
+            .unwrap();
+        })
+        .await;
+        emit("finalize", 1, 1);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (String::from("resolve"), 1, 1),
+                (String::from("extract"), 0, 2),
+                (String::from("extract"), 2, 2),
+                (String::from("extract"), 2, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use super::output;
+use crate::enums::BobError;
+use anyhow::Result;
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::OnceLock;
+use yansi::Paint;
+
+const NONINTERACTIVE_ENV_VAR: &str = "BOB_NONINTERACTIVE";
+
+static ASSUME_YES: OnceLock<()> = OnceLock::new();
+
+pub struct Prompt {
+    pub name: &'static str,
+    pub question: &'static str,
+    // What pressing enter answers, --yes always answers yes whatever this is
+    pub default: bool,
+}
+
+pub const ERASE: Prompt = Prompt {
+    name: "erase",
+    question: "Erase every installed version and everything else bob set up?",
+    default: false,
+};
+
+pub const RESET: Prompt = Prompt {
+    name: "reset",
+    question: "Remove every version, bob's state, caches, logs and the PATH and launcher entries it added?",
+    default: false,
+};
+
+pub const REPAIR: Prompt = Prompt {
+    name: "repair",
+    question: "Reinstall the versions that failed verification?",
+    default: true,
+};
+
+pub const UNINSTALL: Prompt = Prompt {
+    name: "uninstall",
+    question: "Uninstall the picked versions?",
+    default: false,
+};
+
+pub const ARCH_MIGRATION: Prompt = Prompt {
+    name: "arch-migration",
+    question: "Move the versions installed before per_arch_dirs into this architecture's folder?",
+    default: true,
+};
+
+// Every question bob can ask, the README lists them by name so scripts know what --yes answers
+#[cfg(test)]
+pub const PROMPTS: [&Prompt; 5] = [&ERASE, &RESET, &REPAIR, &UNINSTALL, &ARCH_MIGRATION];
+
+pub fn assume_yes() {
+    let _ = ASSUME_YES.set(());
+}
+
+pub fn is_assumed_yes() -> bool {
+    if ASSUME_YES.get().is_some() {
+        return true;
+    }
+    std::env::var(NONINTERACTIVE_ENV_VAR)
+        .is_ok_and(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+}
+
+// Without a terminal there's nobody to answer, failing right away beats a CI job waiting on
+// stdin until it times out
+pub fn confirm(prompt: &Prompt) -> Result<bool> {
+    if is_assumed_yes() {
+        return Ok(true);
+    }
+    if !is_interactive() {
+        return Err(BobError::Usage(format!(
+            "Can't ask \"{}\" ({}) without a terminal, pass --yes or set {NONINTERACTIVE_ENV_VAR}=1 to answer yes",
+            prompt.question, prompt.name
+        ))
+        .into());
+    }
+
+    let choices = if prompt.default { "[Y/n]" } else { "[y/N]" };
+    let mut stderr = std::io::stderr();
+    write!(stderr, "{} {choices} ", prompt.question)?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => prompt.default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+// Whether confirm can get an answer at all, for questions that are better put off than failed on
+pub fn can_confirm() -> bool {
+    is_assumed_yes() || is_interactive()
+}
+
+pub fn is_interactive() -> bool {
+    !output::is_json() && std::io::stdin().is_terminal()
+}
+
+// --yes can't answer this one, neither answer is safe to assume for the user
+pub fn ask_good_or_bad(question: &str) -> Result<bool> {
+    if !is_interactive() {
+        return Err(BobError::Usage(String::from(
+            "Can't ask whether a commit is good without a terminal, pass --cmd to test each commit with a command instead",
+        ))
+        .into());
+    }
+
+    let mut stderr = std::io::stderr();
+    loop {
+        write!(stderr, "{question} [good/bad] ")?;
+        stderr.flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Err(BobError::Usage(String::from("No answer was given")).into());
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "g" | "good" => return Ok(true),
+            "b" | "bad" => return Ok(false),
+            _ => (),
+        }
+    }
+}
+
+pub struct Choice {
+    pub label: String,
+    // Listed but can't be toggled, the reason is shown next to it
+    pub locked: Option<String>,
+}
+
+// A checkbox list read line by line: numbers and ranges like 2-4 toggle entries, "a" toggles
+// every entry that can be picked and an empty line is done. Like ask_good_or_bad --yes can't
+// answer it, there's no choice to assume
+pub fn pick_many(
+    title: &str,
+    choices: &[Choice],
+    summarize: impl Fn(&[usize]) -> String,
+) -> Result<Vec<usize>> {
+    if !is_interactive() {
+        return Err(BobError::Usage(format!("Can't show \"{title}\" without a terminal")).into());
+    }
+
+    let mut picked = vec![false; choices.len()];
+    let mut stderr = std::io::stderr();
+    loop {
+        writeln!(stderr, "{title}")?;
+        for (index, choice) in choices.iter().enumerate() {
+            match &choice.locked {
+                Some(reason) => writeln!(
+                    stderr,
+                    "{:>3}.  -  {} {}",
+                    index + 1,
+                    choice.label,
+                    Paint::new(format!("({reason})")).dimmed()
+                )?,
+                None => writeln!(
+                    stderr,
+                    "{:>3}. [{}] {}",
+                    index + 1,
+                    if picked[index] { "x" } else { " " },
+                    choice.label
+                )?,
+            }
+        }
+        let selected = (0..choices.len())
+            .filter(|index| picked[*index])
+            .collect::<Vec<_>>();
+        writeln!(stderr, "{}", summarize(&selected))?;
+        write!(
+            stderr,
+            "Toggle by number (e.g. 1 3 or 2-4), a for all, enter when done: "
+        )?;
+        stderr.flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Err(BobError::Usage(String::from("No answer was given")).into());
+        }
+        let answer = answer.trim().to_lowercase();
+        if answer.is_empty() {
+            return Ok(selected);
+        }
+
+        let pickable = |index: &usize| *index < choices.len() && choices[*index].locked.is_none();
+        if answer == "a" {
+            let all = (0..choices.len())
+                .filter(pickable)
+                .all(|index| picked[index]);
+            for index in (0..choices.len()).filter(pickable) {
+                picked[index] = !all;
+            }
+            continue;
+        }
+        for part in answer.split(|c: char| c == ',' || c.is_whitespace()) {
+            let range = match part.split_once('-') {
+                Some((start, end)) => start.parse::<usize>().ok().zip(end.parse::<usize>().ok()),
+                None => part.parse::<usize>().ok().map(|number| (number, number)),
+            };
+            match range {
+                Some((start, end)) if start >= 1 && start <= end => {
+                    for index in (start - 1..end).filter(pickable) {
+                        picked[index] = !picked[index];
+                    }
+                    if let Some(Choice {
+                        locked: Some(reason),
+                        ..
+                    }) = choices.get(start - 1).filter(|_| start == end)
+                    {
+                        writeln!(stderr, "{start} can't be picked, {reason}")?;
+                    }
+                }
+                _ if part.is_empty() => (),
+                _ => writeln!(stderr, "Skipping {part}, it isn't a number or a range")?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn prompt_names_are_unique() {
+        let names = PROMPTS
+            .iter()
+            .map(|prompt| prompt.name)
+            .collect::<HashSet<_>>();
+        assert_eq!(names.len(), PROMPTS.len());
+    }
+
+    // stdin is never read, confirm would block or fail on it otherwise. Both answers share one
+    // test since --yes can't be taken back once given
+    #[test]
+    fn yes_answers_without_reading_stdin() {
+        std::env::set_var(NONINTERACTIVE_ENV_VAR, "1");
+        assert!(confirm(&ERASE).unwrap());
+        assert!(can_confirm());
+        std::env::remove_var(NONINTERACTIVE_ENV_VAR);
+
+        assume_yes();
+        for prompt in PROMPTS {
+            assert!(confirm(prompt).unwrap(), "{}", prompt.name);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use super::{install_handler, state, use_handler, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use tracing::info;
+
+pub async fn start(client: &Client, config: Config) -> Result<CommandOutput> {
+    let version = reinstall_current(client, &config).await?;
+    let version_dir = utils::get_downloads_folder(&config)
+        .await?
+        .join(utils::get_version_dir_name(&version));
+    info!(
+        "Reinstalled {} into {}, it's still the used version",
+        version.tag_name,
+        version_dir.display()
+    );
+
+    Ok(CommandOutput {
+        action: String::from("reinstall"),
+        versions: vec![version.tag_name],
+        paths: vec![version_dir],
+        ..Default::default()
+    })
+}
+
+// The used version comes from the state rather than from running nvim, its files may be what's
+// broken. Nightly comes back as the latest nightly since older ones can't be downloaded anymore
+pub async fn reinstall_current(client: &Client, config: &Config) -> Result<InputVersion> {
+    let used =
+        match state::load(config).await?.used {
+            Some(value) => value,
+            None => return Err(BobError::Usage(String::from(
+                "No version is used, there's nothing to reinstall. Run `bob use <version>` first",
+            ))
+            .into()),
+        };
+    let version = match Version::parse(&used) {
+        Some(value) => InputVersion {
+            tag_name: used,
+            version: value,
+        },
+        None => return Err(anyhow!("The used version {used} isn't one bob can install")),
+    };
+    if let Version::Nightly = version.version {
+        info!("Reinstalling nightly replaces it with the latest nightly");
+    }
+
+    install_handler::reinstall(&version, client, config).await?;
+    use_handler::relink(&version, config).await?;
+    Ok(version)
+}
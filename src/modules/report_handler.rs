@@ -0,0 +1,159 @@
+use super::{config_handler, output, state, utils};
+use crate::models::{CommandOutput, Config};
+use anyhow::Result;
+use reqwest::Url;
+use serde_json::json;
+use std::fmt::Write;
+use tokio::fs;
+use tokio::process::Command;
+
+const NEW_ISSUE_URL: &str = "https://github.com/MordechaiHadad/bob/issues/new";
+const LOG_TAIL_LINES: usize = 50;
+// GitHub turns away longer URLs, past this the report is pasted by hand instead
+const MAX_ISSUE_URL_LENGTH: usize = 8000;
+
+// Values of these are shown as they are, secrets only show whether they're set
+const ENV_VARS: [&str; 9] = [
+    "BOB_DATA_DIR",
+    "BOB_NVIM_VERSION",
+    "BOB_PROFILE",
+    "BOB_NONINTERACTIVE",
+    "BOB_OFFLINE",
+    "XDG_DATA_HOME",
+    "XDG_CONFIG_HOME",
+    "SHELL",
+    "NO_PROXY",
+];
+const SECRET_ENV_VARS: [&str; 4] = ["GITHUB_TOKEN", "HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"];
+
+// Everything is gathered locally and only printed, nothing is sent anywhere. --open hands the
+// report to the browser, the issue is only filed once its author submits it
+pub async fn start(config: Config, log_file: Option<String>, open: bool) -> Result<CommandOutput> {
+    let report = build_report(&config, log_file.as_deref()).await?;
+    output::print_human(&report);
+
+    if open {
+        let mut url = Url::parse(NEW_ISSUE_URL)?;
+        url.query_pairs_mut().append_pair("body", &report);
+        if url.as_str().len() > MAX_ISSUE_URL_LENGTH {
+            url = Url::parse(NEW_ISSUE_URL)?;
+            url.query_pairs_mut()
+                .append_pair("body", "<!-- Paste the output of `bob report` here -->");
+            output::print_human("The report is too long to prefill, paste it into the issue");
+        }
+        utils::open_in_default_app(url.as_str())?;
+    }
+
+    Ok(CommandOutput {
+        action: String::from("report"),
+        details: json!({ "report": report }),
+        ..Default::default()
+    })
+}
+
+async fn build_report(config: &Config, log_file: Option<&str>) -> Result<String> {
+    let mut report = String::new();
+
+    writeln!(report, "### Environment\n")?;
+    let build = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    writeln!(
+        report,
+        "- bob: {} ({build} build)",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(
+        report,
+        "- OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    let used = match state::find_version_override()? {
+        Some((version, source)) => format!("{version} (from {source})"),
+        None => match state::load(config).await?.used {
+            Some(version) => format!("{version} (from bob use)"),
+            None => String::from("none"),
+        },
+    };
+    writeln!(report, "- Used version: {used}")?;
+    writeln!(report, "- nvim: {}", get_nvim_version(config).await)?;
+
+    writeln!(report, "\n### Installed versions\n")?;
+    let versions = utils::get_installed_versions(config).await?;
+    if versions.is_empty() {
+        writeln!(report, "None")?;
+    } else {
+        writeln!(report, "| Version | Status |\n|---------|--------|")?;
+        for version in versions {
+            let status = if version.used { "Used" } else { "Installed" };
+            writeln!(report, "| {} | {status} |", version.name)?;
+        }
+    }
+
+    writeln!(report, "\n### Config\n\n```")?;
+    for setting in config_handler::resolved_config(config).await? {
+        if !setting.value.is_null() {
+            writeln!(
+                report,
+                "{} = {} ({})",
+                setting.name, setting.value, setting.source
+            )?;
+        }
+    }
+    writeln!(report, "```")?;
+
+    writeln!(report, "\n### Environment variables\n\n```")?;
+    for name in ENV_VARS {
+        if let Ok(value) = std::env::var(name) {
+            writeln!(report, "{name}={value}")?;
+        }
+    }
+    for name in SECRET_ENV_VARS {
+        if std::env::var_os(name).is_some() {
+            writeln!(report, "{name}=<set>")?;
+        }
+    }
+    writeln!(report, "```")?;
+
+    writeln!(report, "\n### Log\n")?;
+    match log_file {
+        Some(path) => match fs::read_to_string(path).await {
+            Ok(content) if content.trim().is_empty() => writeln!(report, "{path} is empty")?,
+            Ok(content) => {
+                let lines: Vec<&str> = content.lines().collect();
+                let tail = &lines[lines.len().saturating_sub(LOG_TAIL_LINES)..];
+                writeln!(
+                    report,
+                    "<details><summary>Last {} lines of {path}</summary>\n\n```\n{}\n```\n</details>",
+                    tail.len(),
+                    tail.join("\n")
+                )?;
+            }
+            Err(error) => writeln!(report, "Couldn't read {path}: {error}")?,
+        },
+        None => writeln!(
+            report,
+            "No log file, rerun the failing command with `--log-file bob.log` and run `bob report --log-file bob.log`"
+        )?,
+    }
+
+    Ok(report)
+}
+
+async fn get_nvim_version(config: &Config) -> String {
+    let binary = match utils::get_used_binary_path(config) {
+        Ok(value) => value,
+        Err(error) => return error.to_string(),
+    };
+    match Command::new(&binary).arg("--version").output().await {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("no version output")
+            .to_owned(),
+        Err(error) => format!("{} doesn't run: {error}", binary.display()),
+    }
+}
@@ -0,0 +1,481 @@
+use super::{desktop, env_handler, net, output, prompt, state, utils};
+use crate::models::{CommandOutput, Config};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::info;
+use yansi::Paint;
+
+enum Removal {
+    Directory(PathBuf),
+    EmptyDirectory(PathBuf),
+    File(PathBuf),
+    DesktopEntry(PathBuf),
+    PathSetup(PathBuf),
+    #[cfg(windows)]
+    UserPath(PathBuf),
+}
+
+impl Removal {
+    fn describe(&self) -> String {
+        match self {
+            Removal::Directory(path) => format!("{} and everything in it", path.display()),
+            Removal::EmptyDirectory(path) | Removal::File(path) => path.display().to_string(),
+            Removal::DesktopEntry(path) => format!("the launcher entry {}", path.display()),
+            Removal::PathSetup(path) => format!("the PATH line bob added to {}", path.display()),
+            #[cfg(windows)]
+            Removal::UserPath(path) => format!("{} from your user PATH", path.display()),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Removal::Directory(path)
+            | Removal::EmptyDirectory(path)
+            | Removal::File(path)
+            | Removal::DesktopEntry(path)
+            | Removal::PathSetup(path) => path,
+            #[cfg(windows)]
+            Removal::UserPath(path) => path,
+        }
+    }
+}
+
+// What erase removes plus state, logs and PATH lines, so the next run starts the way a fresh
+// install would. Anything bob only reads or that the user pointed it at is left and named
+pub async fn start(
+    config: Config,
+    log_file: Option<String>,
+    keep_config: bool,
+    dry_run: bool,
+) -> Result<CommandOutput> {
+    let (removals, mut kept) = get_plan(&config, log_file, keep_config).await?;
+
+    if removals.is_empty() {
+        info!("There's nothing to reset");
+    } else if dry_run {
+        output::print_human("Would remove:");
+        for removal in &removals {
+            output::print_human(format!("  {}", removal.describe()));
+        }
+    } else {
+        if !prompt::confirm(&prompt::RESET)? {
+            return Err(anyhow!("Reset cancelled, nothing was removed"));
+        }
+        for removal in &removals {
+            if let Some(reason) = remove(&config, removal).await? {
+                kept.push((removal.path().to_path_buf(), reason));
+            }
+            info!("Removed {}", removal.describe());
+        }
+    }
+
+    if !kept.is_empty() {
+        output::print_human(if dry_run {
+            "Would leave behind:"
+        } else {
+            "Left behind:"
+        });
+        for (path, reason) in &kept {
+            output::print_human(format!(
+                "  {} {}",
+                path.display(),
+                Paint::new(format!("({reason})")).dimmed()
+            ));
+        }
+    }
+
+    Ok(CommandOutput {
+        action: String::from("reset"),
+        paths: removals
+            .iter()
+            .map(|removal| removal.path().to_path_buf())
+            .collect(),
+        details: json!({
+            "dry_run": dry_run,
+            "removed": removals
+                .iter()
+                .map(Removal::describe)
+                .collect::<Vec<_>>(),
+            "kept": kept
+                .iter()
+                .map(|(path, reason)| json!({ "path": path, "reason": reason }))
+                .collect::<Vec<_>>(),
+        }),
+        ..Default::default()
+    })
+}
+
+async fn get_plan(
+    config: &Config,
+    log_file: Option<String>,
+    keep_config: bool,
+) -> Result<(Vec<Removal>, Vec<(PathBuf, String)>)> {
+    let mut removals = Vec::new();
+    let mut kept = Vec::new();
+
+    let installation_dir = utils::get_installation_folder(config)?;
+    if fs::metadata(&installation_dir).await.is_ok() {
+        removals.push(Removal::Directory(installation_dir.clone()));
+    }
+
+    let downloads_dir = utils::get_downloads_location(config)?;
+    let has_downloads_dir = fs::metadata(&downloads_dir).await.is_ok();
+    // State only lives outside the default downloads folder when it's configured to
+    let state_files = if config.state_dir.is_some() || config.downloads_dir.is_some() {
+        state::get_state_files(config).await?
+    } else {
+        Vec::new()
+    };
+    let mut is_bob_only = false;
+    if has_downloads_dir {
+        kept.extend(find_linked_sources(&downloads_dir).await);
+        if config.downloads_dir.is_some() {
+            is_bob_only =
+                plan_downloads_dir(&downloads_dir, &state_files, &mut removals, &mut kept).await?;
+        } else {
+            removals.push(Removal::Directory(downloads_dir.clone()));
+        }
+    }
+    removals.extend(state_files.into_iter().map(Removal::File));
+    // After the state files, which may live in it
+    if is_bob_only {
+        removals.push(Removal::EmptyDirectory(downloads_dir));
+    }
+
+    if let Some(desktop_file) = desktop::find_entry().await? {
+        removals.push(Removal::DesktopEntry(desktop_file));
+    }
+    let (added, manual) = env_handler::find_path_setup(config).await?;
+    removals.extend(added.into_iter().map(Removal::PathSetup));
+    for rc_file in manual {
+        kept.push((
+            rc_file,
+            String::from("mentions the bin directory in lines bob didn't write"),
+        ));
+    }
+    #[cfg(windows)]
+    if utils::is_in_user_path(&installation_dir.join("bin"))? {
+        removals.push(Removal::UserPath(installation_dir.join("bin")));
+    }
+
+    if let Some(config_file) = utils::get_config_file() {
+        if fs::metadata(&config_file).await.is_ok() {
+            if keep_config {
+                kept.push((config_file, String::from("kept with --keep-config")));
+            } else {
+                removals.push(Removal::File(config_file));
+            }
+        }
+    }
+    // Removed last so this run's own log keeps being written until the end
+    if let Some(log_file) = log_file.map(PathBuf::from) {
+        if fs::metadata(&log_file).await.is_ok() {
+            removals.push(Removal::File(log_file));
+        }
+    }
+
+    if utils::is_per_arch(config) {
+        kept.push((
+            utils::get_shared_downloads_location(config)?,
+            format!(
+                "shared with other architectures, only {} was reset",
+                utils::get_arch_dir_name()
+            ),
+        ));
+    }
+    for mirror in net::get_offline_dirs() {
+        kept.push((
+            mirror,
+            String::from("offline mirror, bob only reads from it"),
+        ));
+    }
+    for (path, setting) in [
+        (&config.staging_dir, "staging_dir"),
+        (&config.progress_file, "progress_file"),
+    ] {
+        if let Some(path) = path.as_ref().map(PathBuf::from) {
+            if fs::metadata(&path).await.is_ok() {
+                kept.push((path, format!("set as {setting} in the config")));
+            }
+        }
+    }
+    if let Ok(executable) = std::env::current_exe() {
+        kept.push((
+            executable,
+            String::from("bob itself, uninstall it the way it was installed"),
+        ));
+    }
+
+    dedupe(&mut removals, &mut kept);
+    Ok((removals, kept))
+}
+
+// A configured downloads_dir may hold the user's own files, only what bob put there is removed.
+// Returns whether that's everything, the folder itself only goes once nothing else is left in it
+async fn plan_downloads_dir(
+    downloads_dir: &Path,
+    state_files: &[PathBuf],
+    removals: &mut Vec<Removal>,
+    kept: &mut Vec<(PathBuf, String)>,
+) -> Result<bool> {
+    let mut entries = fs::read_dir(downloads_dir).await?;
+    let mut owned = Vec::new();
+    let mut foreign = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if state_files.contains(&path) {
+            continue;
+        }
+        if !utils::is_bob_entry(&entry.file_name()) {
+            foreign.push(path);
+        } else if entry.file_type().await?.is_dir() {
+            owned.push(Removal::Directory(path));
+        } else {
+            owned.push(Removal::File(path));
+        }
+    }
+
+    owned.sort_by(|first, second| first.path().cmp(second.path()));
+    removals.extend(owned);
+    let is_bob_only = foreign.is_empty();
+    foreign.sort();
+    kept.extend(foreign.into_iter().map(|path| {
+        (
+            path,
+            String::from("in downloads_dir but not created by bob"),
+        )
+    }));
+    Ok(is_bob_only)
+}
+
+// Offline mode lists the downloads folder as a mirror too, anything being removed isn't also
+// reported as left behind, and nothing is listed twice
+fn dedupe(removals: &mut Vec<Removal>, kept: &mut Vec<(PathBuf, String)>) {
+    let mut seen = HashSet::new();
+    removals.retain(|removal| seen.insert(removal.path().to_path_buf()));
+    kept.retain(|(path, _)| seen.insert(path.clone()));
+}
+
+// Returns why something stayed after all, the PATH line may share its file with lines the user
+// wrote
+async fn remove(config: &Config, removal: &Removal) -> Result<Option<String>> {
+    match removal {
+        // A state_dir or log file inside a removed folder is already gone
+        Removal::Directory(path) | Removal::EmptyDirectory(path) | Removal::File(path)
+            if fs::symlink_metadata(path).await.is_err() => {}
+        Removal::Directory(path) => fs::remove_dir_all(path).await?,
+        // Something was added since the plan was made
+        Removal::EmptyDirectory(path) => {
+            if fs::remove_dir(path).await.is_err() {
+                return Ok(Some(String::from(
+                    "in downloads_dir but holds files bob didn't create",
+                )));
+            }
+        }
+        Removal::File(path) => {
+            fs::remove_file(path).await?;
+            // The config directory only ever holds the config
+            if utils::get_config_file().is_some_and(|config_file| config_file == *path) {
+                if let Some(parent) = path.parent() {
+                    let _ = fs::remove_dir(parent).await;
+                }
+            }
+        }
+        Removal::DesktopEntry(_) => {
+            desktop::remove_entry().await?;
+        }
+        Removal::PathSetup(path) => {
+            if env_handler::remove_path_setup(config, path).await? {
+                return Ok(Some(String::from(
+                    "still mentions the bin directory in lines bob didn't write",
+                )));
+            }
+        }
+        #[cfg(windows)]
+        Removal::UserPath(path) => {
+            utils::remove_from_user_path(path)?;
+        }
+    }
+    Ok(None)
+}
+
+// Versions imported with migrate --link are symlinks into directories bob never owned, only the
+// links go away with the downloads folder
+async fn find_linked_sources(downloads_dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut sources = Vec::new();
+    let mut entries = match fs::read_dir(downloads_dir).await {
+        Ok(value) => value,
+        Err(_) => return sources,
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let platform_dir = entry.path().join(utils::get_platform_name());
+        let is_link = fs::symlink_metadata(&platform_dir)
+            .await
+            .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if let (true, Ok(source)) = (is_link, fs::read_link(&platform_dir).await) {
+            sources.push((
+                source,
+                format!(
+                    "linked as {} by bob migrate --link",
+                    entry.file_name().to_string_lossy()
+                ),
+            ));
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+
+    #[tokio::test]
+    async fn configured_downloads_dirs_keep_foreign_files() {
+        let dir = TestDir::new("reset-shared-downloads");
+        let downloads_dir = dir.path().join("shared");
+        for version in ["v0.9.5", "nightly", "v0.9.4.previous", "neovim-git"] {
+            std::fs::create_dir_all(downloads_dir.join(version).join("bin")).unwrap();
+        }
+        for file in [
+            "v0.9.6.download",
+            "state.json",
+            "my-notes.txt",
+            "photos.tar.gz",
+        ] {
+            std::fs::write(downloads_dir.join(file), "").unwrap();
+        }
+        std::fs::create_dir(downloads_dir.join("projects")).unwrap();
+        let state_files = vec![downloads_dir.join("state.json")];
+
+        let mut removals = Vec::new();
+        let mut kept = Vec::new();
+        let is_bob_only =
+            plan_downloads_dir(&downloads_dir, &state_files, &mut removals, &mut kept)
+                .await
+                .unwrap();
+        assert!(!is_bob_only);
+        let removed = removals
+            .iter()
+            .map(|removal| removal.path().file_name().unwrap().to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            removed,
+            [
+                "neovim-git",
+                "nightly",
+                "v0.9.4.previous",
+                "v0.9.5",
+                "v0.9.6.download"
+            ]
+        );
+        let kept = kept
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(kept, ["my-notes.txt", "photos.tar.gz", "projects"]);
+
+        let config = Config {
+            downloads_dir: Some(downloads_dir.to_string_lossy().into_owned()),
+            ..dir.config()
+        };
+        for removal in &removals {
+            assert!(remove(&config, removal).await.unwrap().is_none());
+        }
+        let mut left = std::fs::read_dir(&downloads_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(
+            left,
+            ["my-notes.txt", "photos.tar.gz", "projects", "state.json"]
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_downloads_dirs_go_once_empty() {
+        let dir = TestDir::new("reset-own-downloads");
+        let downloads_dir = dir.path().join("versions");
+        std::fs::create_dir_all(downloads_dir.join("v0.9.5")).unwrap();
+        std::fs::write(downloads_dir.join("state.json"), "{}").unwrap();
+        let state_files = vec![downloads_dir.join("state.json")];
+
+        let mut removals = Vec::new();
+        let mut kept = Vec::new();
+        let is_bob_only =
+            plan_downloads_dir(&downloads_dir, &state_files, &mut removals, &mut kept)
+                .await
+                .unwrap();
+        assert!(is_bob_only && kept.is_empty());
+
+        // The same order get_plan uses
+        removals.extend(state_files.into_iter().map(Removal::File));
+        removals.push(Removal::EmptyDirectory(downloads_dir.clone()));
+        let config = dir.config();
+        for removal in &removals {
+            assert!(remove(&config, removal).await.unwrap().is_none());
+        }
+        assert!(!downloads_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn folders_filled_after_planning_are_left_behind() {
+        let dir = TestDir::new("reset-filled-downloads");
+        let downloads_dir = dir.path().join("versions");
+        std::fs::create_dir_all(&downloads_dir).unwrap();
+        std::fs::write(downloads_dir.join("new-file"), "").unwrap();
+
+        let reason = remove(
+            &dir.config(),
+            &Removal::EmptyDirectory(downloads_dir.clone()),
+        )
+        .await
+        .unwrap();
+        assert!(reason.is_some());
+        assert!(downloads_dir.join("new-file").exists());
+    }
+
+    #[test]
+    fn removals_and_kept_paths_are_listed_once() {
+        let downloads_dir = PathBuf::from("/data/bob");
+        let mut removals = vec![
+            Removal::Directory(downloads_dir.clone()),
+            Removal::File(downloads_dir.join("state.json")),
+            Removal::File(downloads_dir.join("state.json")),
+        ];
+        let mut kept = vec![
+            (
+                downloads_dir.clone(),
+                String::from("offline mirror, bob only reads from it"),
+            ),
+            (
+                PathBuf::from("/mirror"),
+                String::from("offline mirror, bob only reads from it"),
+            ),
+            (
+                PathBuf::from("/mirror"),
+                String::from("set as staging_dir in the config"),
+            ),
+        ];
+
+        dedupe(&mut removals, &mut kept);
+        assert_eq!(
+            removals
+                .iter()
+                .map(|removal| removal.path().to_path_buf())
+                .collect::<Vec<_>>(),
+            [downloads_dir.clone(), downloads_dir.join("state.json")]
+        );
+        assert_eq!(
+            kept,
+            [(
+                PathBuf::from("/mirror"),
+                String::from("offline mirror, bob only reads from it")
+            )]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use super::{net, progress};
+use crate::enums::BobError;
+use crate::models::{CommandOutput, ReleaseAsset, UpstreamVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, info};
+
+const CHECKSUM_FILES: [&str; 2] = ["checksums.txt", "SHA256SUMS"];
+
+pub async fn start(client: &Client, check: bool) -> Result<CommandOutput> {
+    let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))?;
+    let latest: UpstreamVersion = progress::with_spinner(
+        "Looking for bob updates…",
+        net::get_json_from(client, &net::bob_api_url("releases/latest")),
+    )
+    .await?;
+    let latest_version = match semver::Version::parse(latest.tag_name.trim_start_matches('v')) {
+        Ok(value) => value,
+        Err(_) => return Err(BobError::InvalidVersion(latest.tag_name).into()),
+    };
+
+    let mut result = CommandOutput {
+        action: String::from("self-update"),
+        versions: vec![latest.tag_name.clone()],
+        details: json!({
+            "current": current.to_string(),
+            "latest": latest_version.to_string(),
+            "updated": false,
+        }),
+        ..Default::default()
+    };
+    if latest_version <= current {
+        info!("bob {current} is up to date");
+        return Ok(result);
+    }
+    if check {
+        return Err(BobError::UpdatesAvailable(vec![format!("bob {}", latest.tag_name)]).into());
+    }
+
+    let executable = get_current_executable()?;
+    if let Some(manager) = detect_package_manager(&executable) {
+        return Err(anyhow!(
+            "bob was installed with {manager}, update it through {manager} so it keeps track of it"
+        ));
+    }
+
+    let asset_name = format!("{}.zip", get_platform_asset());
+    let asset = match latest.assets.iter().find(|asset| asset.name == asset_name) {
+        Some(value) => value,
+        None => {
+            return Err(anyhow!(
+                "Release {} has no {asset_name} for this platform",
+                latest.tag_name
+            ))
+        }
+    };
+    let expected = get_expected_checksum(client, &latest, asset).await?;
+
+    let download_progress = progress::new_sink("download", progress::BYTES_TEMPLATE);
+    let archive = net::download_asset(client, asset, download_progress).await?;
+    let actual = format!("{:x}", Sha256::digest(&archive));
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(anyhow!(
+            "Checksum of {asset_name} doesn't match, expected {expected} but got {actual}"
+        ));
+    }
+    debug!("Checksum of {asset_name} matches {expected}");
+
+    let binary = match tokio::task::spawn_blocking(move || extract_binary(archive)).await {
+        Ok(value) => value?,
+        Err(error) => return Err(anyhow!(error)),
+    };
+    replace_executable(&executable, &binary).await?;
+
+    info!("Updated bob from {current} to {latest_version}");
+    result.paths = vec![executable];
+    result.details["updated"] = json!(true);
+    Ok(result)
+}
+
+// Symlinks like the one cargo-binstall or a dotfiles repo leaves are followed, otherwise only the
+// link would get replaced
+fn get_current_executable() -> Result<PathBuf> {
+    let executable = std::env::current_exe()?;
+    match std::fs::canonicalize(&executable) {
+        Ok(value) => Ok(value),
+        Err(_) => Ok(executable),
+    }
+}
+
+// Replacing a file a package manager owns leaves it out of sync, the next upgrade through it
+// would either fail or silently downgrade bob again
+fn detect_package_manager(executable: &Path) -> Option<&'static str> {
+    let path = executable
+        .to_string_lossy()
+        .replace('\\', "/")
+        .to_lowercase();
+    let managers = [
+        ("/.cargo/bin/", "cargo"),
+        ("/nix/store/", "nix"),
+        ("/cellar/", "homebrew"),
+        ("/homebrew/", "homebrew"),
+        ("/linuxbrew/", "homebrew"),
+        ("/scoop/", "scoop"),
+        ("/chocolatey/", "chocolatey"),
+        ("/winget/", "winget"),
+        ("/usr/bin/", "your system package manager"),
+        ("/snap/", "snap"),
+    ];
+    managers
+        .iter()
+        .find(|(pattern, _)| path.contains(pattern))
+        .map(|(_, manager)| *manager)
+}
+
+fn get_platform_asset() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "bob-windows-x86_64"
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        "bob-macos-arm"
+    } else if cfg!(target_os = "macos") {
+        "bob-macos-x86_64"
+    } else {
+        "bob-linux-x86_64"
+    }
+}
+
+// Releases either ship a <asset>.sha256 next to each asset or one file listing all of them, an
+// asset without a published checksum is never installed
+async fn get_expected_checksum(
+    client: &Client,
+    release: &UpstreamVersion,
+    asset: &ReleaseAsset,
+) -> Result<String> {
+    let sidecar = format!("{}.sha256", asset.name);
+    let checksum_asset = match release
+        .assets
+        .iter()
+        .find(|candidate| candidate.name == sidecar)
+        .or_else(|| {
+            release
+                .assets
+                .iter()
+                .find(|candidate| CHECKSUM_FILES.contains(&candidate.name.as_str()))
+        }) {
+        Some(value) => value,
+        None => {
+            return Err(anyhow!(
+                "Release {} doesn't publish a checksum for {}, refusing to install it unverified",
+                release.tag_name,
+                asset.name
+            ))
+        }
+    };
+
+    let content = net::send(client.get(&checksum_asset.browser_download_url))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    // Lines look like `<hash>  <file name>`, a sidecar file may only have the hash
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let hash = match parts.next() {
+            Some(value) => value,
+            None => continue,
+        };
+        match parts.next() {
+            Some(name) if name.trim_start_matches('*') == asset.name => return Ok(hash.to_owned()),
+            None if checksum_asset.name == sidecar => return Ok(hash.to_owned()),
+            _ => (),
+        }
+    }
+    Err(anyhow!(
+        "{} has no checksum for {}",
+        checksum_asset.name,
+        asset.name
+    ))
+}
+
+// The archive holds the binary either at its root or inside a folder named after the asset
+fn extract_binary(archive: Vec<u8>) -> Result<Vec<u8>> {
+    let binary_name = if cfg!(target_os = "windows") {
+        "bob.exe"
+    } else {
+        "bob"
+    };
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive))?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let is_binary = file
+            .enclosed_name()
+            .and_then(|path| path.file_name().map(|name| name == binary_name))
+            .unwrap_or(false);
+        if is_binary && file.is_file() {
+            let mut binary = Vec::new();
+            file.read_to_end(&mut binary)?;
+            return Ok(binary);
+        }
+    }
+    Err(anyhow!(
+        "The downloaded archive doesn't contain {binary_name}"
+    ))
+}
+
+// The new binary is written next to the old one so the final rename never crosses filesystems.
+// Windows won't let a running exe be overwritten but does let it be renamed, so the old one is
+// moved aside first and removed the next time bob updates itself
+async fn replace_executable(executable: &Path, binary: &[u8]) -> Result<()> {
+    let new_executable = executable.with_extension("new");
+    let old_executable = executable.with_extension("old");
+    if fs::metadata(&old_executable).await.is_ok() {
+        let _ = fs::remove_file(&old_executable).await;
+    }
+
+    if let Err(error) = fs::write(&new_executable, binary).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            new_executable.display()
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new_executable, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    if cfg!(target_os = "windows") {
+        fs::rename(executable, &old_executable).await?;
+        if let Err(error) = fs::rename(&new_executable, executable).await {
+            let _ = fs::rename(&old_executable, executable).await;
+            let _ = fs::remove_file(&new_executable).await;
+            return Err(anyhow!(
+                "Failed to replace {}, reason: {error}",
+                executable.display()
+            ));
+        }
+    } else if let Err(error) = fs::rename(&new_executable, executable).await {
+        let _ = fs::remove_file(&new_executable).await;
+        return Err(anyhow!(
+            "Failed to replace {}, reason: {error}",
+            executable.display()
+        ));
+    }
+    debug!("Replaced {}", executable.display());
+    Ok(())
+}
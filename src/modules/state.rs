@@ -0,0 +1,412 @@
+use crate::models::{Config, State, VersionState};
+
+use super::{bisect_handler, utils};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::fs;
+use tracing::{debug, info};
+
+const STATE_SCHEMA_VERSION: u32 = 1;
+pub const STATE_FILE: &str = "state.json";
+const LEGACY_USED_FILE: &str = "used";
+const PROJECT_VERSION_FILE: &str = ".bob-version";
+pub const VERSION_ENV_VAR: &str = "BOB_NVIM_VERSION";
+
+static VERSION_OVERRIDE: OnceLock<Option<(String, String)>> = OnceLock::new();
+
+// state_dir lets users sharing one downloads dir keep their own used version
+pub async fn get_state_dir(config: &Config) -> Result<PathBuf> {
+    let state_dir = match &config.state_dir {
+        Some(value) if utils::is_per_arch(config) => {
+            PathBuf::from(value).join(utils::get_arch_dir_name())
+        }
+        Some(value) => PathBuf::from(value),
+        None => return utils::get_downloads_folder(config).await,
+    };
+
+    if let Err(error) = fs::create_dir_all(&state_dir).await {
+        return Err(anyhow!(
+            "Couldn't create state directory {}, reason: {error}",
+            state_dir.display()
+        ));
+    }
+    Ok(state_dir)
+}
+
+pub async fn get_state_file(config: &Config) -> Result<PathBuf> {
+    Ok(get_state_dir(config).await?.join(STATE_FILE))
+}
+
+// Only the files bob owns are removed, a configured state_dir may be shared with other tools
+pub async fn remove_state_files(config: &Config) -> Result<()> {
+    for path in get_state_files(config).await? {
+        fs::remove_file(path).await?;
+    }
+    Ok(())
+}
+
+pub async fn get_state_files(config: &Config) -> Result<Vec<PathBuf>> {
+    let state_dir = get_state_dir(config).await?;
+    let mut files = Vec::new();
+    for file in [STATE_FILE, LEGACY_USED_FILE, bisect_handler::BISECT_FILE] {
+        let path = state_dir.join(file);
+        if fs::metadata(&path).await.is_ok() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+pub async fn load(config: &Config) -> Result<State> {
+    let state_file = get_state_file(config).await?;
+
+    match fs::read_to_string(&state_file).await {
+        Ok(content) => {
+            let state: State = match serde_json::from_str(&content) {
+                Ok(value) => value,
+                Err(error) => return Err(anyhow!("{} is corrupted, try running bob use again or open an issue at https://github.com/MordechaiHadad/bob, reason: {error}", state_file.display())),
+            };
+            if state.schema_version > STATE_SCHEMA_VERSION {
+                return Err(anyhow!(
+                    "{} was written by a newer version of bob, please update bob",
+                    state_file.display()
+                ));
+            }
+            Ok(state)
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => migrate_legacy(config).await,
+        Err(error) => Err(anyhow!(
+            "Failed to read {}, reason: {error}",
+            state_file.display()
+        )),
+    }
+}
+
+pub async fn save(config: &Config, state: &State) -> Result<()> {
+    let state_dir = get_state_dir(config).await?;
+    let state_file = state_dir.join(STATE_FILE);
+    let temp_file = state_dir.join(format!("{STATE_FILE}.tmp"));
+
+    let content = serde_json::to_string_pretty(state)?;
+    fs::write(&temp_file, content).await?;
+    if let Err(error) = fs::rename(&temp_file, &state_file).await {
+        return Err(anyhow!(
+            "Failed to write {}, reason: {error}",
+            state_file.display()
+        ));
+    }
+    debug!("Saved state to {}", state_file.display());
+
+    // Kept around for scripts that still read the plain used file
+    let legacy_file = state_dir.join(LEGACY_USED_FILE);
+    match &state.used {
+        Some(used) => fs::write(legacy_file, used).await?,
+        None => {
+            if fs::metadata(&legacy_file).await.is_ok() {
+                fs::remove_file(legacy_file).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn set_used(config: &Config, version: &str) -> Result<()> {
+    let mut state = load(config).await?;
+
+    if state.used.as_deref() != Some(version) {
+        state.previous = state.used.take();
+    }
+    state.used = Some(version.to_string());
+    state
+        .versions
+        .entry(version.to_string())
+        .or_default()
+        .last_used = Some(Utc::now());
+
+    save(config, &state).await
+}
+
+// Which use_strategy actually put the installation folder in place, a symlink that couldn't be
+// created falls back to shims. None once nothing is there anymore
+pub async fn set_install_mode(config: &Config, mode: Option<&str>) -> Result<()> {
+    let mut state = load(config).await?;
+    if state.install_mode.as_deref() == mode {
+        return Ok(());
+    }
+    state.install_mode = mode.map(String::from);
+    save(config, &state).await
+}
+
+pub async fn get_install_mode(config: &Config) -> Result<Option<String>> {
+    Ok(load(config).await?.install_mode)
+}
+
+// Returns whether anything changed, pinning a pinned version is a no-op
+pub async fn set_pinned(config: &Config, version: &str, pinned: bool) -> Result<bool> {
+    let mut state = load(config).await?;
+    let existing = state
+        .versions
+        .iter_mut()
+        .find(|(tag, _)| utils::is_same_version(tag, version));
+    let entry = match existing {
+        Some((_, entry)) => entry,
+        None if !pinned => return Ok(false),
+        None => state.versions.entry(version.to_string()).or_default(),
+    };
+    if entry.pinned == pinned {
+        return Ok(false);
+    }
+    entry.pinned = pinned;
+
+    save(config, &state).await?;
+    Ok(true)
+}
+
+pub async fn get_pinned(config: &Config) -> Result<Vec<String>> {
+    Ok(load(config)
+        .await?
+        .versions
+        .into_iter()
+        .filter(|(_, entry)| entry.pinned)
+        .map(|(tag, _)| tag)
+        .collect())
+}
+
+pub async fn is_pinned(config: &Config, version: &str) -> Result<bool> {
+    Ok(get_pinned(config)
+        .await?
+        .iter()
+        .any(|tag| utils::is_same_version(tag, version)))
+}
+
+// Installed versions that were never switched to, pinned ones are kept on purpose so they're left
+// out. Versions used before bob kept state still count as used when they're the used or previous
+// one
+pub async fn unused_versions(config: &Config) -> Result<Vec<String>> {
+    let state = load(config).await?;
+    let was_used = |name: &str| {
+        state
+            .versions
+            .iter()
+            .any(|(tag, entry)| entry.last_used.is_some() && utils::is_same_version(tag, name))
+            || [&state.used, &state.previous]
+                .into_iter()
+                .flatten()
+                .any(|tag| utils::is_same_version(tag, name))
+    };
+    let is_pinned = |name: &str| {
+        state
+            .versions
+            .iter()
+            .any(|(tag, entry)| entry.pinned && utils::is_same_version(tag, name))
+    };
+
+    Ok(utils::get_installed_versions(config)
+        .await?
+        .into_iter()
+        .filter(|version| !version.used && !was_used(&version.name) && !is_pinned(&version.name))
+        .map(|version| version.name)
+        .collect())
+}
+
+// BOB_NVIM_VERSION wins over a .bob-version file in the current directory or any of its
+// ancestors, both win over the version stored in the state file
+pub fn get_version_override() -> Result<Option<String>> {
+    Ok(find_version_override()?.map(|(version, _)| version))
+}
+
+// Looks the override up from the directory bob was started in, later lookups reuse it so a
+// command that changes the working directory still sees the same override
+pub fn init_version_override() -> Result<()> {
+    let version = lookup_version_override()?;
+    let _ = VERSION_OVERRIDE.set(version);
+    Ok(())
+}
+
+// Same as get_version_override along with where the version came from
+pub fn find_version_override() -> Result<Option<(String, String)>> {
+    match VERSION_OVERRIDE.get() {
+        Some(value) => Ok(value.clone()),
+        None => lookup_version_override(),
+    }
+}
+
+fn lookup_version_override() -> Result<Option<(String, String)>> {
+    if let Ok(version) = std::env::var(VERSION_ENV_VAR) {
+        if !version.trim().is_empty() {
+            return Ok(Some((
+                utils::normalize_tag(&version),
+                format!("env {VERSION_ENV_VAR}"),
+            )));
+        }
+    }
+
+    let current_dir = std::env::current_dir()?;
+    for directory in current_dir.ancestors() {
+        let file = directory.join(PROJECT_VERSION_FILE);
+        if let Ok(version) = std::fs::read_to_string(&file) {
+            if !version.trim().is_empty() {
+                return Ok(Some((
+                    utils::normalize_tag(&version),
+                    file.display().to_string(),
+                )));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// The active version can go missing when its directory is deleted by hand, in which case it
+// shouldn't be treated as used anymore
+pub async fn get_missing_used(config: &Config) -> Result<Option<String>> {
+    match load(config).await?.used {
+        Some(used) if !utils::is_version_installed(&used, config).await? => Ok(Some(used)),
+        _ => Ok(None),
+    }
+}
+
+// Merges entries that only differ by tag spelling and reports entries pointing at versions
+// that aren't installed anymore, they're only removed when fix is set
+pub async fn tidy(config: &Config, fix: bool) -> Result<Vec<String>> {
+    let mut state = load(config).await?;
+    let mut changed = false;
+    let mut problems = Vec::new();
+
+    let mut versions: BTreeMap<String, VersionState> = BTreeMap::new();
+    for (tag, entry) in std::mem::take(&mut state.versions) {
+        let canonical = utils::normalize_tag(&tag);
+        changed |= canonical != tag;
+        match versions.entry(canonical) {
+            Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+            }
+            Entry::Occupied(mut occupied) => {
+                info!("Merged the state entry for {tag} into {}", occupied.key());
+                let existing = occupied.get_mut();
+                existing.pinned |= entry.pinned;
+                existing.last_used = existing.last_used.max(entry.last_used);
+                changed = true;
+            }
+        }
+    }
+    state.versions = versions;
+
+    for tag in [&mut state.used, &mut state.previous].into_iter().flatten() {
+        let canonical = utils::normalize_tag(tag);
+        if canonical != *tag {
+            *tag = canonical;
+            changed = true;
+        }
+    }
+
+    let mut stale = Vec::new();
+    for (tag, entry) in &state.versions {
+        if !utils::is_version_installed(tag, config).await? {
+            if entry.pinned {
+                problems.push(format!("{tag} is pinned but isn't installed"));
+            }
+            stale.push(tag.clone());
+        }
+    }
+    if let Some(used) = &state.used {
+        if !utils::is_version_installed(used, config).await? {
+            problems.push(format!("active version {used} is missing from disk"));
+            if fix {
+                info!("Cleared the active version {used}, it's missing from disk");
+                state.used = None;
+                changed = true;
+            }
+        }
+    }
+
+    if fix {
+        for tag in stale {
+            info!("Dropped the state entry for {tag}, it isn't installed anymore");
+            state.versions.remove(&tag);
+            changed = true;
+        }
+        if let Some(previous) = &state.previous {
+            if !utils::is_version_installed(previous, config).await? {
+                info!("Cleared the previous version {previous}, it isn't installed anymore");
+                state.previous = None;
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        save(config, &state).await?;
+    }
+    Ok(problems)
+}
+
+async fn migrate_legacy(config: &Config) -> Result<State> {
+    let legacy_file = get_state_dir(config).await?.join(LEGACY_USED_FILE);
+
+    let mut state = State {
+        schema_version: STATE_SCHEMA_VERSION,
+        ..Default::default()
+    };
+
+    match fs::read_to_string(&legacy_file).await {
+        Ok(used) => {
+            let used = used.trim().to_string();
+            if !used.is_empty() {
+                state.used = Some(used);
+            }
+            save(config, &state).await?;
+            info!("Migrated {} to {STATE_FILE}", legacy_file.display());
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+        Err(error) => return Err(anyhow!("{} is corrupted, try running bob use again or open an issue at https://github.com/MordechaiHadad/bob, reason: {error}", legacy_file.display())),
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDir;
+
+    #[tokio::test]
+    async fn tidy_drops_every_entry_that_isnt_installed() {
+        let dir = TestDir::new("state-tidy");
+        let config = dir.config();
+        let bin_dir = PathBuf::from(config.downloads_dir.as_ref().unwrap())
+            .join("v0.9.5")
+            .join(utils::get_platform_name())
+            .join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join(utils::get_binary_name()), "").unwrap();
+
+        let mut state = State {
+            used: Some(String::from("v0.9.5")),
+            previous: Some(String::from("v0.9.2")),
+            ..Default::default()
+        };
+        for (tag, pinned) in [("v0.9.5", false), ("v0.9.4", false), ("v0.9.2", true)] {
+            state.versions.insert(
+                tag.to_string(),
+                VersionState {
+                    pinned,
+                    ..Default::default()
+                },
+            );
+        }
+        save(&config, &state).await.unwrap();
+
+        let problems = tidy(&config, true).await.unwrap();
+        assert_eq!(problems, ["v0.9.2 is pinned but isn't installed"]);
+        let state = load(&config).await.unwrap();
+        assert_eq!(state.versions.keys().collect::<Vec<_>>(), ["v0.9.5"]);
+        assert_eq!(state.previous, None);
+        assert_eq!(state.used.as_deref(), Some("v0.9.5"));
+    }
+}
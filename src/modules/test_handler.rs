@@ -0,0 +1,123 @@
+use super::{output, use_handler, utils};
+use crate::enums::BobError;
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::{process::Command, time};
+use tracing::{debug, info, warn};
+
+// Errors while loading the config only end up in v:errmsg, nvim still exits with 0 on +qa
+const ERROR_CHECK: &str = "+if v:errmsg != '' | cquit | endif";
+
+// Runs the installed build of the version as is, with the user's config and without a UI.
+// Nothing about the used version changes unless then_use is set and the run passed
+pub async fn start(
+    client: &Client,
+    config: Config,
+    version: InputVersion,
+    cmd: Option<String>,
+    timeout: Duration,
+    then_use: bool,
+) -> Result<CommandOutput> {
+    let version_dir = match utils::find_version_dir(&version.tag_name, &config).await? {
+        Some(value) if utils::is_version_installed(&version.tag_name, &config).await? => value,
+        _ => return Err(BobError::NotInstalled(version.tag_name).into()),
+    };
+    let binary = version_dir
+        .join(utils::get_platform_name())
+        .join("bin")
+        .join(utils::get_binary_name());
+
+    let mut args = vec![String::from("--headless")];
+    if let Some(cmd) = &cmd {
+        args.push(format!("+{}", cmd.trim_start_matches('+')));
+    }
+    args.push(String::from(ERROR_CHECK));
+    args.push(String::from("+qa!"));
+    debug!("Running {} {}", binary.display(), args.join(" "));
+
+    let started = Instant::now();
+    let child = match Command::new(&binary)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(value) => value,
+        Err(error) => {
+            return Err(anyhow!(
+                "Failed to run {}, reason: {error}",
+                binary.display()
+            ))
+        }
+    };
+    let (exit_code, run_output, timed_out) =
+        match time::timeout(timeout, child.wait_with_output()).await {
+            Ok(Ok(value)) => {
+                let mut run_output = String::from_utf8_lossy(&value.stdout).into_owned();
+                run_output.push_str(&String::from_utf8_lossy(&value.stderr));
+                (value.status.code(), run_output, false)
+            }
+            Ok(Err(error)) => {
+                return Err(anyhow!(
+                    "Failed to run {}, reason: {error}",
+                    binary.display()
+                ))
+            }
+            Err(_) => (None, String::new(), true),
+        };
+    let elapsed = started.elapsed();
+    let passed = exit_code == Some(0);
+
+    let run_output = run_output.trim().to_owned();
+    if !run_output.is_empty() {
+        output::print_human(&run_output);
+    }
+    if passed {
+        info!(
+            "{} loaded your config without errors in {:.1}s",
+            version.tag_name,
+            elapsed.as_secs_f64()
+        );
+    } else if timed_out {
+        warn!(
+            "{} didn't exit within {}s, it was stopped",
+            version.tag_name,
+            timeout.as_secs()
+        );
+    } else {
+        warn!(
+            "{} failed with exit code {}",
+            version.tag_name,
+            exit_code.map_or(String::from("unknown"), |code| code.to_string())
+        );
+    }
+
+    let tag_name = version.tag_name.clone();
+    let used = passed && then_use;
+    if used {
+        use_handler::start_installed(version, client, config).await?;
+    } else if then_use {
+        info!("Not switching to {tag_name} since the test failed");
+    }
+
+    Ok(CommandOutput {
+        action: String::from("test"),
+        versions: vec![tag_name],
+        paths: vec![binary],
+        details: json!({
+            "passed": passed,
+            "exit_code": exit_code,
+            "timed_out": timed_out,
+            "output": run_output,
+            "used": used,
+        }),
+        exit_code: if passed { 0 } else { 1 },
+        ..Default::default()
+    })
+}
@@ -0,0 +1,58 @@
+use crate::models::Config;
+use crate::modules::utils;
+use std::path::{Path, PathBuf};
+
+// A directory of its own under the system temp dir for one test, removed again once the test is
+// done. Named after the test and the process so tests running in parallel never share one
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("bob-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // Everything bob writes stays inside the directory, the real data dir is never touched
+    pub fn config(&self) -> Config {
+        let downloads_dir = self.0.join("downloads");
+        std::fs::create_dir_all(&downloads_dir).unwrap();
+        Config {
+            downloads_dir: Some(downloads_dir.to_string_lossy().into_owned()),
+            installation_location: Some(self.0.join("neovim").to_string_lossy().into_owned()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Lays out an installed version the way an extracted archive leaves it and returns its directory.
+// Its nvim prints the tag and its arguments, next to it is a file that isn't executable
+pub fn plant_version(config: &Config, tag: &str) -> PathBuf {
+    let source = PathBuf::from(config.downloads_dir.as_ref().unwrap())
+        .join(tag)
+        .join(utils::get_platform_name());
+    let bin_dir = source.join("bin");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    std::fs::create_dir_all(source.join("share")).unwrap();
+    std::fs::write(source.join("share").join("runtime"), tag).unwrap();
+    let binary = bin_dir.join(utils::get_binary_name());
+    std::fs::write(&binary, format!("#!/bin/sh\necho {tag} \"$@\"\n")).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    std::fs::write(bin_dir.join("README"), "not a program").unwrap();
+    source
+}
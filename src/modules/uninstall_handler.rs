@@ -1,24 +1,203 @@
-use crate::{models::Config, modules::utils};
+use crate::{
+    enums::BobError,
+    models::{CommandOutput, Config, InputVersion},
+    modules::{
+        progress,
+        prompt::{self, Choice},
+        state, use_handler, utils,
+    },
+};
 use anyhow::{anyhow, Result};
 use reqwest::Client;
+use serde_json::json;
+use std::path::PathBuf;
 use tokio::fs;
 use tracing::{info, warn};
 
-pub async fn start(version: &str, config: Config) -> Result<()> {
-    let client = Client::new();
-    let version = utils::parse_version_type(&client, version).await?;
+pub async fn start(
+    version: &str,
+    client: &Client,
+    config: Config,
+    force: bool,
+) -> Result<CommandOutput> {
+    let version = utils::parse_version_type(client, version).await?;
 
-    if utils::is_version_used(&version.tag_name, &config).await {
+    if utils::is_version_linked(&version.tag_name, &config).await {
         warn!("Switch to a different version before proceeding");
-        return Ok(());
+        return Ok(CommandOutput {
+            action: String::from("uninstall"),
+            ..Default::default()
+        });
     }
 
-    let downloads_dir = match utils::get_downloads_folder(&config).await {
-        Ok(value) => value,
-        Err(error) => return Err(anyhow!(error)),
-    };
+    if state::is_pinned(&config, &version.tag_name).await? {
+        if !force {
+            return Err(BobError::Usage(format!(
+                "{} is pinned, run `bob unpin {}` or pass --force to uninstall it anyway",
+                version.tag_name, version.tag_name
+            ))
+            .into());
+        }
+        state::set_pinned(&config, &version.tag_name, false).await?;
+    }
 
-    fs::remove_dir_all(&format!("{}/{}", downloads_dir.display(), version.tag_name)).await?;
+    let version_dir = remove_version(&version, &config).await?;
     info!("Successfully uninstalled version: {}", version.tag_name);
-    Ok(())
+    Ok(CommandOutput {
+        action: String::from("uninstall"),
+        versions: vec![version.tag_name],
+        paths: vec![version_dir],
+        ..Default::default()
+    })
+}
+
+// Lists every installed version with its size and when it was last used and removes the ones
+// picked. The active and pinned versions are listed too but stay locked unless allowed
+pub async fn start_interactive(
+    config: Config,
+    allow_active: bool,
+    allow_pinned: bool,
+) -> Result<CommandOutput> {
+    if !prompt::is_interactive() {
+        return Err(BobError::Usage(String::from(
+            "Pass the version to uninstall, picking several from a list needs a terminal",
+        ))
+        .into());
+    }
+
+    // Only directories named like a version can be told apart from the rest of the folder
+    let installed = utils::get_installed_versions(&config)
+        .await?
+        .into_iter()
+        .filter_map(|version| Some((version.version.clone()?, version)))
+        .collect::<Vec<_>>();
+    if installed.is_empty() {
+        return Err(BobError::Usage(String::from("There are no versions installed")).into());
+    }
+    let sizes = progress::with_spinner("Measuring installed versions…", async {
+        let mut sizes = Vec::new();
+        for (_, version) in &installed {
+            sizes.push(utils::get_dir_size(&version.path).await?);
+        }
+        Ok::<_, anyhow::Error>(sizes)
+    })
+    .await?;
+
+    let state = state::load(&config).await?;
+    let find_entry = |name: &str| {
+        state
+            .versions
+            .iter()
+            .find(|(tag, _)| utils::is_same_version(tag, name))
+            .map(|(_, entry)| entry)
+    };
+    let choices = installed
+        .iter()
+        .zip(&sizes)
+        .map(|((_, version), size)| {
+            let entry = find_entry(&version.name);
+            let last_used = match entry.and_then(|entry| entry.last_used) {
+                Some(value) => format!("last used {}", value.format("%Y-%m-%d")),
+                None => String::from("never used"),
+            };
+            let is_pinned = entry.is_some_and(|entry| entry.pinned);
+            Choice {
+                label: format!(
+                    "{:<10} {:>10}  {last_used}",
+                    version.name,
+                    utils::format_size(*size)
+                ),
+                locked: if version.used && !allow_active {
+                    Some(String::from("active, pass --allow-active to pick it"))
+                } else if is_pinned && !allow_pinned {
+                    Some(String::from("pinned, pass --allow-pinned to pick it"))
+                } else {
+                    None
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let picked = prompt::pick_many("Pick the versions to uninstall:", &choices, |selected| {
+        let total = selected.iter().map(|index| sizes[*index]).sum();
+        format!(
+            "{} picked, {} to reclaim",
+            selected.len(),
+            utils::format_size(total)
+        )
+    })?;
+    if picked.is_empty() {
+        info!("Nothing was picked, no versions were uninstalled");
+        return Ok(CommandOutput {
+            action: String::from("uninstall"),
+            ..Default::default()
+        });
+    }
+
+    let total: u64 = picked.iter().map(|index| sizes[*index]).sum();
+    info!(
+        "Uninstalling {} reclaims {}",
+        picked
+            .iter()
+            .map(|index| installed[*index].1.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        utils::format_size(total)
+    );
+    if !prompt::confirm(&prompt::UNINSTALL)? {
+        return Err(anyhow!("Uninstall cancelled, nothing was removed"));
+    }
+
+    let mut removed = Vec::new();
+    let mut paths = Vec::new();
+    let mut removed_active = false;
+    for index in picked {
+        let (version, installed) = &installed[index];
+        let version = InputVersion {
+            tag_name: installed.name.clone(),
+            version: version.clone(),
+        };
+        if find_entry(&version.tag_name).is_some_and(|entry| entry.pinned) {
+            state::set_pinned(&config, &version.tag_name, false).await?;
+        }
+        paths.push(remove_version(&version, &config).await?);
+        removed_active |= installed.used;
+        info!("Successfully uninstalled version: {}", version.tag_name);
+        removed.push(version.tag_name);
+    }
+
+    // Nothing is left for the installation folder to point at
+    if removed_active {
+        use_handler::remove_installation(
+            &utils::get_installation_folder(&config)?,
+            state::get_install_mode(&config).await?.as_deref(),
+        )
+        .await?;
+        state::set_install_mode(&config, None).await?;
+        state::tidy(&config, true).await?;
+        warn!("No version is used anymore, run `bob use <version>` to pick one");
+    }
+
+    Ok(CommandOutput {
+        action: String::from("uninstall"),
+        versions: removed,
+        paths,
+        details: json!({ "reclaimed_bytes": total }),
+        ..Default::default()
+    })
+}
+
+pub async fn remove_version(version: &InputVersion, config: &Config) -> Result<PathBuf> {
+    if let Some(true) = config.low_priority {
+        utils::lower_process_priority();
+    }
+
+    // Broken installs are still removed, they just don't count as installed anywhere else
+    let version_dir = match utils::find_version_dir(&version.tag_name, config).await? {
+        Some(value) => value,
+        None => return Err(BobError::NotInstalled(version.tag_name.clone()).into()),
+    };
+
+    fs::remove_dir_all(&version_dir).await?;
+    Ok(version_dir)
 }
@@ -1,103 +1,543 @@
-use crate::enums::InstallResult;
+use crate::enums::{BobError, InstallResult};
 use crate::models::{Config, InputVersion};
-use crate::modules::{install_handler, utils};
+use crate::modules::{desktop, gui, install_handler, output, progress, state, utils};
 use anyhow::{anyhow, Result};
 use reqwest::Client;
+use std::path::Path;
 use tokio::fs;
-use tracing::info;
+use tracing::{debug, info, warn};
 
 pub async fn start(version: InputVersion, client: &Client, config: Config) -> Result<()> {
-    let is_version_used = utils::is_version_used(&version.tag_name, &config).await;
-    if is_version_used && version.tag_name != "nightly" {
+    run(version, client, config, true).await
+}
+
+// Switches to exactly the build that's installed, without installing it or updating nightly
+// first, e.g. after bob test vouched for it
+pub async fn start_installed(version: InputVersion, client: &Client, config: Config) -> Result<()> {
+    if !utils::is_version_installed(&version.tag_name, &config).await? {
+        return Err(BobError::NotInstalled(version.tag_name).into());
+    }
+    run(version, client, config, false).await
+}
+
+async fn run(version: InputVersion, client: &Client, config: Config, install: bool) -> Result<()> {
+    let version_dir = utils::get_downloads_folder(&config)
+        .await?
+        .join(utils::get_version_dir_name(&version));
+    switch_version(version, client, &config, install).await?;
+    // Both also run when the version was already used, they may have been opted into since
+    gui::ensure_installed(client, &config, &version_dir).await?;
+    if let Some(true) = config.install_desktop_entry {
+        desktop::install_entry(&config).await?;
+    }
+    Ok(())
+}
+
+async fn switch_version(
+    version: InputVersion,
+    client: &Client,
+    config: &Config,
+    install: bool,
+) -> Result<()> {
+    let is_version_used = utils::is_version_linked(&version.tag_name, config).await;
+    if is_version_used && (version.tag_name != "nightly" || !install) {
         info!("{} is already installed and used!", version.tag_name);
         return Ok(());
     }
 
-    match install_handler::start(&version, client, &config).await {
-        Ok(success) => {
-            if let InstallResult::NightlyIsUpdated = success {
-                if is_version_used {
-                    info!("Nightly is already updated and used!");
-                    return Ok(());
+    if install {
+        match install_handler::start(&version, client, config).await {
+            Ok(success) => {
+                if let InstallResult::NightlyIsUpdated = success {
+                    if is_version_used {
+                        info!("Nightly is already updated and used!");
+                        return Ok(());
+                    }
                 }
             }
+            Err(error) => return Err(error),
+        }
+    }
+
+    let version_dir = utils::get_downloads_folder(config)
+        .await?
+        .join(utils::get_version_dir_name(&version));
+    let mode = link_version(&version_dir, config, is_version_used).await?;
+    state::set_used(config, &version.tag_name).await?;
+    state::set_install_mode(config, Some(mode)).await?;
+    progress::emit("finalize", 1, 1);
+    info!("You can now use {}!", version.tag_name);
+
+    if let Some(shadowing) = utils::find_shadowing_nvim(config)? {
+        warn!(
+            "{} comes before {} in PATH, running nvim won't use the version managed by bob",
+            shadowing.display(),
+            utils::get_used_binary_path(config)?.display()
+        );
+    }
+
+    Ok(())
+}
+
+// Runs the command with the version first in PATH and BOB_NVIM_VERSION naming it, nothing is
+// linked and the state isn't written, so however the command ends the used version stays as it was
+pub async fn run_ephemeral(
+    version: &InputVersion,
+    client: &Client,
+    config: &Config,
+    command: &[String],
+) -> Result<i32> {
+    let (program, args) = match command.split_first() {
+        Some(value) => value,
+        None => {
+            return Err(BobError::Usage(String::from(
+                "Give the command to run after --, e.g. bob use --ephemeral nightly -- nvim",
+            ))
+            .into())
         }
-        Err(error) => return Err(error),
+    };
+    if !utils::is_version_installed(&version.tag_name, config).await? {
+        install_handler::start(version, client, config).await?;
     }
 
-    std::env::set_current_dir(utils::get_downloads_folder(&config).await?)?;
+    let version_dir = utils::get_downloads_folder(config)
+        .await?
+        .join(utils::get_version_dir_name(version));
+    let platform_dir = if cfg!(windows) && fs::metadata(version_dir.join("Neovim")).await.is_ok() {
+        "Neovim"
+    } else {
+        utils::get_platform_name()
+    };
+    let mut paths = vec![version_dir.join(platform_dir).join("bin")];
+    if let Some(path) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&path));
+    }
 
-    let version_link = match version.version_type {
-        crate::enums::VersionType::Standard => &version.tag_name,
-        crate::enums::VersionType::Hash => &version.tag_name[0..7],
+    debug!("Running `{}` with {}", command.join(" "), version.tag_name);
+    let mut child = match tokio::process::Command::new(program)
+        .args(args)
+        .env("PATH", std::env::join_paths(paths)?)
+        .env(state::VERSION_ENV_VAR, &version.tag_name)
+        .stdout(output::child_stdout())
+        .spawn()
+    {
+        Ok(value) => value,
+        Err(error) => return Err(anyhow!("Failed to run {program}, reason: {error}")),
     };
 
-    link_version(version_link, &config, is_version_used).await?;
-    fs::write("used", &version.tag_name).await?;
-    info!("You can now use {}!", version.tag_name);
+    // Ctrl-C reaches the command too, bob waits for it to exit rather than going first
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status?,
+            _ = tokio::signal::ctrl_c() => continue,
+        }
+    };
+    Ok(status.code().unwrap_or(1))
+}
 
-    Ok(())
+// Points the installation folder at the version again without switching to it, for when its
+// files were replaced underneath the link
+pub async fn relink(version: &InputVersion, config: &Config) -> Result<()> {
+    let version_dir = utils::get_downloads_folder(config)
+        .await?
+        .join(utils::get_version_dir_name(version));
+    let mode = link_version(&version_dir, config, true).await?;
+    state::set_install_mode(config, Some(mode)).await
 }
 
-async fn link_version(version: &str, config: &Config, is_version_used: bool) -> Result<()> {
+// base_path is the version's absolute path inside the downloads folder, returns the strategy
+// that was used in the end
+async fn link_version(
+    base_path: &Path,
+    config: &Config,
+    is_version_used: bool,
+) -> Result<&'static str> {
     let installation_dir = match utils::get_installation_folder(config) {
         Err(_) => return Err(anyhow!("Couldn't get data dir")),
         Ok(value) => value,
     };
 
-    let current_path = std::env::current_dir()?;
-    let base_path = &format!("{}/{}", current_path.display(), version);
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            let base_dir = if fs::metadata(base_path.join("Neovim")).await.is_ok() {
+                "Neovim"
+            } else {
+                "nvim-win64"
+            };
+        } else {
+            // Versions installed before bob normalized archive layouts may still use the old name
+            if fs::metadata(base_path.join("nvim-osx64")).await.is_ok() {
+                fs::rename(base_path.join("nvim-osx64"), base_path.join("nvim-macos")).await?;
+            }
+            let base_dir = utils::get_platform_name();
+        }
+    }
+    let source = base_path.join(base_dir);
+    let strategy = get_use_strategy(config);
+    debug!(
+        "Switching {} to {} with {strategy}",
+        installation_dir.display(),
+        source.display()
+    );
+
+    let previous_mode = state::get_install_mode(config).await?;
+    remove_installation(&installation_dir, previous_mode.as_deref()).await?;
+    let result = match strategy {
+        "copy" => {
+            let target = installation_dir.clone();
+            tokio::task::spawn_blocking(move || utils::copy_dir(&source, &target))
+                .await?
+                .map(|_| "copy")
+        }
+        "shim" => write_shims(&source, &installation_dir)
+            .await
+            .map(|_| "shim"),
+        _ => link_or_shim(&source, &installation_dir, config).await,
+    };
+    // Half a copy would pass for a working installation
+    let mode = match result {
+        Ok(value) => value,
+        Err(error) => {
+            remove_installation(&installation_dir, None).await?;
+            state::set_install_mode(config, None).await?;
+            return Err(error);
+        }
+    };
 
-    if fs::metadata(&installation_dir).await.is_ok() {
-        fs::remove_dir_all(&installation_dir).await?;
+    if !is_version_used {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                utils::add_to_user_path(&installation_dir.join("bin"))?;
+            } else {
+                info!("Make sure to have {}/bin in PATH, `bob env --apply` sets it up for your shell", installation_dir.display());
+            }
+        }
     }
+    Ok(mode)
+}
+
+pub const USE_STRATEGIES: [&str; 3] = ["symlink", "copy", "shim"];
 
+// Creating symlinks on Windows takes admin rights or developer mode, shims work for everyone
+pub fn get_default_use_strategy() -> &'static str {
     cfg_if::cfg_if! {
         if #[cfg(windows)] {
-           use std::os::windows::fs::symlink_dir;
-            use winreg::RegKey;
+            "shim"
+        } else {
+            "symlink"
+        }
+    }
+}
 
+pub fn get_use_strategy(config: &Config) -> &str {
+    match config.use_strategy.as_deref() {
+        Some(value) if USE_STRATEGIES.contains(&value) => value,
+        _ => get_default_use_strategy(),
+    }
+}
 
-            let base_dir = if fs::metadata(&format!("{base_path}/Neovim")).await.is_ok() {
-                "Neovim"
-            } else {
-                "nvim-win64"
-            };
+// Whatever the previous strategy left there goes, a link, a copied tree or a folder of shims,
+// a link is removed without following it. install_mode is what state.json says bob put there,
+// None when that isn't known
+pub async fn remove_installation(
+    installation_dir: &Path,
+    install_mode: Option<&str>,
+) -> Result<()> {
+    let metadata = match fs::symlink_metadata(installation_dir).await {
+        Ok(value) => value,
+        Err(_) => return Ok(()),
+    };
+    // A real folder where bob left a link was put there by someone else
+    if install_mode == Some("symlink") && !metadata.file_type().is_symlink() {
+        return Err(anyhow!(
+            "{} was replaced since bob linked it, move it out of the way and run `bob use` again",
+            installation_dir.display()
+        ));
+    }
 
-            if symlink_dir(format!("{base_path}/{base_dir}"),
-               &installation_dir).is_err() {
-                   return Err(anyhow!("Please restart this application as admin to complete the installation."));
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            if metadata.file_type().is_symlink() {
+                utils::retry_on_lock_async(installation_dir, || std::fs::remove_dir(installation_dir)).await?;
+            } else {
+                utils::retry_on_lock_async(installation_dir, || std::fs::remove_dir_all(installation_dir)).await?;
             }
         } else {
-            use std::os::unix::fs::symlink;
-            if fs::metadata(format!("{base_path}/nvim-osx64")).await.is_ok() {
-                fs::rename(format!("{base_path}/nvim-osx64"), "nvim-macos").await?;
+            if metadata.file_type().is_symlink() {
+                fs::remove_file(installation_dir).await?;
+            } else {
+                fs::remove_dir_all(installation_dir).await?;
             }
-            let folder_name = utils::get_platform_name();
-            if let Err(error) = symlink(format!("{base_path}/{folder_name}"), &installation_dir) {
-                return Err(anyhow!(error))
+        }
+    }
+    Ok(())
+}
+
+// When use_strategy asks for symlink but the link can't be created, on Windows without admin
+// rights or developer mode, shims stand in for it so bob use still works. A failing default
+// link is an error like any other
+async fn link_or_shim(
+    source: &Path,
+    installation_dir: &Path,
+    config: &Config,
+) -> Result<&'static str> {
+    match create_link(source, installation_dir) {
+        Ok(()) => Ok("symlink"),
+        Err(error) if config.use_strategy.as_deref() == Some("symlink") => {
+            warn!(
+                "Couldn't link {} ({error}), writing shims instead",
+                installation_dir.display()
+            );
+            write_shims(source, installation_dir).await?;
+            Ok("shim")
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn create_link(source: &Path, installation_dir: &Path) -> Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            if std::os::windows::fs::symlink_dir(source, installation_dir).is_err() {
+                return Err(anyhow!("Please restart this application as admin to complete the installation, or set use_strategy to shim"));
             }
+        } else {
+            std::os::unix::fs::symlink(source, installation_dir)?;
         }
     }
+    Ok(())
+}
+
+// Every executable in the version's bin folder gets a script of the same name that hands its
+// arguments to it, nvim finds its runtime files next to the real binary
+async fn write_shims(source: &Path, installation_dir: &Path) -> Result<()> {
+    let bin_dir = installation_dir.join("bin");
+    fs::create_dir_all(&bin_dir).await?;
+
+    let mut entries = fs::read_dir(source.join("bin")).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let target = entry.path();
+        if !fs::metadata(&target)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            continue;
+        }
 
-    if !is_version_used {
         cfg_if::cfg_if! {
             if #[cfg(windows)] {
-                use winreg::enums::*;
-
-                let current_usr = RegKey::predef(HKEY_CURRENT_USER);
-                let env = current_usr.open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)?;
-                let usr_path: String = env.get_value("Path")?;
-                let new_path = if usr_path.ends_with(';') {
-                    format!("{usr_path}{}\\bin", installation_dir.display())
-                } else {
-                    format!("{usr_path};{}\\bin", installation_dir.display())
+                let is_executable = target
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("exe"));
+                let name = match target.file_stem() {
+                    Some(value) if is_executable => value.to_string_lossy(),
+                    _ => continue,
                 };
-                env.set_value("Path", &new_path)?;
+                let shim = bin_dir.join(format!("{name}.cmd"));
+                fs::write(&shim, format!("@echo off\r\n\"{}\" %*\r\n", target.display())).await?;
             } else {
-                info!("Make sure to have {}/bin in PATH", installation_dir.display());
+                use std::os::unix::fs::PermissionsExt;
+
+                let mode = fs::metadata(&target).await?.permissions().mode();
+                if mode & 0o111 == 0 {
+                    continue;
+                }
+                let quoted = target.to_string_lossy().replace('\'', "'\\''");
+                let shim = bin_dir.join(entry.file_name());
+                fs::write(&shim, format!("#!/bin/sh\nexec '{quoted}' \"$@\"\n")).await?;
+                fs::set_permissions(&shim, std::fs::Permissions::from_mode(0o755)).await?;
             }
         }
+        debug!("Wrote {} for {}", shim.display(), target.display());
     }
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::enums::Version;
+    use crate::modules::test_support::{plant_version, TestDir};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    fn input_version(tag: &str) -> InputVersion {
+        InputVersion {
+            tag_name: tag.to_string(),
+            version: Version::parse(tag).unwrap(),
+        }
+    }
+
+    fn with_strategy(config: &Config, strategy: Option<&str>) -> Config {
+        Config {
+            use_strategy: strategy.map(String::from),
+            ..config.clone()
+        }
+    }
+
+    fn run_nvim(installation_dir: &Path, argument: &str) -> String {
+        let output = std::process::Command::new(installation_dir.join("bin").join("nvim"))
+            .arg(argument)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn every_strategy_links_and_cleans_up() {
+        let dir = TestDir::new("use-strategies");
+        let source = plant_version(&dir.config(), "v0.9.5");
+        let installation_dir = utils::get_installation_folder(&dir.config()).unwrap();
+
+        for strategy in USE_STRATEGIES {
+            let config = with_strategy(&dir.config(), Some(strategy));
+            relink(&input_version("v0.9.5"), &config).await.unwrap();
+            let mode = state::get_install_mode(&config).await.unwrap();
+            assert_eq!(mode.as_deref(), Some(strategy));
+
+            let metadata = std::fs::symlink_metadata(&installation_dir).unwrap();
+            assert_eq!(metadata.file_type().is_symlink(), strategy == "symlink");
+            assert_eq!(run_nvim(&installation_dir, "--version"), "v0.9.5 --version");
+            match strategy {
+                "symlink" => assert_eq!(std::fs::read_link(&installation_dir).unwrap(), source),
+                "copy" => assert!(installation_dir.join("share").join("runtime").is_file()),
+                _ => {
+                    let shim =
+                        std::fs::read_to_string(installation_dir.join("bin").join("nvim")).unwrap();
+                    assert!(shim.contains(&source.join("bin").join("nvim").display().to_string()));
+                    assert!(!installation_dir.join("bin").join("README").exists());
+                    assert!(!installation_dir.join("share").exists());
+                }
+            }
+
+            remove_installation(&installation_dir, mode.as_deref())
+                .await
+                .unwrap();
+            assert!(std::fs::symlink_metadata(&installation_dir).is_err());
+            assert!(source.join("bin").join("nvim").is_file(), "{strategy}");
+        }
+    }
+
+    #[tokio::test]
+    async fn switching_strategies_replaces_what_was_there() {
+        let dir = TestDir::new("use-strategy-switch");
+        plant_version(&dir.config(), "v0.9.4");
+        let source = plant_version(&dir.config(), "v0.9.5");
+        let installation_dir = utils::get_installation_folder(&dir.config()).unwrap();
+
+        for (strategy, tag) in [
+            ("copy", "v0.9.4"),
+            ("shim", "v0.9.5"),
+            ("symlink", "v0.9.4"),
+            ("copy", "v0.9.5"),
+            ("symlink", "v0.9.5"),
+        ] {
+            let config = with_strategy(&dir.config(), Some(strategy));
+            relink(&input_version(tag), &config).await.unwrap();
+            assert_eq!(run_nvim(&installation_dir, "-v"), format!("{tag} -v"));
+        }
+        // Nothing the copy left behind ended up in the version the link points at
+        assert_eq!(std::fs::read_link(&installation_dir).unwrap(), source);
+        assert_eq!(std::fs::read_dir(source.join("bin")).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn symlinks_that_cant_be_created_fall_back_to_shims() {
+        let dir = TestDir::new("use-strategy-fallback");
+        // The link's parent doesn't exist, write_shims creates it
+        let config = Config {
+            installation_location: Some(
+                dir.path()
+                    .join("missing")
+                    .join("neovim")
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            ..dir.config()
+        };
+        plant_version(&config, "v0.9.5");
+        let installation_dir = utils::get_installation_folder(&config).unwrap();
+
+        // The default link fails like any other strategy would
+        let error = relink(&input_version("v0.9.5"), &config)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("No such file"), "{error}");
+        assert_eq!(state::get_install_mode(&config).await.unwrap(), None);
+
+        relink(
+            &input_version("v0.9.5"),
+            &with_strategy(&config, Some("symlink")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            state::get_install_mode(&config).await.unwrap().as_deref(),
+            Some("shim")
+        );
+        assert_eq!(run_nvim(&installation_dir, "-v"), "v0.9.5 -v");
+    }
+
+    #[tokio::test]
+    async fn failed_strategies_leave_nothing_behind() {
+        let dir = TestDir::new("use-strategy-failed");
+        let installation_dir = utils::get_installation_folder(&dir.config()).unwrap();
+        // A version folder without the platform folder inside
+        std::fs::create_dir_all(PathBuf::from(dir.config().downloads_dir.unwrap()).join("v0.9.5"))
+            .unwrap();
+
+        for strategy in ["copy", "shim"] {
+            let config = with_strategy(&dir.config(), Some(strategy));
+            assert!(relink(&input_version("v0.9.5"), &config).await.is_err());
+            assert!(std::fs::symlink_metadata(&installation_dir).is_err());
+            assert_eq!(state::get_install_mode(&config).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn real_folders_in_place_of_the_link_are_kept() {
+        let dir = TestDir::new("use-replaced-link");
+        let installation_dir = dir.path().join("neovim");
+        std::fs::create_dir_all(installation_dir.join("bin")).unwrap();
+        std::fs::write(installation_dir.join("bin").join("nvim"), "mine").unwrap();
+
+        let error = remove_installation(&installation_dir, Some("symlink"))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("was replaced"), "{error}");
+        assert!(installation_dir.join("bin").join("nvim").is_file());
+
+        remove_installation(&installation_dir, Some("copy"))
+            .await
+            .unwrap();
+        assert!(!installation_dir.exists());
+        // Nothing there is nothing to remove
+        remove_installation(&installation_dir, Some("symlink"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn shims_quote_their_target() {
+        let dir = TestDir::new("use-shim-quotes");
+        let source = dir.path().join("it's a version");
+        std::fs::create_dir_all(source.join("bin")).unwrap();
+        std::fs::write(source.join("bin").join("nvim"), "#!/bin/sh\necho \"$@\"\n").unwrap();
+        std::fs::set_permissions(
+            source.join("bin").join("nvim"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        let installation_dir = dir.path().join("neovim");
+
+        write_shims(&source, &installation_dir).await.unwrap();
+        assert_eq!(run_nvim(&installation_dir, "a b"), "a b");
+        let mode = std::fs::metadata(installation_dir.join("bin").join("nvim"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+}
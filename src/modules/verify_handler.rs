@@ -0,0 +1,194 @@
+use super::manifest::{self, Verification};
+use super::{install_handler, output, progress, prompt, use_handler, utils};
+use crate::enums::{BobError, Version};
+use crate::models::{CommandOutput, Config, InputVersion};
+use anyhow::Result;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{info, warn};
+use yansi::Paint;
+
+const VERIFY_FAILED_EXIT_CODE: i32 = 12;
+
+// Files are compared against the manifest recorded when the version was installed. A version
+// whose nvim binary is gone still counts here as long as its manifest is left, that's exactly
+// what verify should report
+pub async fn start(
+    client: &Client,
+    config: Config,
+    version: Option<InputVersion>,
+    repair: bool,
+) -> Result<CommandOutput> {
+    let targets = match version {
+        Some(version) => match utils::find_version_dir(&version.tag_name, &config).await? {
+            Some(value) => vec![(version, value)],
+            None => return Err(BobError::NotInstalled(version.tag_name).into()),
+        },
+        None => get_verifiable_versions(&config).await?,
+    };
+    if targets.is_empty() {
+        return Err(BobError::Usage(String::from("There are no versions installed")).into());
+    }
+
+    let mut failed = Vec::new();
+    let mut results = Vec::new();
+    for (version, version_dir) in &targets {
+        let verification =
+            progress::with_spinner("Hashing installed files…", manifest::verify(version_dir))
+                .await?;
+        print_verification(&version.tag_name, &verification);
+        if verification.is_failed() {
+            failed.push(version);
+        }
+        results.push(describe(&version.tag_name, &verification));
+    }
+
+    let mut repaired = Vec::new();
+    if repair && !failed.is_empty() && prompt::confirm(&prompt::REPAIR)? {
+        for version in &failed {
+            if let Version::Nightly = version.version {
+                info!("Reinstalling nightly replaces it with the latest nightly");
+            }
+            install_handler::reinstall(version, client, &config).await?;
+            if utils::is_version_linked(&version.tag_name, &config).await {
+                use_handler::relink(version, &config).await?;
+            }
+            info!("Reinstalled {}", version.tag_name);
+            repaired.push(version.tag_name.clone());
+        }
+    } else if !failed.is_empty() {
+        warn!("Run `bob verify <version> --repair` to reinstall a version that failed");
+    }
+
+    let still_failed = failed
+        .iter()
+        .any(|version| !repaired.contains(&version.tag_name));
+    Ok(CommandOutput {
+        action: String::from("verify"),
+        paths: targets.iter().map(|(_, path)| path.clone()).collect(),
+        versions: targets
+            .into_iter()
+            .map(|(version, _)| version.tag_name)
+            .collect(),
+        details: json!({
+            "results": results,
+            "repaired": repaired,
+        }),
+        exit_code: if still_failed {
+            VERIFY_FAILED_EXIT_CODE
+        } else {
+            0
+        },
+        ..Default::default()
+    })
+}
+
+async fn get_verifiable_versions(config: &Config) -> Result<Vec<(InputVersion, PathBuf)>> {
+    let downloads_dir = utils::get_downloads_folder(config).await?;
+    let mut entries = fs::read_dir(&downloads_dir).await?;
+
+    let mut versions = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = match entry.file_name().to_str() {
+            Some(value) => value.to_owned(),
+            None => continue,
+        };
+        let version = match Version::parse(&name) {
+            Some(value) if entry.file_type().await?.is_dir() => value,
+            _ => continue,
+        };
+        let has_manifest = fs::metadata(entry.path().join(utils::MANIFEST_FILE))
+            .await
+            .is_ok();
+        if !has_manifest && !utils::is_version_installed(&name, config).await? {
+            continue;
+        }
+        versions.push((
+            InputVersion {
+                tag_name: name,
+                version,
+            },
+            entry.path(),
+        ));
+    }
+    versions.sort_by(|(a, _), (b, _)| {
+        a.version
+            .cmp(&b.version)
+            .then_with(|| a.tag_name.cmp(&b.tag_name))
+    });
+    Ok(versions)
+}
+
+fn print_verification(tag_name: &str, verification: &Verification) {
+    match verification {
+        Verification::Unverifiable(reason) => output::print_human(format!(
+            "[{}] {tag_name}: {reason}",
+            Paint::yellow("unverifiable")
+        )),
+        Verification::Checked {
+            files,
+            revalidated,
+            cached,
+            ..
+        } if !verification.is_failed() => output::print_human(format!(
+            "[{}] {tag_name}: all {files} files match {}",
+            Paint::green("ok"),
+            Paint::new(format!("({revalidated} hashed, {cached} cached)")).dimmed()
+        )),
+        Verification::Checked {
+            missing,
+            modified,
+            extra,
+            ..
+        } => {
+            output::print_human(format!(
+                "[{}] {tag_name}: {} missing, {} modified, {} extra",
+                Paint::red("fail"),
+                missing.len(),
+                modified.len(),
+                extra.len()
+            ));
+            for (label, paths) in [
+                ("missing", missing),
+                ("modified", modified),
+                ("extra", extra),
+            ] {
+                for path in paths {
+                    output::print_human(format!(
+                        "       {}",
+                        Paint::new(format!("{label}: {path}")).dimmed()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn describe(tag_name: &str, verification: &Verification) -> Value {
+    match verification {
+        Verification::Unverifiable(reason) => json!({
+            "version": tag_name,
+            "status": "unverifiable",
+            "reason": reason,
+        }),
+        Verification::Checked {
+            files,
+            missing,
+            modified,
+            extra,
+            revalidated,
+            cached,
+        } => json!({
+            "version": tag_name,
+            "status": if verification.is_failed() { "failed" } else { "ok" },
+            "files": files,
+            "missing": missing,
+            "modified": modified,
+            "extra": extra,
+            "revalidated": revalidated,
+            "cached": cached,
+        }),
+    }
+}
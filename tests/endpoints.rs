@@ -0,0 +1,160 @@
+// Points github_api_url and github_download_url at a server on localhost, so resolving and
+// installing run end to end without GitHub
+#![cfg(unix)]
+
+use bob_core::{Config, InstallResult};
+use flate2::{write::GzEncoder, Compression};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+type Route = fn(&str) -> Option<(&'static str, Vec<u8>)>;
+
+struct Server {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+// Answers every connection once and closes it, GET requests have no body to read past the
+// headers
+async fn serve(route: Route) -> Server {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(value) => value,
+                Err(_) => return,
+            };
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match stream.read(&mut buffer).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or("/").to_owned();
+            seen.lock().unwrap().push(path.clone());
+
+            let (status, content_type, body) = match route(&path) {
+                Some((content_type, body)) => ("200 OK", content_type, body),
+                None => (
+                    "404 Not Found",
+                    "application/json",
+                    br#"{"message":"Not Found"}"#.to_vec(),
+                ),
+            };
+            let head = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    Server { url, requests }
+}
+
+fn build_archive() -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+    for (path, content, mode) in [
+        (
+            "nvim-linux64/bin/nvim",
+            "#!/bin/sh\necho 'NVIM v0.9.5'\n",
+            0o755,
+        ),
+        ("nvim-linux64/share/nvim/runtime/doc/tags", "help\n", 0o644),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+fn route(path: &str) -> Option<(&'static str, Vec<u8>)> {
+    if path.ends_with("/releases/latest") {
+        let release = include_str!("fixtures/release.json");
+        return Some(("application/json", release.as_bytes().to_vec()));
+    }
+    if path.contains("/releases/download/v0.9.5/") && path.ends_with(".tar.gz") {
+        return Some(("application/gzip", build_archive()));
+    }
+    None
+}
+
+struct TestDir(PathBuf);
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Endpoints are picked up for the whole process by create_client, so everything against the
+// server happens in this one test
+#[tokio::test]
+async fn endpoints_serve_releases_and_downloads() {
+    let server = serve(route).await;
+    let dir = TestDir(std::env::temp_dir().join(format!("bob-it-{}", std::process::id())));
+    let downloads_dir = dir.0.join("downloads");
+    std::fs::create_dir_all(&downloads_dir).unwrap();
+    let config = Config {
+        downloads_dir: Some(downloads_dir.to_string_lossy().into_owned()),
+        installation_location: Some(dir.0.join("nvim-bin").to_string_lossy().into_owned()),
+        github_api_url: Some(server.url.clone()),
+        github_download_url: Some(server.url.clone()),
+        ..Default::default()
+    };
+
+    let client = bob_core::create_client(&config).await.unwrap();
+    let version = bob_core::resolve(&client, "stable").await.unwrap();
+    assert_eq!(version.tag_name, "v0.9.5");
+
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let seen = phases.clone();
+    let result = bob_core::install(&client, &config, &version, move |event| {
+        seen.lock().unwrap().push(event.phase.to_owned());
+    })
+    .await
+    .unwrap();
+    assert!(matches!(result, InstallResult::InstallationSuccess(_)));
+    assert!(phases
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|phase| phase == "download"));
+
+    let installed = bob_core::list(&config).await.unwrap();
+    assert_eq!(
+        installed
+            .iter()
+            .map(|version| version.name.as_str())
+            .collect::<Vec<_>>(),
+        ["v0.9.5"]
+    );
+    let leftovers = std::fs::read_dir(&downloads_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".tar.gz") || name.ends_with(".download"))
+        .collect::<Vec<_>>();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests
+        .iter()
+        .any(|path| path == "/repos/neovim/neovim/releases/latest"));
+    assert!(requests
+        .iter()
+        .any(|path| path.starts_with("/neovim/neovim/releases/download/v0.9.5/")));
+}